@@ -0,0 +1,76 @@
+use bytes::BytesMut;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use simple_redis::{
+    BulkString, RespArray, RespDecode, RespEncode, RespError, RespFrame, RespFrameDecoder,
+};
+
+// size of a socket read
+const CHUNK: usize = 4096;
+
+// an HSET with `fields` fields of `value_len` bytes each
+fn hset(fields: usize, value_len: usize) -> Vec<u8> {
+    let mut args: Vec<RespFrame> = vec![b"hset".into(), b"key".into()];
+    for i in 0..fields {
+        args.push(BulkString::new(format!("field{}", i)).into());
+        args.push(BulkString::new(vec![b'x'; value_len]).into());
+    }
+    RespArray::new(args).encode()
+}
+
+// decode as the old codec did, retrying the whole frame after every read
+fn decode_frame(data: &[u8], chunk: usize) -> RespFrame {
+    let mut buf = BytesMut::new();
+    for part in data.chunks(chunk) {
+        buf.extend_from_slice(part);
+        match RespFrame::decode(&mut buf) {
+            Ok(frame) => return frame,
+            Err(RespError::NotComplete) => continue,
+            Err(e) => panic!("{}", e),
+        }
+    }
+    panic!("incomplete frame");
+}
+
+fn decode_stream(data: &[u8], chunk: usize) -> RespFrame {
+    let mut decoder = RespFrameDecoder::new();
+    let mut buf = BytesMut::new();
+    for part in data.chunks(chunk) {
+        buf.extend_from_slice(part);
+        if let Some(frame) = decoder.decode(&mut buf).unwrap() {
+            return frame;
+        }
+    }
+    panic!("incomplete frame");
+}
+
+fn bench_decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode");
+    for (fields, value_len) in [(10, 16), (1000, 16), (100, 64 * 1024)] {
+        let data = hset(fields, value_len);
+        let name = format!("{}x{}", fields, value_len);
+        group.throughput(Throughput::Bytes(data.len() as u64));
+
+        group.bench_with_input(BenchmarkId::new("frame/whole", &name), &data, |b, data| {
+            b.iter(|| decode_frame(data, data.len()))
+        });
+        group.bench_with_input(
+            BenchmarkId::new("decoder/whole", &name),
+            &data,
+            |b, data| b.iter(|| decode_stream(data, data.len())),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("frame/chunked", &name),
+            &data,
+            |b, data| b.iter(|| decode_frame(data, CHUNK)),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("decoder/chunked", &name),
+            &data,
+            |b, data| b.iter(|| decode_stream(data, CHUNK)),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_decode);
+criterion_main!(benches);
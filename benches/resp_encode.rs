@@ -0,0 +1,41 @@
+use bytes::BytesMut;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use simple_redis::{BulkString, RespArray, RespEncode, RespFrame};
+
+// an HGETALL like reply: field, value pairs
+fn reply(fields: usize) -> RespFrame {
+    let frames = (0..fields)
+        .flat_map(|i| {
+            [
+                BulkString::new(format!("field{}", i)).into(),
+                BulkString::new(vec![b'x'; 16]).into(),
+            ]
+        })
+        .collect::<Vec<RespFrame>>();
+    RespArray::new(frames).into()
+}
+
+fn bench_encode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("encode");
+    for fields in [10, 10_000] {
+        let frame = reply(fields);
+        group.bench_with_input(BenchmarkId::new("vec", fields), &frame, |b, frame| {
+            b.iter(|| {
+                let mut buf = BytesMut::new();
+                buf.extend_from_slice(&frame.clone().encode());
+                buf
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("encode_to", fields), &frame, |b, frame| {
+            b.iter(|| {
+                let mut buf = BytesMut::new();
+                frame.clone().encode_to(&mut buf);
+                buf
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_encode);
+criterion_main!(benches);
@@ -0,0 +1,18 @@
+#![no_main]
+
+use bytes::BytesMut;
+use libfuzzer_sys::fuzz_target;
+use simple_redis::{RespArray, RespDecode, RespFrame, RespFrameDecoder};
+
+// any input must decode to frames or fail with an error, never panic
+fuzz_target!(|data: &[u8]| {
+    let mut buf = BytesMut::from(data);
+    while let Ok(_frame) = RespFrame::decode(&mut buf) {}
+
+    let mut decoder = RespFrameDecoder::new();
+    let mut buf = BytesMut::from(data);
+    while let Ok(Some(_frame)) = decoder.decode(&mut buf) {}
+
+    let mut buf = BytesMut::from(data);
+    while let Ok(_frame) = RespArray::decode_inline(&mut buf) {}
+});
@@ -0,0 +1,157 @@
+use std::sync::{
+    atomic::{AtomicU8, AtomicUsize, Ordering},
+    Arc, Mutex,
+};
+
+use thiserror::Error;
+
+use super::{Backend, BackendInner};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    Resp2,
+    Resp3,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ClientError {
+    #[error("NOPROTO unsupported protocol version")]
+    NoProto,
+    #[error("WRONGPASS invalid username-password pair or user is disabled.")]
+    WrongPass,
+    #[error("ERR Client names cannot contain spaces, newlines or special characters.")]
+    InvalidName,
+    #[error("ERR max number of clients reached")]
+    MaxClients,
+}
+
+/// A connection counted in the connected clients until it is dropped.
+#[derive(Debug)]
+pub struct ClientSlot {
+    inner: Arc<BackendInner>,
+}
+
+/// Per connection state.
+#[derive(Debug)]
+pub(super) struct Session {
+    id: u64,
+    db: AtomicUsize,
+    protocol: AtomicU8,
+    name: Mutex<Option<String>>,
+}
+
+impl Protocol {
+    pub fn from_version(version: i64) -> Result<Self, ClientError> {
+        match version {
+            2 => Ok(Protocol::Resp2),
+            3 => Ok(Protocol::Resp3),
+            _ => Err(ClientError::NoProto),
+        }
+    }
+
+    pub fn version(&self) -> i64 {
+        match self {
+            Protocol::Resp2 => 2,
+            Protocol::Resp3 => 3,
+        }
+    }
+}
+
+impl Session {
+    pub(super) fn new(id: u64) -> Self {
+        Session {
+            id,
+            db: AtomicUsize::new(0),
+            protocol: AtomicU8::new(2),
+            name: Mutex::new(None),
+        }
+    }
+
+    pub(super) fn db(&self) -> usize {
+        self.db.load(Ordering::Relaxed)
+    }
+
+    pub(super) fn select(&self, index: usize) {
+        self.db.store(index, Ordering::Relaxed);
+    }
+}
+
+impl Backend {
+    /// A handle for a new client connection, starting on db 0 with RESP2.
+    pub fn connect(&self) -> Backend {
+        let id = self.inner.next_client_id.fetch_add(1, Ordering::Relaxed);
+        self.inner
+            .stats
+            .total_connections_received
+            .fetch_add(1, Ordering::Relaxed);
+        Backend {
+            inner: self.inner.clone(),
+            session: Arc::new(Session::new(id)),
+            index: 0,
+            db: self.slot(0),
+        }
+    }
+
+    /// Count a new connection in the connected clients, refused when there
+    /// are already maxclients.
+    pub fn register_client(&self) -> Result<ClientSlot, ClientError> {
+        let maxclients = self.inner.config.borrow().maxclients;
+        let connected = self.inner.connected_clients.fetch_add(1, Ordering::Relaxed);
+        // a refused connection is uncounted when the slot is dropped
+        let slot = ClientSlot {
+            inner: self.inner.clone(),
+        };
+        if connected >= maxclients {
+            return Err(ClientError::MaxClients);
+        }
+        Ok(slot)
+    }
+
+    pub fn connected_clients(&self) -> usize {
+        self.inner.connected_clients.load(Ordering::Relaxed)
+    }
+
+    pub fn client_id(&self) -> u64 {
+        self.session.id
+    }
+
+    pub fn protocol(&self) -> Protocol {
+        match self.session.protocol.load(Ordering::Relaxed) {
+            3 => Protocol::Resp3,
+            _ => Protocol::Resp2,
+        }
+    }
+
+    pub fn set_protocol(&self, protocol: Protocol) {
+        self.session
+            .protocol
+            .store(protocol.version() as u8, Ordering::Relaxed);
+    }
+
+    pub fn client_name(&self) -> Option<String> {
+        self.session.name.lock().unwrap().clone()
+    }
+
+    pub fn set_client_name(&self, name: String) -> Result<(), ClientError> {
+        if name.bytes().any(|c| !(b'!'..=b'~').contains(&c)) {
+            return Err(ClientError::InvalidName);
+        }
+        *self.session.name.lock().unwrap() = (!name.is_empty()).then_some(name);
+        Ok(())
+    }
+
+    /// There are no ACL users, only `default` which accepts any password.
+    pub fn authenticate(&self, user: &str, _password: &str) -> Result<(), ClientError> {
+        if user == "default" {
+            Ok(())
+        } else {
+            Err(ClientError::WrongPass)
+        }
+    }
+}
+
+impl Drop for ClientSlot {
+    fn drop(&mut self) {
+        self.inner.connected_clients.fetch_sub(1, Ordering::Relaxed);
+    }
+}
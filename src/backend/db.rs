@@ -0,0 +1,347 @@
+use std::sync::Arc;
+
+use dashmap::{
+    mapref::{entry::Entry, one::RefMut},
+    DashMap,
+};
+use thiserror::Error;
+
+use super::{Backend, Db};
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum DbError {
+    #[error("ERR DB index is out of range")]
+    OutOfRange,
+    #[error("ERR source and destination objects are the same")]
+    SameObject,
+}
+
+// The maps of the types are changed through the methods below, which keep the
+// index of the keys, used by SCAN, KEYS and DBSIZE, in line with them. The
+// index is updated while the entry of the key is held, so that it agrees with
+// the maps whatever runs concurrently.
+impl Db {
+    fn key_added(&self, key: &str) {
+        let mut index = self.key_index.lock().unwrap();
+        match index.get_mut(key) {
+            Some(types) => *types += 1,
+            None => {
+                index.insert(key.to_string(), 1);
+            }
+        }
+    }
+
+    fn key_removed(&self, key: &str) {
+        let mut index = self.key_index.lock().unwrap();
+        if let Some(types) = index.get_mut(key) {
+            *types -= 1;
+            if *types == 0 {
+                index.remove(key);
+            }
+        }
+    }
+
+    /// The value of `key` in a map of the db, created empty if missing.
+    pub fn value_or_default<'a, V: Default>(
+        &self,
+        map: &'a DashMap<String, V>,
+        key: String,
+    ) -> RefMut<'a, String, V> {
+        match map.entry(key) {
+            Entry::Occupied(entry) => entry.into_ref(),
+            Entry::Vacant(entry) => {
+                self.key_added(entry.key());
+                entry.insert(V::default())
+            }
+        }
+    }
+
+    /// Set the value of `key` in a map of the db, returns the value replaced.
+    pub fn insert_value<V>(&self, map: &DashMap<String, V>, key: String, value: V) -> Option<V> {
+        match map.entry(key) {
+            Entry::Occupied(mut entry) => Some(entry.insert(value)),
+            Entry::Vacant(entry) => {
+                self.key_added(entry.key());
+                entry.insert(value);
+                None
+            }
+        }
+    }
+
+    /// Remove `key` from a map of the db if its value satisfies `f`.
+    pub fn remove_value_if<V>(
+        &self,
+        map: &DashMap<String, V>,
+        key: &str,
+        f: impl FnOnce(&V) -> bool,
+    ) -> Option<V> {
+        match map.entry(key.to_string()) {
+            Entry::Occupied(entry) if f(entry.get()) => {
+                self.key_removed(key);
+                Some(entry.remove())
+            }
+            _ => None,
+        }
+    }
+
+    // the number of blocked clients the value of a key can serve: each one
+    // popping from a sorted set takes a member at least, while every reader of
+    // a stream may be served by the same entries
+    pub(super) fn ready_count(&self, key: &str) -> usize {
+        if let Some(zset) = self.zset.get(key) {
+            return zset.len();
+        }
+        if self.stream.contains_key(key) {
+            return usize::MAX;
+        }
+        0
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.map.contains_key(key)
+            || self.hmap.contains_key(key)
+            || self.set.contains_key(key)
+            || self.zset.contains_key(key)
+            || self.stream.contains_key(key)
+    }
+
+    fn clear(&self) {
+        self.map.clear();
+        self.hmap.clear();
+        self.set.clear();
+        self.zset.clear();
+        self.stream.clear();
+        self.key_index.lock().unwrap().clear();
+    }
+}
+
+// Free a db taken out of its slot. Handles taken before the flush may still
+// hold it, so it is cleared explicitly rather than dropped, on a blocking
+// thread when lazy so that freeing a large keyspace doesn't stall the client.
+fn free_db(db: Arc<Db>, lazy: bool) {
+    let free = move || db.clear();
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) if lazy => {
+            handle.spawn_blocking(free);
+        }
+        _ => free(),
+    }
+}
+
+// Move the value of a key between the maps of a type in two dbs, holding both
+// entries so that a value written to the target meanwhile is never replaced.
+// The entries are taken in the order of the dbs, so two moves in opposite
+// directions can't deadlock.
+fn move_entry<V>(
+    (from, from_map): (&Db, &DashMap<String, V>),
+    (to, to_map): (&Db, &DashMap<String, V>),
+    key: &str,
+    from_first: bool,
+) -> bool {
+    let (src, dst) = if from_first {
+        let src = from_map.entry(key.to_string());
+        (src, to_map.entry(key.to_string()))
+    } else {
+        let dst = to_map.entry(key.to_string());
+        (from_map.entry(key.to_string()), dst)
+    };
+    match (src, dst) {
+        (Entry::Occupied(src), Entry::Vacant(dst)) => {
+            to.key_added(key);
+            from.key_removed(key);
+            dst.insert(src.remove());
+            true
+        }
+        _ => false,
+    }
+}
+
+impl Backend {
+    /// Refresh the handle with the db currently selected by the connection.
+    /// Commands should run against a single refreshed handle, which makes
+    /// `SWAPDB` and `FLUSHDB` atomic with respect to them.
+    pub fn current(&self) -> Backend {
+        let index = self.session.db();
+        Backend {
+            inner: self.inner.clone(),
+            session: self.session.clone(),
+            index,
+            db: self.slot(index),
+        }
+    }
+
+    pub(super) fn slot(&self, index: usize) -> Arc<Db> {
+        self.inner.dbs[index].read().unwrap().clone()
+    }
+
+    fn check_index(&self, index: usize) -> Result<(), DbError> {
+        if index < self.databases() {
+            Ok(())
+        } else {
+            Err(DbError::OutOfRange)
+        }
+    }
+
+    pub fn databases(&self) -> usize {
+        self.inner.dbs.len()
+    }
+
+    pub fn db_index(&self) -> usize {
+        self.index
+    }
+
+    /// Select the db used by the connection from its next command on.
+    pub fn select(&self, index: usize) -> Result<(), DbError> {
+        self.check_index(index)?;
+        self.session.select(index);
+        Ok(())
+    }
+
+    pub fn swapdb(&self, a: usize, b: usize) -> Result<(), DbError> {
+        self.check_index(a)?;
+        self.check_index(b)?;
+        if a != b {
+            // always lock the lower index first so concurrent swaps can't deadlock
+            let (lo, hi) = (a.min(b), a.max(b));
+            let mut lo = self.inner.dbs[lo].write().unwrap();
+            let mut hi = self.inner.dbs[hi].write().unwrap();
+            std::mem::swap(&mut *lo, &mut *hi);
+        }
+
+        // clients blocked on either db may be served by the data swapped in
+        let blocked: Vec<_> = self
+            .inner
+            .blocked
+            .iter()
+            .map(|v| v.key().clone())
+            .filter(|(index, _)| *index == a || *index == b)
+            .collect();
+        for (index, key) in blocked {
+            self.signal_db_key_ready(index, &key);
+        }
+        Ok(())
+    }
+
+    pub fn flushdb(&self, lazy: bool) {
+        let old = std::mem::take(&mut *self.inner.dbs[self.index].write().unwrap());
+        free_db(old, lazy);
+    }
+
+    pub fn flushall(&self, lazy: bool) {
+        for slot in self.inner.dbs.iter() {
+            let old = std::mem::take(&mut *slot.write().unwrap());
+            free_db(old, lazy);
+        }
+    }
+
+    /// Move a key to another db, returns false if the key doesn't exist or
+    /// already exists in the target db.
+    pub fn move_key(&self, key: &str, index: usize) -> Result<bool, DbError> {
+        self.check_index(index)?;
+        if index == self.index {
+            return Err(DbError::SameObject);
+        }
+        let target = self.slot(index);
+        if target.contains_key(key) {
+            return Ok(false);
+        }
+
+        let (from, to) = (&*self.db, &*target);
+        let from_first = self.index < index;
+        let moved = [
+            move_entry((from, &from.map), (to, &to.map), key, from_first),
+            move_entry((from, &from.hmap), (to, &to.hmap), key, from_first),
+            move_entry((from, &from.set), (to, &to.set), key, from_first),
+            move_entry((from, &from.zset), (to, &to.zset), key, from_first),
+            move_entry((from, &from.stream), (to, &to.stream), key, from_first),
+        ];
+        if !moved.contains(&true) {
+            return Ok(false);
+        }
+        self.signal_db_key_ready(index, key);
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+
+    #[test]
+    fn test_select_and_move() {
+        let backend = Backend::with_databases(4).connect();
+        backend.set("k".to_string(), BulkString::new("v").into());
+        assert_eq!(backend.select(4), Err(DbError::OutOfRange));
+        assert_eq!(backend.move_key("k", 0), Err(DbError::SameObject));
+        assert_eq!(backend.move_key("k", 2), Ok(true));
+        assert!(backend.get("k").is_none());
+
+        backend.select(2).unwrap();
+        // the handle only changes db once refreshed
+        assert!(backend.get("k").is_none());
+        let backend = backend.current();
+        assert_eq!(backend.db_index(), 2);
+        assert_eq!(backend.get("k"), Some(BulkString::new("v").into()));
+
+        // other connections keep their own selected db
+        assert_eq!(backend.connect().current().db_index(), 0);
+    }
+
+    #[test]
+    fn test_move_races() {
+        let backend = Backend::with_databases(2);
+        backend.set("k".to_string(), BulkString::new("v").into());
+        // moves in both directions at once neither deadlock nor lose the key
+        let handles = [0, 1].map(|index| {
+            let backend = backend.connect();
+            backend.select(index).unwrap();
+            let backend = backend.current();
+            std::thread::spawn(move || {
+                (0..1000)
+                    .filter(|_| backend.move_key("k", 1 - index).unwrap())
+                    .count()
+            })
+        });
+        let moves = handles.map(|v| v.join().unwrap());
+        assert!(moves[0] - moves[1] <= 1);
+        let found = [0, 1].map(|index| backend.slot(index).map.contains_key("k"));
+        assert_eq!(found, [moves[0] == moves[1], moves[0] != moves[1]]);
+        // the key is counted in the db it ended up in only
+        let sizes = [0, 1].map(|index| backend.slot(index).key_index.lock().unwrap().len());
+        assert_eq!(sizes, found.map(usize::from));
+
+        // a key already in the target is kept
+        backend.set("o".to_string(), BulkString::new("v").into());
+        let target = backend.slot(1);
+        target.insert_value(
+            &target.map,
+            "o".to_string(),
+            BulkString::new("other").into(),
+        );
+        assert_eq!(backend.move_key("o", 1), Ok(false));
+        assert_eq!(
+            target.map.get("o").unwrap().value(),
+            &BulkString::new("other").into()
+        );
+    }
+
+    #[test]
+    fn test_swap_and_flush() {
+        let backend = Backend::with_databases(2);
+        backend.set("k".to_string(), BulkString::new("v").into());
+        backend.swapdb(0, 1).unwrap();
+        assert!(backend.current().get("k").is_none());
+        backend.select(1).unwrap();
+        assert!(backend.current().get("k").is_some());
+
+        backend.current().flushdb(false);
+        assert!(backend.current().get("k").is_none());
+
+        backend
+            .current()
+            .set("k".to_string(), BulkString::new("v").into());
+        backend.flushall(true);
+        assert!(backend.current().get("k").is_none());
+    }
+}
@@ -0,0 +1,596 @@
+use std::{
+    borrow::Borrow,
+    collections::BTreeMap,
+    fmt,
+    hash::{DefaultHasher, Hash, Hasher},
+};
+
+use super::Backend;
+use crate::RespFrame;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyType {
+    String,
+    Hash,
+    Set,
+    ZSet,
+    Stream,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScanOptions {
+    pub pattern: Option<String>,
+    pub count: usize,
+}
+
+impl KeyType {
+    pub const ALL: [KeyType; 5] = [
+        KeyType::String,
+        KeyType::Hash,
+        KeyType::Set,
+        KeyType::ZSet,
+        KeyType::Stream,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KeyType::String => "string",
+            KeyType::Hash => "hash",
+            KeyType::Set => "set",
+            KeyType::ZSet => "zset",
+            KeyType::Stream => "stream",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|v| v.as_str().eq_ignore_ascii_case(name))
+    }
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        ScanOptions {
+            pattern: None,
+            count: 10,
+        }
+    }
+}
+
+impl ScanOptions {
+    fn matches(&self, s: &[u8]) -> bool {
+        self.pattern
+            .as_ref()
+            .is_none_or(|p| glob_match(p.as_bytes(), s))
+    }
+}
+
+/// Glob-style matching as done by redis: `*`, `?`, `[abc]`, `[^a-z]` and `\`
+/// to escape special characters.
+pub fn glob_match(pattern: &[u8], s: &[u8]) -> bool {
+    let (mut p, mut i) = (0, 0);
+    // pattern position after the last `*` and the string position it matched up to
+    let mut star: Option<(usize, usize)> = None;
+    while i < s.len() {
+        let matched = match pattern.get(p) {
+            Some(b'*') => {
+                star = Some((p + 1, i));
+                p += 1;
+                continue;
+            }
+            Some(b'?') => Some(p + 1),
+            Some(b'[') => match match_class(pattern, p, s[i]) {
+                (true, next) => Some(next),
+                (false, _) => None,
+            },
+            Some(b'\\') if p + 1 < pattern.len() => (pattern[p + 1] == s[i]).then_some(p + 2),
+            Some(c) => (*c == s[i]).then_some(p + 1),
+            None => None,
+        };
+        match (matched, star) {
+            (Some(next), _) => {
+                p = next;
+                i += 1;
+            }
+            // let the last `*` swallow one more character and retry
+            (None, Some((sp, si))) => {
+                star = Some((sp, si + 1));
+                p = sp;
+                i = si + 1;
+            }
+            (None, None) => return false,
+        }
+    }
+    pattern[p..].iter().all(|c| *c == b'*')
+}
+
+// match `c` against the class opened at `pattern[p]`, returns whether it
+// matched and the pattern position after the class
+fn match_class(pattern: &[u8], mut p: usize, c: u8) -> (bool, usize) {
+    p += 1;
+    let negate = pattern.get(p) == Some(&b'^');
+    if negate {
+        p += 1;
+    }
+    let mut matched = false;
+    while p < pattern.len() && pattern[p] != b']' {
+        if pattern[p] == b'\\' && p + 1 < pattern.len() {
+            p += 1;
+            matched |= pattern[p] == c;
+        } else if p + 2 < pattern.len() && pattern[p + 1] == b'-' {
+            let (lo, hi) = (
+                pattern[p].min(pattern[p + 2]),
+                pattern[p].max(pattern[p + 2]),
+            );
+            matched |= (lo..=hi).contains(&c);
+            p += 2;
+        } else {
+            matched |= pattern[p] == c;
+        }
+        p += 1;
+    }
+    (matched != negate, (p + 1).min(pattern.len()))
+}
+
+fn cursor_hash<T: Hash + ?Sized>(v: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    v.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// A map keeping its entries in the order of the hash of their key, the
+/// order SCAN and its variants visit them in. A cursor is the next hash to
+/// visit, so a page resumes from it in O(log N) with nothing kept between
+/// two calls. That order doesn't change as the map grows or shrinks, so an
+/// entry present for a whole iteration is returned at least once whatever is
+/// inserted or removed meanwhile.
+#[derive(Clone)]
+pub struct ScanMap<K, V> {
+    // entries sharing a hash are kept together, a page never splits them
+    buckets: BTreeMap<u64, Vec<(K, V)>>,
+    len: usize,
+}
+
+/// Fields of a hash.
+pub type HashValue = ScanMap<String, RespFrame>;
+
+/// Members of a set.
+pub type SetValue = ScanMap<RespFrame, ()>;
+
+impl<K, V> Default for ScanMap<K, V> {
+    fn default() -> Self {
+        ScanMap {
+            buckets: BTreeMap::new(),
+            len: 0,
+        }
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for ScanMap<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: Hash + Eq, V: PartialEq> PartialEq for ScanMap<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().all(|(k, v)| other.get(k) == Some(v))
+    }
+}
+
+impl<K, V> ScanMap<K, V> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.buckets.values().flatten().map(|(k, v)| (k, v))
+    }
+
+    pub fn clear(&mut self) {
+        self.buckets.clear();
+        self.len = 0;
+    }
+
+    /// The entries from `cursor` on, at least `count` of them unless fewer
+    /// are left, with the cursor of the next page, 0 once all are visited.
+    pub fn scan(&self, cursor: u64, count: usize) -> (u64, Vec<(&K, &V)>) {
+        let mut page = vec![];
+        let mut buckets = self.buckets.range(cursor..);
+        for (_, bucket) in buckets.by_ref() {
+            page.extend(bucket.iter().map(|(k, v)| (k, v)));
+            if page.len() >= count.max(1) {
+                break;
+            }
+        }
+        // the hashes left are greater than the cursor, so can't be 0
+        let next = buckets.next().map(|(h, _)| *h).unwrap_or(0);
+        (next, page)
+    }
+}
+
+impl<K: Hash + Eq, V> ScanMap<K, V> {
+    pub fn get<Q>(&self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let bucket = self.buckets.get(&cursor_hash(k))?;
+        bucket.iter().find(|(v, _)| v.borrow() == k).map(|(_, v)| v)
+    }
+
+    pub fn get_mut<Q>(&mut self, k: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let bucket = self.buckets.get_mut(&cursor_hash(k))?;
+        bucket
+            .iter_mut()
+            .find(|(v, _)| v.borrow() == k)
+            .map(|(_, v)| v)
+    }
+
+    pub fn contains_key<Q>(&self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get(k).is_some()
+    }
+
+    /// Insert or replace an entry, returns the value replaced.
+    pub fn insert(&mut self, k: K, v: V) -> Option<V> {
+        let bucket = self.buckets.entry(cursor_hash(&k)).or_default();
+        match bucket.iter_mut().find(|(key, _)| *key == k) {
+            Some((_, old)) => Some(std::mem::replace(old, v)),
+            None => {
+                bucket.push((k, v));
+                self.len += 1;
+                None
+            }
+        }
+    }
+
+    pub fn remove<Q>(&mut self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let hash = cursor_hash(k);
+        let bucket = self.buckets.get_mut(&hash)?;
+        let i = bucket.iter().position(|(v, _)| v.borrow() == k)?;
+        let (_, v) = bucket.swap_remove(i);
+        if bucket.is_empty() {
+            self.buckets.remove(&hash);
+        }
+        self.len -= 1;
+        Some(v)
+    }
+}
+
+impl<K: Hash + Eq, V> FromIterator<(K, V)> for ScanMap<K, V> {
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        let mut map = ScanMap::new();
+        for (k, v) in iter {
+            map.insert(k, v);
+        }
+        map
+    }
+}
+
+fn member_bytes(frame: &RespFrame) -> &[u8] {
+    match frame {
+        RespFrame::BulkString(v) => v,
+        RespFrame::SimpleString(v) => v.as_bytes(),
+        _ => &[],
+    }
+}
+
+impl Backend {
+    pub fn key_type(&self, key: &str) -> Option<KeyType> {
+        KeyType::ALL
+            .into_iter()
+            .find(|v| self.contains_key_of(key, *v))
+    }
+
+    fn contains_key_of(&self, key: &str, key_type: KeyType) -> bool {
+        match key_type {
+            KeyType::String => self.map.contains_key(key),
+            KeyType::Hash => self.hmap.contains_key(key),
+            KeyType::Set => self.set.contains_key(key),
+            KeyType::ZSet => self.zset.contains_key(key),
+            KeyType::Stream => self.stream.contains_key(key),
+        }
+    }
+
+    /// Keys matching the pattern, each once whatever the types of the values
+    /// it holds.
+    pub fn keys(&self, pattern: &str) -> Vec<String> {
+        self.key_index
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(k, _)| k)
+            .filter(|k| glob_match(pattern.as_bytes(), k.as_bytes()))
+            .cloned()
+            .collect()
+    }
+
+    /// The number of keys, counted as `keys` lists them.
+    pub fn dbsize(&self) -> usize {
+        self.key_index.lock().unwrap().len()
+    }
+
+    /// Incrementally iterate the keyspace, a returned cursor of 0 means the
+    /// iteration is complete.
+    pub fn scan(
+        &self,
+        cursor: u64,
+        opts: &ScanOptions,
+        key_type: Option<KeyType>,
+    ) -> (u64, Vec<String>) {
+        let (next, keys) = {
+            let index = self.key_index.lock().unwrap();
+            let (next, keys) = index.scan(cursor, opts.count);
+            let keys: Vec<String> = keys.into_iter().map(|(k, _)| k.clone()).collect();
+            (next, keys)
+        };
+        // the type is looked up once the index is released, like any value
+        let keys = keys
+            .into_iter()
+            .filter(|k| opts.matches(k.as_bytes()))
+            .filter(|k| key_type.is_none_or(|t| self.contains_key_of(k, t)))
+            .collect();
+        (next, keys)
+    }
+
+    pub fn hscan(
+        &self,
+        key: &str,
+        cursor: u64,
+        opts: &ScanOptions,
+    ) -> (u64, Vec<(String, RespFrame)>) {
+        let Some(hmap) = self.hmap.get(key) else {
+            return (0, vec![]);
+        };
+        let (next, fields) = hmap.scan(cursor, opts.count);
+        let fields = fields
+            .into_iter()
+            .filter(|(f, _)| opts.matches(f.as_bytes()))
+            .map(|(f, v)| (f.clone(), v.clone()))
+            .collect();
+        (next, fields)
+    }
+
+    pub fn sscan(&self, key: &str, cursor: u64, opts: &ScanOptions) -> (u64, Vec<RespFrame>) {
+        let Some(set) = self.set.get(key) else {
+            return (0, vec![]);
+        };
+        let (next, members) = set.scan(cursor, opts.count);
+        let members = members
+            .into_iter()
+            .map(|(v, _)| v)
+            .filter(|v| opts.matches(member_bytes(v)))
+            .cloned()
+            .collect();
+        (next, members)
+    }
+
+    pub fn zscan(&self, key: &str, cursor: u64, opts: &ScanOptions) -> (u64, Vec<(String, f64)>) {
+        let Some(zset) = self.zset.get(key) else {
+            return (0, vec![]);
+        };
+        let (next, members) = zset.scan(cursor, opts.count);
+        let members = members
+            .into_iter()
+            .filter(|(m, _)| opts.matches(m.as_bytes()))
+            .map(|(m, s)| (m.to_string(), s))
+            .collect();
+        (next, members)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_glob_match() {
+        let cases: &[(&str, &str, bool)] = &[
+            ("*", "", true),
+            ("*", "anything", true),
+            ("h?llo", "hello", true),
+            ("h?llo", "hllo", false),
+            ("h*llo", "heeeello", true),
+            ("h*llo", "hello world", false),
+            ("h[ae]llo", "hallo", true),
+            ("h[ae]llo", "hillo", false),
+            ("h[^e]llo", "hallo", true),
+            ("h[^e]llo", "hello", false),
+            ("h[a-b]llo", "hbllo", true),
+            ("h[b-a]llo", "hbllo", true),
+            ("h\\*llo", "h*llo", true),
+            ("h\\*llo", "hello", false),
+            ("user:*:name", "user:42:name", true),
+            ("*a*b", "xaxxbxb", true),
+            ("**", "x", true),
+        ];
+        for (pattern, s, expected) in cases {
+            assert_eq!(
+                glob_match(pattern.as_bytes(), s.as_bytes()),
+                *expected,
+                "{} ~ {}",
+                pattern,
+                s
+            );
+        }
+    }
+
+    #[test]
+    fn test_scan_visits_all_keys_under_mutation() {
+        let backend = Backend::new();
+        for i in 0..100 {
+            backend.set(format!("key:{}", i), BulkString::new("v").into());
+        }
+        backend.hset(
+            "hash".to_string(),
+            "f".to_string(),
+            BulkString::new("v").into(),
+        );
+
+        let opts = ScanOptions {
+            pattern: Some("key:*".to_string()),
+            count: 7,
+        };
+        let mut seen = HashSet::new();
+        let mut cursor = 0;
+        let mut i = 100;
+        loop {
+            let (next, keys) = backend.scan(cursor, &opts, None);
+            seen.extend(keys);
+            // keys added and removed concurrently must not hide stable keys
+            backend.set(format!("key:{}", i), BulkString::new("v").into());
+            backend.remove_value_if(&backend.map, &format!("key:{}", i - 1), |_| true);
+            i += 1;
+            if next == 0 {
+                break;
+            }
+            cursor = next;
+        }
+        assert!((0..99).all(|i| seen.contains(&format!("key:{}", i))));
+        assert!(!seen.contains("hash"));
+
+        let (_, keys) = backend.scan(
+            0,
+            &ScanOptions {
+                pattern: None,
+                count: 1000,
+            },
+            Some(KeyType::Hash),
+        );
+        assert_eq!(keys, ["hash"]);
+        assert_eq!(backend.key_type("hash"), Some(KeyType::Hash));
+    }
+
+    #[test]
+    fn test_scan_resumed_elsewhere() {
+        let backend = Backend::new();
+        for i in 0..20 {
+            backend.hset(
+                "hash".to_string(),
+                format!("f{}", i),
+                BulkString::new("v").into(),
+            );
+        }
+        let opts = ScanOptions {
+            pattern: None,
+            count: 5,
+        };
+        let (cursor, mut seen) = backend.hscan("hash", 0, &opts);
+        // removed elements are no longer returned, updated ones have their new value
+        let (_, last) = backend.hscan(
+            "hash",
+            0,
+            &ScanOptions {
+                count: 20,
+                ..opts.clone()
+            },
+        );
+        let (removed, _) = &last[19];
+        backend.hmap.get_mut("hash").unwrap().remove(removed);
+        let (updated, _) = &last[18];
+        backend.hset(
+            "hash".to_string(),
+            updated.clone(),
+            BulkString::new("new").into(),
+        );
+
+        // another connection picks up the cursor, nothing is kept in between
+        let other = backend.connect();
+        let mut cursor = cursor;
+        while cursor != 0 {
+            let (next, fields) = other.hscan("hash", cursor, &opts);
+            seen.extend(fields);
+            cursor = next;
+        }
+        assert_eq!(seen.len(), 19);
+        assert!(seen.iter().all(|(f, _)| f != removed));
+        assert!(seen.contains(&(updated.clone(), BulkString::new("new").into())));
+    }
+
+    #[test]
+    fn test_dbsize() {
+        let backend = Backend::new();
+        backend.set("a".to_string(), BulkString::new("v").into());
+        backend.sadd("b".to_string(), BulkString::new("m").into());
+        assert_eq!(backend.dbsize(), 2);
+        // a key holding values of two types is counted once, as listed
+        backend.hset(
+            "a".to_string(),
+            "f".to_string(),
+            BulkString::new("v").into(),
+        );
+        assert_eq!(backend.dbsize(), 2);
+        assert_eq!(backend.keys("*").len(), 2);
+        backend.remove_value_if(&backend.map, "a", |_| true);
+        assert_eq!(backend.dbsize(), 2);
+        backend.remove_value_if(&backend.hmap, "a", |_| true);
+        assert_eq!(backend.dbsize(), 1);
+        assert_eq!(backend.keys("*"), ["b"]);
+    }
+
+    #[test]
+    fn test_scan_map_pages() {
+        let mut map: ScanMap<String, usize> = (0..50).map(|i| (i.to_string(), i)).collect();
+        assert_eq!(map.len(), 50);
+        assert_eq!(map.get("7"), Some(&7));
+        assert_eq!(map.insert("7".to_string(), 70), Some(7));
+        assert_eq!(map.remove("8"), Some(8));
+        assert_eq!(map.remove("8"), None);
+
+        let mut seen = vec![];
+        let mut cursor = 0;
+        loop {
+            let (next, page) = map.scan(cursor, 10);
+            // every page does a bounded amount of work
+            assert!(page.len() <= 10);
+            seen.extend(page.into_iter().map(|(k, v)| (k.clone(), *v)));
+            if next == 0 {
+                break;
+            }
+            cursor = next;
+        }
+        seen.sort();
+        let mut expected: Vec<_> = map.iter().map(|(k, v)| (k.clone(), *v)).collect();
+        expected.sort();
+        assert_eq!(seen, expected);
+        assert_eq!(seen.len(), 49);
+    }
+
+    #[test]
+    fn test_keys() {
+        let backend = Backend::new();
+        backend.set("a1".to_string(), BulkString::new("v").into());
+        backend.hset(
+            "a2".to_string(),
+            "f".to_string(),
+            BulkString::new("v").into(),
+        );
+        backend.set("b1".to_string(), BulkString::new("v").into());
+        let mut keys = backend.keys("a*");
+        keys.sort();
+        assert_eq!(keys, ["a1", "a2"]);
+    }
+}
@@ -1,48 +1,106 @@
-use std::{fmt, ops::Deref, sync::Arc};
-
-use dashmap::{DashMap, DashSet};
-
-use crate::RespFrame;
-
+mod client;
+mod db;
+mod keyspace;
+mod server;
+mod stream;
+mod zset;
+
+use std::{
+    fmt,
+    ops::Deref,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex, RwLock,
+    },
+};
+
+use dashmap::DashMap;
+use tokio::sync::{watch, Notify};
+
+use crate::{config::Config, RespFrame};
+
+use client::Session;
+
+pub use client::*;
+pub use db::*;
+pub use keyspace::*;
+pub use server::*;
+pub use stream::*;
+pub use zset::*;
+
+pub const DEFAULT_DATABASES: usize = 16;
+
+/// Handle on the keyspace for a client connection. It derefs to the database
+/// selected by the connection, as it was when the handle was last refreshed
+/// with [`Backend::current`].
 #[derive(Debug, Clone)]
-pub struct Backend(pub(crate) Arc<BackendInner>);
+pub struct Backend {
+    inner: Arc<BackendInner>,
+    session: Arc<Session>,
+    index: usize,
+    db: Arc<Db>,
+}
 
+#[derive(Debug)]
 pub struct BackendInner {
+    dbs: Vec<RwLock<Arc<Db>>>,
+    // clients blocked on a key of a db in the order they blocked in, woken up
+    // when the key receives new data
+    blocked: DashMap<(usize, String), Vec<Arc<Notify>>>,
+    next_client_id: AtomicU64,
+    // connections being served, bounded by maxclients
+    connected_clients: AtomicUsize,
+    config: watch::Sender<Config>,
+    stats: Stats,
+    // set by SHUTDOWN or a signal, cleared by SHUTDOWN ABORT
+    shutdown: watch::Sender<Option<Shutdown>>,
+}
+
+pub struct Db {
     pub map: DashMap<String, RespFrame>,
-    pub hmap: DashMap<String, DashMap<String, RespFrame>>,
-    pub set: DashMap<String, DashSet<RespFrame>>,
+    pub hmap: DashMap<String, HashValue>,
+    pub set: DashMap<String, SetValue>,
+    pub zset: DashMap<String, SortedSet>,
+    pub stream: DashMap<String, Stream>,
+    // every key once, with the number of types it holds a value of
+    key_index: Mutex<ScanMap<String, usize>>,
 }
 
-impl fmt::Debug for BackendInner {
+impl fmt::Debug for Db {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("BackendInner")
+        f.debug_struct("Db")
             .field("map", &self.map)
             .field("hmap", &self.hmap)
-            .field("set", &"<DashSet<RespFrame>>") // 将 DashSet 转为 Vec 输出
+            .field("set", &self.set)
+            .field("zset", &self.zset)
+            .field("stream", &self.stream)
             .finish()
     }
 }
 
 impl Deref for Backend {
-    type Target = BackendInner;
+    type Target = Db;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.db
     }
 }
 
 impl Default for Backend {
     fn default() -> Self {
-        Self(Arc::new(BackendInner::default()))
+        Self::with_config(Config::default())
     }
 }
 
-impl Default for BackendInner {
+impl Default for Db {
     fn default() -> Self {
         Self {
             map: DashMap::new(),
             hmap: DashMap::new(),
             set: DashMap::new(),
+            zset: DashMap::new(),
+            stream: DashMap::new(),
+            key_index: Mutex::new(ScanMap::new()),
         }
     }
 }
@@ -52,27 +110,54 @@ impl Backend {
         Self::default()
     }
 
+    pub fn with_databases(databases: usize) -> Self {
+        Self::with_config(Config {
+            databases,
+            ..Config::default()
+        })
+    }
+
+    pub fn with_config(config: Config) -> Self {
+        let dbs = (0..config.databases.max(1))
+            .map(|_| RwLock::new(Arc::new(Db::default())))
+            .collect::<Vec<_>>();
+        let inner = BackendInner {
+            dbs,
+            blocked: DashMap::new(),
+            next_client_id: AtomicU64::new(1),
+            connected_clients: AtomicUsize::new(0),
+            config: watch::Sender::new(config),
+            stats: Stats::default(),
+            shutdown: watch::Sender::new(None),
+        };
+        let db = inner.dbs[0].read().unwrap().clone();
+        let session = Session::new(inner.next_client_id.fetch_add(1, Ordering::Relaxed));
+        Backend {
+            inner: Arc::new(inner),
+            session: Arc::new(session),
+            index: 0,
+            db,
+        }
+    }
+
     pub fn get(&self, key: &str) -> Option<RespFrame> {
         self.map.get(key).map(|v| v.value().clone())
     }
 
     pub fn set(&self, key: String, value: RespFrame) {
-        self.map.insert(key, value);
+        self.insert_value(&self.map, key, value);
     }
 
     pub fn hget(&self, key: &str, field: &str) -> Option<RespFrame> {
-        self.hmap
-            .get(key)
-            .and_then(|v| v.get(field).map(|v| v.value().clone()))
+        self.hmap.get(key).and_then(|v| v.get(field).cloned())
     }
 
     pub fn hset(&self, key: String, field: String, value: RespFrame) {
-        // let hmap = self.hmap.entry(key).or_insert_with(DashMap::new);
-        let hmap = self.hmap.entry(key).or_default();
+        let mut hmap = self.value_or_default(&self.hmap, key);
         hmap.insert(field, value);
     }
 
-    pub fn hgetall(&self, key: &str) -> Option<DashMap<String, RespFrame>> {
+    pub fn hgetall(&self, key: &str) -> Option<HashValue> {
         self.hmap.get(key).map(|v| v.clone())
     }
 
@@ -81,15 +166,60 @@ impl Backend {
     }
 
     pub fn sadd(&self, key: String, value: RespFrame) -> Option<bool> {
-        let set = self.set.entry(key).or_default();
-        if set.contains(&value) {
+        let mut set = self.value_or_default(&self.set, key);
+        if set.contains_key(&value) {
             return None;
         }
-        Some(set.insert(value))
+        Some(set.insert(value, ()).is_none())
+    }
+
+    /// Register a waiter on the given keys. The returned `Notify` keeps a
+    /// permit, so data pushed between registration and waiting is not missed.
+    pub fn block_on_keys(&self, keys: &[String]) -> Arc<Notify> {
+        let notify = Arc::new(Notify::new());
+        for key in keys {
+            self.inner
+                .blocked
+                .entry((self.index, key.clone()))
+                .or_default()
+                .push(notify.clone());
+        }
+        notify
+    }
+
+    pub fn unblock_keys(&self, keys: &[String], notify: &Arc<Notify>) {
+        for key in keys {
+            let blocked = (self.index, key.clone());
+            if let Some(mut waiters) = self.inner.blocked.get_mut(&blocked) {
+                waiters.retain(|v| !Arc::ptr_eq(v, notify));
+            }
+            self.inner.blocked.remove_if(&blocked, |_, v| v.is_empty());
+            // the client may have been woken up for data it didn't take, being
+            // served by another key or gone, the next one in line gets it
+            self.signal_key_ready(key);
+        }
+    }
+
+    /// Wake up the clients blocked on the key in the order they blocked in,
+    /// as many as its data can serve, so the longest waiting gets it.
+    pub fn signal_key_ready(&self, key: &str) {
+        self.signal_db_key_ready(self.index, key);
+    }
+
+    fn signal_db_key_ready(&self, index: usize, key: &str) {
+        // counted before looking up the waiters, no shard is locked meanwhile
+        let ready = self.slot(index).ready_count(key);
+        if ready == 0 {
+            return;
+        }
+        if let Some(waiters) = self.inner.blocked.get(&(index, key.to_string())) {
+            for notify in waiters.iter().take(ready) {
+                notify.notify_one();
+            }
+        }
     }
 
     pub fn sismember(&self, key: String, value: &RespFrame) -> bool {
-        let set = self.set.entry(key).or_default();
-        set.contains(value)
+        self.set.get(&key).is_some_and(|v| v.contains_key(value))
     }
 }
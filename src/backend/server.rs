@@ -0,0 +1,257 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use thiserror::Error;
+use tokio::sync::watch;
+
+use super::Backend;
+use crate::{
+    config::{Config, ConfigError},
+    tls,
+};
+
+#[derive(Debug, Error)]
+pub enum ConfigCommandError {
+    #[error("ERR Unknown option or number of arguments for CONFIG SET - '{0}'")]
+    UnknownOption(String),
+    #[error("ERR CONFIG SET failed (possibly related to argument '{0}') - {1}")]
+    SetFailed(String, String),
+    #[error("ERR The server is running without a config file")]
+    NoConfigFile,
+    #[error("ERR Rewriting config file: {0}")]
+    Rewrite(std::io::Error),
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ShutdownError {
+    #[error("ERR No shutdown in progress.")]
+    NotInProgress,
+}
+
+/// How the server was asked to shut down, by SHUTDOWN or a signal.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Shutdown {
+    /// NOSAVE was given. Nothing is saved either way as there is no
+    /// persistence, so SAVE is refused by the command.
+    pub nosave: bool,
+    /// Don't wait for the clients to finish their commands.
+    pub now: bool,
+    /// Ignore the errors that would prevent the shutdown, there are none
+    /// without persistence.
+    pub force: bool,
+}
+
+/// Server counters, cleared by CONFIG RESETSTAT.
+#[derive(Debug, Default)]
+pub struct Stats {
+    pub total_connections_received: AtomicU64,
+    pub total_commands_processed: AtomicU64,
+    /// Connections refused by maxclients.
+    pub rejected_connections: AtomicU64,
+}
+
+impl Stats {
+    fn reset(&self) {
+        self.total_connections_received.store(0, Ordering::Relaxed);
+        self.total_commands_processed.store(0, Ordering::Relaxed);
+        self.rejected_connections.store(0, Ordering::Relaxed);
+    }
+}
+
+impl Backend {
+    /// The effective configuration of the server.
+    pub fn config(&self) -> Config {
+        self.inner.config.borrow().clone()
+    }
+
+    /// A receiver notified when CONFIG SET changes the configuration.
+    pub fn subscribe_config(&self) -> watch::Receiver<Config> {
+        self.inner.config.subscribe()
+    }
+
+    /// Whether requests and replies must not be logged, they hold user data.
+    pub fn hide_user_data_from_log(&self) -> bool {
+        self.inner.config.borrow().hide_user_data_from_log
+    }
+
+    /// Set the given directives at once: if one of them is refused none is
+    /// changed.
+    pub fn config_set(&self, params: &[(String, String)]) -> Result<(), ConfigCommandError> {
+        let mut config = self.config();
+        for (i, (name, value)) in params.iter().enumerate() {
+            let name = name.to_ascii_lowercase();
+            if config.get(&name).is_none() {
+                return Err(ConfigCommandError::UnknownOption(name));
+            }
+            let failed = |reason: &str| ConfigCommandError::SetFailed(name.clone(), reason.into());
+            if Config::IMMUTABLE.contains(&name.as_str()) {
+                return Err(failed("can't set immutable config"));
+            }
+            if params[..i]
+                .iter()
+                .any(|(n, _)| n.eq_ignore_ascii_case(&name))
+            {
+                return Err(failed("duplicate parameter"));
+            }
+            config
+                .set(&name, std::slice::from_ref(value))
+                .map_err(|e: ConfigError| failed(&e.to_string()))?;
+        }
+        // the TLS listeners reload the certificates on change, they must load
+        if config.tls_port != 0 {
+            if let Some((name, _)) = params
+                .iter()
+                .find(|(name, _)| Config::TLS.contains(&name.to_ascii_lowercase().as_str()))
+            {
+                tls::acceptor(&config).map_err(|e| {
+                    ConfigCommandError::SetFailed(
+                        name.to_ascii_lowercase(),
+                        format!("Unable to update TLS configuration: {}", e),
+                    )
+                })?;
+            }
+        }
+        self.inner.config.send_replace(config);
+        Ok(())
+    }
+
+    pub fn config_rewrite(&self) -> Result<(), ConfigCommandError> {
+        let config = self.config();
+        if config.config_file.is_none() {
+            return Err(ConfigCommandError::NoConfigFile);
+        }
+        config.rewrite().map_err(ConfigCommandError::Rewrite)
+    }
+
+    /// Ask the server to shut down: the listeners stop accepting and the
+    /// connections are closed once their current command is served.
+    pub fn shutdown(&self, shutdown: Shutdown) {
+        self.inner.shutdown.send_replace(Some(shutdown));
+    }
+
+    /// Cancel a shutdown still waiting for the clients, the listeners accept
+    /// again.
+    pub fn abort_shutdown(&self) -> Result<(), ShutdownError> {
+        if self.inner.shutdown.send_if_modified(|s| s.take().is_some()) {
+            Ok(())
+        } else {
+            Err(ShutdownError::NotInProgress)
+        }
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        self.inner.shutdown.borrow().is_some()
+    }
+
+    /// A receiver notified when a shutdown is requested or aborted.
+    pub fn subscribe_shutdown(&self) -> watch::Receiver<Option<Shutdown>> {
+        self.inner.shutdown.subscribe()
+    }
+
+    pub fn stats(&self) -> &Stats {
+        &self.inner.stats
+    }
+
+    pub fn reset_stats(&self) {
+        self.inner.stats.reset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_config_set() -> anyhow::Result<()> {
+        let backend = Backend::new();
+        let mut rx = backend.subscribe_config();
+        backend.config_set(&params(&[("MAXMEMORY", "1kb"), ("loglevel", "debug")]))?;
+        assert!(rx.has_changed()?);
+        let config = rx.borrow_and_update().clone();
+        assert_eq!(config.maxmemory, 1024);
+        assert_eq!(config.loglevel, "debug");
+
+        let errors = [
+            (
+                params(&[("nope", "1")]),
+                "ERR Unknown option or number of arguments for CONFIG SET - 'nope'",
+            ),
+            (
+                params(&[("port", "1")]),
+                "ERR CONFIG SET failed (possibly related to argument 'port') - can't set immutable config",
+            ),
+            (
+                params(&[("maxmemory", "1"), ("maxmemory", "2")]),
+                "ERR CONFIG SET failed (possibly related to argument 'maxmemory') - duplicate parameter",
+            ),
+            (
+                params(&[("maxmemory", "2"), ("loglevel", "loud")]),
+                "ERR CONFIG SET failed (possibly related to argument 'loglevel') - argument must be one of debug, verbose, notice, warning, nothing",
+            ),
+        ];
+        for (params, message) in errors {
+            let err = backend.config_set(&params).unwrap_err();
+            assert_eq!(err.to_string(), message);
+        }
+        // nothing was applied by the failed calls
+        assert!(!rx.has_changed()?);
+        assert_eq!(backend.config().maxmemory, 1024);
+
+        // TLS is only checked when enabled
+        backend.config_set(&params(&[("tls-cert-file", "/nonexistent.crt")]))?;
+        let backend = Backend::with_config(Config {
+            tls_port: 6380,
+            ..Config::default()
+        });
+        let err = backend
+            .config_set(&params(&[("tls-cert-file", "/nonexistent.crt")]))
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("ERR CONFIG SET failed (possibly related to argument 'tls-cert-file') - Unable to update TLS configuration"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_shutdown() {
+        let backend = Backend::new();
+        let rx = backend.subscribe_shutdown();
+        assert_eq!(backend.abort_shutdown(), Err(ShutdownError::NotInProgress));
+
+        let forced = Shutdown {
+            force: true,
+            ..Shutdown::default()
+        };
+        backend.shutdown(forced);
+        assert!(backend.is_shutting_down());
+        assert_eq!(*rx.borrow(), Some(forced));
+        assert_eq!(backend.abort_shutdown(), Ok(()));
+        assert!(!backend.is_shutting_down());
+    }
+
+    #[test]
+    fn test_reset_stats() {
+        let backend = Backend::new();
+        backend.connect();
+        backend.connect();
+        let received = || {
+            backend
+                .stats()
+                .total_connections_received
+                .load(Ordering::Relaxed)
+        };
+        assert_eq!(received(), 2);
+        backend.reset_stats();
+        assert_eq!(received(), 0);
+        assert!(matches!(
+            backend.config_rewrite(),
+            Err(ConfigCommandError::NoConfigFile)
+        ));
+    }
+}
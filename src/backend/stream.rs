@@ -0,0 +1,395 @@
+mod group;
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use thiserror::Error;
+
+use super::Backend;
+
+pub use group::*;
+
+// approximate trimming (`~`) only evicts whole chunks of this many entries,
+// mirroring the macro nodes of the redis radix tree
+const APPROX_TRIM_CHUNK: usize = 100;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StreamId {
+    pub ms: u64,
+    pub seq: u64,
+}
+
+pub type StreamEntry = (StreamId, Vec<(String, String)>);
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Stream {
+    entries: BTreeMap<StreamId, Vec<(String, String)>>,
+    last_id: StreamId,
+    max_deleted_id: StreamId,
+    entries_added: u64,
+    groups: BTreeMap<String, ConsumerGroup>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum XAddId {
+    Auto,
+    AutoSeq(u64),
+    Explicit(StreamId),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrimStrategy {
+    MaxLen(usize),
+    MinId(StreamId),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StreamTrim {
+    pub strategy: TrimStrategy,
+    pub approx: bool,
+    pub limit: Option<usize>,
+}
+
+/// Stream errors, displayed with the redis error code prefix.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum StreamError {
+    #[error("ERR The ID specified in XADD must be greater than 0-0")]
+    ZeroId,
+    #[error("ERR The ID specified in XADD is equal or smaller than the target stream top item")]
+    IdTooSmall,
+    #[error("ERR The stream has exhausted the last possible ID, unable to add more items")]
+    Exhausted,
+    #[error("ERR The XGROUP subcommand requires the key to exist. Note that for CREATE you may want to use the MKSTREAM option to create an empty stream automatically.")]
+    NoSuchKey,
+    #[error("BUSYGROUP Consumer Group name already exists")]
+    BusyGroup,
+    #[error("NOGROUP No such key '{0}' or consumer group '{1}'")]
+    NoGroup(String, String),
+}
+
+pub(crate) fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+impl StreamId {
+    pub const MIN: StreamId = StreamId { ms: 0, seq: 0 };
+    pub const MAX: StreamId = StreamId {
+        ms: u64::MAX,
+        seq: u64::MAX,
+    };
+
+    pub fn new(ms: u64, seq: u64) -> Self {
+        StreamId { ms, seq }
+    }
+
+    pub fn next(&self) -> Option<StreamId> {
+        match (self.seq.checked_add(1), self.ms.checked_add(1)) {
+            (Some(seq), _) => Some(StreamId::new(self.ms, seq)),
+            (None, Some(ms)) => Some(StreamId::new(ms, 0)),
+            (None, None) => None,
+        }
+    }
+
+    pub fn prev(&self) -> Option<StreamId> {
+        match (self.seq.checked_sub(1), self.ms.checked_sub(1)) {
+            (Some(seq), _) => Some(StreamId::new(self.ms, seq)),
+            (None, Some(ms)) => Some(StreamId::new(ms, u64::MAX)),
+            (None, None) => None,
+        }
+    }
+}
+
+impl fmt::Display for StreamId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.ms, self.seq)
+    }
+}
+
+impl Stream {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn last_id(&self) -> StreamId {
+        self.last_id
+    }
+
+    pub fn get(&self, id: &StreamId) -> Option<&Vec<(String, String)>> {
+        self.entries.get(id)
+    }
+
+    /// Append an entry, generating or validating its id.
+    pub fn add(
+        &mut self,
+        id: XAddId,
+        fields: Vec<(String, String)>,
+    ) -> Result<StreamId, StreamError> {
+        let last = self.last_id;
+        let id = match id {
+            XAddId::Auto => {
+                let now = now_ms();
+                if now > last.ms {
+                    StreamId::new(now, 0)
+                } else {
+                    last.next().ok_or(StreamError::Exhausted)?
+                }
+            }
+            XAddId::AutoSeq(ms) if ms == last.ms => {
+                let seq = last.seq.checked_add(1).ok_or(StreamError::IdTooSmall)?;
+                StreamId::new(ms, seq)
+            }
+            XAddId::AutoSeq(ms) if ms > last.ms => StreamId::new(ms, if ms == 0 { 1 } else { 0 }),
+            XAddId::AutoSeq(_) => return Err(StreamError::IdTooSmall),
+            XAddId::Explicit(id) if id == StreamId::MIN => return Err(StreamError::ZeroId),
+            XAddId::Explicit(id) if id <= last => return Err(StreamError::IdTooSmall),
+            XAddId::Explicit(id) => id,
+        };
+
+        self.entries.insert(id, fields);
+        self.last_id = id;
+        self.entries_added += 1;
+        Ok(id)
+    }
+
+    /// Entries with `start <= id <= end`, at most `count` of them.
+    pub fn range(
+        &self,
+        start: StreamId,
+        end: StreamId,
+        rev: bool,
+        count: Option<usize>,
+    ) -> Vec<StreamEntry> {
+        if start > end {
+            return vec![];
+        }
+        let iter = self.entries.range(start..=end);
+        let count = count.unwrap_or(usize::MAX);
+        let clone = |(id, fields): (&StreamId, &Vec<(String, String)>)| (*id, fields.clone());
+        if rev {
+            iter.rev().take(count).map(clone).collect()
+        } else {
+            iter.take(count).map(clone).collect()
+        }
+    }
+
+    pub fn delete(&mut self, ids: &[StreamId]) -> usize {
+        let mut deleted = 0;
+        for id in ids {
+            if self.entries.remove(id).is_some() {
+                self.max_deleted_id = self.max_deleted_id.max(*id);
+                deleted += 1;
+            }
+        }
+        deleted
+    }
+
+    /// Evict the oldest entries according to the trim strategy, returns the
+    /// number of evicted entries.
+    pub fn trim(&mut self, trim: &StreamTrim) -> usize {
+        let mut evict = match trim.strategy {
+            TrimStrategy::MaxLen(max) => self.len().saturating_sub(max),
+            TrimStrategy::MinId(min) => self.entries.range(..min).count(),
+        };
+        if let Some(limit) = trim.limit.filter(|v| *v > 0) {
+            evict = evict.min(limit);
+        }
+        // the limit is rounded down to whole chunks too
+        if trim.approx {
+            evict -= evict % APPROX_TRIM_CHUNK;
+        }
+
+        for _ in 0..evict {
+            if let Some((id, _)) = self.entries.pop_first() {
+                self.max_deleted_id = self.max_deleted_id.max(id);
+            }
+        }
+        evict
+    }
+}
+
+impl Backend {
+    /// Append an entry to the stream. Returns `Ok(None)` if the stream does
+    /// not exist and `nomkstream` is set.
+    pub fn xadd(
+        &self,
+        key: String,
+        id: XAddId,
+        fields: Vec<(String, String)>,
+        nomkstream: bool,
+        trim: Option<&StreamTrim>,
+    ) -> Result<Option<StreamId>, StreamError> {
+        if nomkstream && !self.stream.contains_key(&key) {
+            return Ok(None);
+        }
+        let mut stream = self.value_or_default(&self.stream, key.clone());
+        let id = stream.add(id, fields);
+        if let (Ok(_), Some(trim)) = (&id, trim) {
+            stream.trim(trim);
+        }
+        let empty = stream.is_empty() && stream.last_id == StreamId::MIN;
+        drop(stream);
+
+        if empty {
+            self.remove_value_if(&self.stream, &key, |v| {
+                v.is_empty() && v.last_id == StreamId::MIN
+            });
+        } else if id.is_ok() {
+            self.signal_key_ready(&key);
+        }
+        id.map(Some)
+    }
+
+    pub fn xrange(
+        &self,
+        key: &str,
+        start: StreamId,
+        end: StreamId,
+        rev: bool,
+        count: Option<usize>,
+    ) -> Vec<StreamEntry> {
+        self.stream
+            .get(key)
+            .map(|v| v.range(start, end, rev, count))
+            .unwrap_or_default()
+    }
+
+    pub fn xlen(&self, key: &str) -> usize {
+        self.stream.get(key).map(|v| v.len()).unwrap_or(0)
+    }
+
+    pub fn xdel(&self, key: &str, ids: &[StreamId]) -> usize {
+        self.stream
+            .get_mut(key)
+            .map(|mut v| v.delete(ids))
+            .unwrap_or(0)
+    }
+
+    pub fn xtrim(&self, key: &str, trim: &StreamTrim) -> usize {
+        self.stream
+            .get_mut(key)
+            .map(|mut v| v.trim(trim))
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields() -> Vec<(String, String)> {
+        vec![("f".to_string(), "v".to_string())]
+    }
+
+    #[test]
+    fn test_add_ids() {
+        let mut stream = Stream::new();
+        assert_eq!(
+            stream.add(XAddId::Explicit(StreamId::MIN), fields()),
+            Err(StreamError::ZeroId)
+        );
+        assert_eq!(
+            stream.add(XAddId::AutoSeq(0), fields()),
+            Ok(StreamId::new(0, 1))
+        );
+        assert_eq!(
+            stream.add(XAddId::Explicit(StreamId::new(5, 3)), fields()),
+            Ok(StreamId::new(5, 3))
+        );
+        assert_eq!(
+            stream.add(XAddId::AutoSeq(5), fields()),
+            Ok(StreamId::new(5, 4))
+        );
+        assert_eq!(
+            stream.add(XAddId::Explicit(StreamId::new(5, 4)), fields()),
+            Err(StreamError::IdTooSmall)
+        );
+        assert_eq!(
+            stream.add(XAddId::AutoSeq(4), fields()),
+            Err(StreamError::IdTooSmall)
+        );
+
+        let id = stream.add(XAddId::Auto, fields()).unwrap();
+        assert!(id > StreamId::new(5, 4));
+        assert_eq!(stream.len(), 4);
+    }
+
+    #[test]
+    fn test_range_and_delete() {
+        let mut stream = Stream::new();
+        for i in 1..=5 {
+            stream
+                .add(XAddId::Explicit(StreamId::new(i, 0)), fields())
+                .unwrap();
+        }
+        let ids = |v: Vec<StreamEntry>| v.into_iter().map(|(id, _)| id.ms).collect::<Vec<_>>();
+        assert_eq!(
+            ids(stream.range(StreamId::new(2, 0), StreamId::MAX, false, Some(2))),
+            [2, 3]
+        );
+        assert_eq!(
+            ids(stream.range(StreamId::MIN, StreamId::new(4, 0), true, None)),
+            [4, 3, 2, 1]
+        );
+
+        assert_eq!(
+            stream.delete(&[StreamId::new(3, 0), StreamId::new(9, 0)]),
+            1
+        );
+        assert_eq!(
+            ids(stream.range(StreamId::MIN, StreamId::MAX, false, None)),
+            [1, 2, 4, 5]
+        );
+        assert_eq!(stream.last_id(), StreamId::new(5, 0));
+    }
+
+    #[test]
+    fn test_trim() {
+        let mut stream = Stream::new();
+        for i in 1..=250 {
+            stream
+                .add(XAddId::Explicit(StreamId::new(i, 0)), fields())
+                .unwrap();
+        }
+        let approx = StreamTrim {
+            strategy: TrimStrategy::MaxLen(100),
+            approx: true,
+            limit: None,
+        };
+        assert_eq!(stream.trim(&approx), 100);
+        assert_eq!(stream.len(), 150);
+
+        // a limit smaller than a chunk evicts nothing
+        let mut limited = StreamTrim {
+            strategy: TrimStrategy::MaxLen(0),
+            approx: true,
+            limit: Some(10),
+        };
+        assert_eq!(stream.trim(&limited), 0);
+        assert_eq!(stream.len(), 150);
+        limited.limit = Some(120);
+        assert_eq!(stream.trim(&limited), 100);
+        assert_eq!(stream.len(), 50);
+
+        let exact = StreamTrim {
+            strategy: TrimStrategy::MinId(StreamId::new(220, 0)),
+            approx: false,
+            limit: None,
+        };
+        assert_eq!(stream.trim(&exact), 19);
+        assert_eq!(stream.len(), 31);
+    }
+}
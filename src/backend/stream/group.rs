@@ -0,0 +1,797 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ops::Bound,
+};
+
+use super::{now_ms, Stream, StreamEntry, StreamError, StreamId};
+use crate::backend::Backend;
+
+/// An entry delivered to a consumer but not acknowledged yet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingEntry {
+    pub consumer: String,
+    pub delivery_time: u64,
+    pub delivery_count: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Consumer {
+    seen_time: u64,
+    active_time: Option<u64>,
+    pending: BTreeSet<StreamId>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConsumerGroup {
+    last_delivered_id: StreamId,
+    entries_read: Option<u64>,
+    pending: BTreeMap<StreamId, PendingEntry>,
+    consumers: BTreeMap<String, Consumer>,
+}
+
+/// Entry returned to a group reader, `None` fields mean the entry was deleted
+/// from the stream while still pending.
+pub type GroupEntry = (StreamId, Option<Vec<(String, String)>>);
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ClaimOptions {
+    pub idle: Option<u64>,
+    pub time: Option<u64>,
+    pub retry_count: Option<u64>,
+    pub force: bool,
+    pub justid: bool,
+    pub last_id: Option<StreamId>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingFilter {
+    pub min_idle: u64,
+    pub start: StreamId,
+    pub end: StreamId,
+    pub count: usize,
+    pub consumer: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingSummary {
+    pub count: usize,
+    pub min: Option<StreamId>,
+    pub max: Option<StreamId>,
+    pub consumers: Vec<(String, usize)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingDetail {
+    pub id: StreamId,
+    pub consumer: String,
+    pub idle: u64,
+    pub delivery_count: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AutoClaim {
+    pub cursor: StreamId,
+    pub claimed: Vec<StreamEntry>,
+    pub deleted: Vec<StreamId>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamInfo {
+    pub length: usize,
+    pub last_generated_id: StreamId,
+    pub max_deleted_entry_id: StreamId,
+    pub entries_added: u64,
+    pub recorded_first_entry_id: StreamId,
+    pub groups: usize,
+    pub first_entry: Option<StreamEntry>,
+    pub last_entry: Option<StreamEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GroupInfo {
+    pub name: String,
+    pub consumers: usize,
+    pub pending: usize,
+    pub last_delivered_id: StreamId,
+    pub entries_read: Option<u64>,
+    pub lag: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConsumerInfo {
+    pub name: String,
+    pub pending: usize,
+    pub idle: u64,
+    pub inactive: Option<u64>,
+}
+
+impl Consumer {
+    fn new(now: u64) -> Self {
+        Consumer {
+            seen_time: now,
+            active_time: None,
+            pending: BTreeSet::new(),
+        }
+    }
+}
+
+impl ConsumerGroup {
+    fn new(last_delivered_id: StreamId, entries_read: Option<u64>) -> Self {
+        ConsumerGroup {
+            last_delivered_id,
+            entries_read,
+            pending: BTreeMap::new(),
+            consumers: BTreeMap::new(),
+        }
+    }
+
+    fn consumer(&mut self, name: &str, now: u64) -> &mut Consumer {
+        let consumer = self
+            .consumers
+            .entry(name.to_string())
+            .or_insert_with(|| Consumer::new(now));
+        consumer.seen_time = now;
+        consumer
+    }
+
+    // (re)assign a pending entry to the consumer
+    fn assign(&mut self, id: StreamId, consumer: &str, entry: PendingEntry) {
+        if let Some(old) = self.pending.insert(id, entry) {
+            if let Some(c) = self.consumers.get_mut(&old.consumer) {
+                c.pending.remove(&id);
+            }
+        }
+        if let Some(c) = self.consumers.get_mut(consumer) {
+            c.pending.insert(id);
+        }
+    }
+
+    fn unassign(&mut self, id: &StreamId) -> bool {
+        match self.pending.remove(id) {
+            Some(entry) => {
+                if let Some(c) = self.consumers.get_mut(&entry.consumer) {
+                    c.pending.remove(id);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl Stream {
+    fn group_mut(&mut self, key: &str, group: &str) -> Result<&mut ConsumerGroup, StreamError> {
+        self.groups
+            .get_mut(group)
+            .ok_or_else(|| StreamError::NoGroup(key.to_string(), group.to_string()))
+    }
+
+    // entries read by a group reaching the stream top item, `None` when unknown
+    fn entries_read_at(&self, id: StreamId) -> Option<u64> {
+        if id >= self.last_id {
+            Some(self.entries_added)
+        } else if id == StreamId::MIN && self.max_deleted_id == StreamId::MIN {
+            Some(0)
+        } else {
+            None
+        }
+    }
+
+    /// Create a group, `None` as id means the last entry (`$`).
+    pub fn create_group(
+        &mut self,
+        group: String,
+        id: Option<StreamId>,
+        entries_read: Option<u64>,
+    ) -> Result<(), StreamError> {
+        if self.groups.contains_key(&group) {
+            return Err(StreamError::BusyGroup);
+        }
+        let id = id.unwrap_or(self.last_id);
+        let entries_read = entries_read.or_else(|| self.entries_read_at(id));
+        self.groups
+            .insert(group, ConsumerGroup::new(id, entries_read));
+        Ok(())
+    }
+
+    pub fn set_group_id(
+        &mut self,
+        key: &str,
+        group: &str,
+        id: Option<StreamId>,
+        entries_read: Option<u64>,
+    ) -> Result<(), StreamError> {
+        let id = id.unwrap_or(self.last_id);
+        let entries_read = entries_read.or_else(|| self.entries_read_at(id));
+        let g = self.group_mut(key, group)?;
+        g.last_delivered_id = id;
+        g.entries_read = entries_read;
+        Ok(())
+    }
+
+    /// Read entries for a consumer. `None` as id means new entries (`>`),
+    /// any other id reads the consumer's pending history after it.
+    pub fn read_group(
+        &mut self,
+        key: &str,
+        group: &str,
+        consumer: &str,
+        id: Option<StreamId>,
+        count: Option<usize>,
+        noack: bool,
+    ) -> Result<Vec<GroupEntry>, StreamError> {
+        let now = now_ms();
+        let count = count.filter(|v| *v > 0).unwrap_or(usize::MAX);
+        let (entries, last_id, entries_added) = (&self.entries, self.last_id, self.entries_added);
+        let g = self
+            .groups
+            .get_mut(group)
+            .ok_or_else(|| StreamError::NoGroup(key.to_string(), group.to_string()))?;
+        g.consumer(consumer, now);
+
+        match id {
+            Some(id) => {
+                let c = g.consumer(consumer, now);
+                Ok(c.pending
+                    .range((Bound::Excluded(id), Bound::Unbounded))
+                    .take(count)
+                    .map(|id| (*id, entries.get(id).cloned()))
+                    .collect())
+            }
+            None => {
+                let start = match g.last_delivered_id.next() {
+                    Some(start) => start,
+                    None => return Ok(vec![]),
+                };
+                let ret: Vec<GroupEntry> = entries
+                    .range(start..)
+                    .take(count)
+                    .map(|(id, fields)| (*id, Some(fields.clone())))
+                    .collect();
+
+                for (id, _) in &ret {
+                    g.last_delivered_id = *id;
+                    g.entries_read = g.entries_read.map(|v| v + 1);
+                    if !noack {
+                        let entry = PendingEntry {
+                            consumer: consumer.to_string(),
+                            delivery_time: now,
+                            delivery_count: 1,
+                        };
+                        g.assign(*id, consumer, entry);
+                    }
+                }
+                if g.last_delivered_id == last_id {
+                    g.entries_read = Some(entries_added);
+                }
+                if !ret.is_empty() {
+                    g.consumer(consumer, now).active_time = Some(now);
+                }
+                Ok(ret)
+            }
+        }
+    }
+
+    pub fn claim(
+        &mut self,
+        key: &str,
+        group: &str,
+        consumer: &str,
+        min_idle: u64,
+        ids: &[StreamId],
+        opts: &ClaimOptions,
+    ) -> Result<Vec<StreamEntry>, StreamError> {
+        let now = now_ms();
+        let delivery_time = match (opts.time, opts.idle) {
+            (Some(time), _) => time,
+            (None, Some(idle)) => now.saturating_sub(idle),
+            (None, None) => now,
+        };
+        let entries = &self.entries;
+        let g = self
+            .groups
+            .get_mut(group)
+            .ok_or_else(|| StreamError::NoGroup(key.to_string(), group.to_string()))?;
+        if let Some(last_id) = opts.last_id {
+            g.last_delivered_id = g.last_delivered_id.max(last_id);
+        }
+        g.consumer(consumer, now);
+
+        let mut ret = vec![];
+        for id in ids {
+            let Some(fields) = entries.get(id) else {
+                // entries deleted from the stream are dropped from the PEL
+                g.unassign(id);
+                continue;
+            };
+            let mut entry = match g.pending.get(id) {
+                Some(entry) if now.saturating_sub(entry.delivery_time) < min_idle => continue,
+                Some(entry) => entry.clone(),
+                None if opts.force => PendingEntry {
+                    consumer: consumer.to_string(),
+                    delivery_time,
+                    delivery_count: 0,
+                },
+                None => continue,
+            };
+            entry.consumer = consumer.to_string();
+            entry.delivery_time = delivery_time;
+            match opts.retry_count {
+                Some(count) => entry.delivery_count = count,
+                None if !opts.justid => entry.delivery_count += 1,
+                None => {}
+            }
+            g.assign(*id, consumer, entry);
+            ret.push((*id, fields.clone()));
+        }
+        if !ret.is_empty() {
+            g.consumer(consumer, now).active_time = Some(now);
+        }
+        Ok(ret)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn auto_claim(
+        &mut self,
+        key: &str,
+        group: &str,
+        consumer: &str,
+        min_idle: u64,
+        start: StreamId,
+        count: usize,
+        justid: bool,
+    ) -> Result<AutoClaim, StreamError> {
+        let now = now_ms();
+        let entries = &self.entries;
+        let g = self
+            .groups
+            .get_mut(group)
+            .ok_or_else(|| StreamError::NoGroup(key.to_string(), group.to_string()))?;
+        g.consumer(consumer, now);
+
+        // like redis, scan at most 10 PEL entries per requested entry
+        let attempts = count.saturating_mul(10);
+        let candidates: Vec<StreamId> = g
+            .pending
+            .range(start..)
+            .map(|(id, _)| *id)
+            .take(attempts.saturating_add(1))
+            .collect();
+
+        let mut claimed = vec![];
+        let mut deleted = vec![];
+        let mut scanned = 0;
+        for id in &candidates {
+            if scanned == attempts || claimed.len() == count {
+                break;
+            }
+            scanned += 1;
+            let Some(fields) = entries.get(id) else {
+                g.unassign(id);
+                deleted.push(*id);
+                continue;
+            };
+            let mut entry = match g.pending.get(id) {
+                Some(entry) if now.saturating_sub(entry.delivery_time) >= min_idle => entry.clone(),
+                _ => continue,
+            };
+            entry.consumer = consumer.to_string();
+            entry.delivery_time = now;
+            if !justid {
+                entry.delivery_count += 1;
+            }
+            g.assign(*id, consumer, entry);
+            claimed.push((*id, fields.clone()));
+        }
+        if !claimed.is_empty() {
+            g.consumer(consumer, now).active_time = Some(now);
+        }
+
+        Ok(AutoClaim {
+            cursor: candidates.get(scanned).copied().unwrap_or(StreamId::MIN),
+            claimed,
+            deleted,
+        })
+    }
+
+    pub fn ack(&mut self, group: &str, ids: &[StreamId]) -> usize {
+        match self.groups.get_mut(group) {
+            Some(g) => ids.iter().filter(|id| g.unassign(id)).count(),
+            None => 0,
+        }
+    }
+
+    pub fn pending_summary(&self, key: &str, group: &str) -> Result<PendingSummary, StreamError> {
+        let g = self
+            .groups
+            .get(group)
+            .ok_or_else(|| StreamError::NoGroup(key.to_string(), group.to_string()))?;
+        Ok(PendingSummary {
+            count: g.pending.len(),
+            min: g.pending.keys().next().copied(),
+            max: g.pending.keys().next_back().copied(),
+            consumers: g
+                .consumers
+                .iter()
+                .filter(|(_, c)| !c.pending.is_empty())
+                .map(|(name, c)| (name.clone(), c.pending.len()))
+                .collect(),
+        })
+    }
+
+    pub fn pending(
+        &self,
+        key: &str,
+        group: &str,
+        filter: &PendingFilter,
+    ) -> Result<Vec<PendingDetail>, StreamError> {
+        let now = now_ms();
+        let g = self
+            .groups
+            .get(group)
+            .ok_or_else(|| StreamError::NoGroup(key.to_string(), group.to_string()))?;
+        if filter.start > filter.end {
+            return Ok(vec![]);
+        }
+        Ok(g.pending
+            .range(filter.start..=filter.end)
+            .filter(|(_, e)| filter.consumer.as_ref().is_none_or(|c| *c == e.consumer))
+            .map(|(id, e)| PendingDetail {
+                id: *id,
+                consumer: e.consumer.clone(),
+                idle: now.saturating_sub(e.delivery_time),
+                delivery_count: e.delivery_count,
+            })
+            .filter(|v| v.idle >= filter.min_idle)
+            .take(filter.count)
+            .collect())
+    }
+
+    pub fn info(&self) -> StreamInfo {
+        let first_entry = self.entries.first_key_value();
+        StreamInfo {
+            length: self.len(),
+            last_generated_id: self.last_id,
+            max_deleted_entry_id: self.max_deleted_id,
+            entries_added: self.entries_added,
+            recorded_first_entry_id: first_entry.map(|(id, _)| *id).unwrap_or_default(),
+            groups: self.groups.len(),
+            first_entry: first_entry.map(|(id, v)| (*id, v.clone())),
+            last_entry: self
+                .entries
+                .last_key_value()
+                .map(|(id, v)| (*id, v.clone())),
+        }
+    }
+
+    pub fn groups_info(&self) -> Vec<GroupInfo> {
+        self.groups
+            .iter()
+            .map(|(name, g)| {
+                // lag is unknown when entries were deleted after the last delivered one
+                let lag = match g.entries_read {
+                    Some(read) if self.max_deleted_id <= g.last_delivered_id => {
+                        Some(self.entries_added.saturating_sub(read))
+                    }
+                    _ => None,
+                };
+                GroupInfo {
+                    name: name.clone(),
+                    consumers: g.consumers.len(),
+                    pending: g.pending.len(),
+                    last_delivered_id: g.last_delivered_id,
+                    entries_read: g.entries_read,
+                    lag,
+                }
+            })
+            .collect()
+    }
+
+    pub fn consumers_info(&self, key: &str, group: &str) -> Result<Vec<ConsumerInfo>, StreamError> {
+        let now = now_ms();
+        let g = self
+            .groups
+            .get(group)
+            .ok_or_else(|| StreamError::NoGroup(key.to_string(), group.to_string()))?;
+        Ok(g.consumers
+            .iter()
+            .map(|(name, c)| ConsumerInfo {
+                name: name.clone(),
+                pending: c.pending.len(),
+                idle: now.saturating_sub(c.seen_time),
+                inactive: c.active_time.map(|t| now.saturating_sub(t)),
+            })
+            .collect())
+    }
+}
+
+impl Backend {
+    pub fn xgroup_create(
+        &self,
+        key: String,
+        group: String,
+        id: Option<StreamId>,
+        mkstream: bool,
+        entries_read: Option<u64>,
+    ) -> Result<(), StreamError> {
+        if !mkstream && !self.stream.contains_key(&key) {
+            return Err(StreamError::NoSuchKey);
+        }
+        self.value_or_default(&self.stream, key)
+            .create_group(group, id, entries_read)
+    }
+
+    pub fn xgroup_setid(
+        &self,
+        key: &str,
+        group: &str,
+        id: Option<StreamId>,
+        entries_read: Option<u64>,
+    ) -> Result<(), StreamError> {
+        match self.stream.get_mut(key) {
+            Some(mut stream) => stream.set_group_id(key, group, id, entries_read),
+            None => Err(StreamError::NoSuchKey),
+        }
+    }
+
+    pub fn xgroup_destroy(&self, key: &str, group: &str) -> Result<bool, StreamError> {
+        match self.stream.get_mut(key) {
+            Some(mut stream) => Ok(stream.groups.remove(group).is_some()),
+            None => Err(StreamError::NoSuchKey),
+        }
+    }
+
+    pub fn xgroup_createconsumer(
+        &self,
+        key: &str,
+        group: &str,
+        consumer: &str,
+    ) -> Result<bool, StreamError> {
+        let mut stream = self.stream.get_mut(key).ok_or(StreamError::NoSuchKey)?;
+        let g = stream.group_mut(key, group)?;
+        if g.consumers.contains_key(consumer) {
+            return Ok(false);
+        }
+        g.consumer(consumer, now_ms());
+        Ok(true)
+    }
+
+    /// Delete a consumer, returns the number of pending entries it had.
+    pub fn xgroup_delconsumer(
+        &self,
+        key: &str,
+        group: &str,
+        consumer: &str,
+    ) -> Result<usize, StreamError> {
+        let mut stream = self.stream.get_mut(key).ok_or(StreamError::NoSuchKey)?;
+        let g = stream.group_mut(key, group)?;
+        match g.consumers.remove(consumer) {
+            Some(c) => {
+                for id in &c.pending {
+                    g.pending.remove(id);
+                }
+                Ok(c.pending.len())
+            }
+            None => Ok(0),
+        }
+    }
+
+    /// Fails with NOGROUP unless the stream at `key` has the group.
+    pub fn xgroup_check(&self, key: &str, group: &str) -> Result<(), StreamError> {
+        match self.stream.get(key) {
+            Some(stream) if stream.groups.contains_key(group) => Ok(()),
+            _ => Err(StreamError::NoGroup(key.to_string(), group.to_string())),
+        }
+    }
+
+    pub fn xreadgroup(
+        &self,
+        key: &str,
+        group: &str,
+        consumer: &str,
+        id: Option<StreamId>,
+        count: Option<usize>,
+        noack: bool,
+    ) -> Result<Vec<GroupEntry>, StreamError> {
+        match self.stream.get_mut(key) {
+            Some(mut stream) => stream.read_group(key, group, consumer, id, count, noack),
+            None => Err(StreamError::NoGroup(key.to_string(), group.to_string())),
+        }
+    }
+
+    pub fn xack(&self, key: &str, group: &str, ids: &[StreamId]) -> usize {
+        self.stream
+            .get_mut(key)
+            .map(|mut v| v.ack(group, ids))
+            .unwrap_or(0)
+    }
+
+    pub fn xpending_summary(&self, key: &str, group: &str) -> Result<PendingSummary, StreamError> {
+        match self.stream.get(key) {
+            Some(stream) => stream.pending_summary(key, group),
+            None => Err(StreamError::NoGroup(key.to_string(), group.to_string())),
+        }
+    }
+
+    pub fn xpending(
+        &self,
+        key: &str,
+        group: &str,
+        filter: &PendingFilter,
+    ) -> Result<Vec<PendingDetail>, StreamError> {
+        match self.stream.get(key) {
+            Some(stream) => stream.pending(key, group, filter),
+            None => Err(StreamError::NoGroup(key.to_string(), group.to_string())),
+        }
+    }
+
+    pub fn xclaim(
+        &self,
+        key: &str,
+        group: &str,
+        consumer: &str,
+        min_idle: u64,
+        ids: &[StreamId],
+        opts: &ClaimOptions,
+    ) -> Result<Vec<StreamEntry>, StreamError> {
+        match self.stream.get_mut(key) {
+            Some(mut stream) => stream.claim(key, group, consumer, min_idle, ids, opts),
+            None => Err(StreamError::NoGroup(key.to_string(), group.to_string())),
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn xautoclaim(
+        &self,
+        key: &str,
+        group: &str,
+        consumer: &str,
+        min_idle: u64,
+        start: StreamId,
+        count: usize,
+        justid: bool,
+    ) -> Result<AutoClaim, StreamError> {
+        match self.stream.get_mut(key) {
+            Some(mut stream) => {
+                stream.auto_claim(key, group, consumer, min_idle, start, count, justid)
+            }
+            None => Err(StreamError::NoGroup(key.to_string(), group.to_string())),
+        }
+    }
+
+    pub fn xinfo_stream(&self, key: &str) -> Option<StreamInfo> {
+        self.stream.get(key).map(|v| v.info())
+    }
+
+    pub fn xinfo_groups(&self, key: &str) -> Option<Vec<GroupInfo>> {
+        self.stream.get(key).map(|v| v.groups_info())
+    }
+
+    pub fn xinfo_consumers(
+        &self,
+        key: &str,
+        group: &str,
+    ) -> Result<Vec<ConsumerInfo>, StreamError> {
+        match self.stream.get(key) {
+            Some(stream) => stream.consumers_info(key, group),
+            None => Err(StreamError::NoGroup(key.to_string(), group.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::XAddId;
+
+    fn stream_with(n: u64) -> Stream {
+        let mut stream = Stream::new();
+        for i in 1..=n {
+            stream
+                .add(
+                    XAddId::Explicit(StreamId::new(i, 0)),
+                    vec![("n".to_string(), i.to_string())],
+                )
+                .unwrap();
+        }
+        stream
+    }
+
+    fn ids(entries: &[GroupEntry]) -> Vec<u64> {
+        entries.iter().map(|(id, _)| id.ms).collect()
+    }
+
+    #[test]
+    fn test_read_group_and_ack() {
+        let mut stream = stream_with(3);
+        stream
+            .create_group("g".to_string(), Some(StreamId::MIN), None)
+            .unwrap();
+        assert_eq!(
+            stream.create_group("g".to_string(), None, None),
+            Err(StreamError::BusyGroup)
+        );
+
+        let ret = stream
+            .read_group("s", "g", "alice", None, Some(2), false)
+            .unwrap();
+        assert_eq!(ids(&ret), [1, 2]);
+        let ret = stream
+            .read_group("s", "g", "bob", None, None, false)
+            .unwrap();
+        assert_eq!(ids(&ret), [3]);
+        assert!(stream
+            .read_group("s", "g", "bob", None, None, false)
+            .unwrap()
+            .is_empty());
+
+        // history of alice's pending entries
+        let ret = stream
+            .read_group("s", "g", "alice", Some(StreamId::MIN), None, false)
+            .unwrap();
+        assert_eq!(ids(&ret), [1, 2]);
+
+        assert_eq!(
+            stream.ack("g", &[StreamId::new(1, 0), StreamId::new(9, 0)]),
+            1
+        );
+        let summary = stream.pending_summary("s", "g").unwrap();
+        assert_eq!(summary.count, 2);
+        assert_eq!(summary.min, Some(StreamId::new(2, 0)));
+        assert_eq!(
+            summary.consumers,
+            [("alice".to_string(), 1), ("bob".to_string(), 1)]
+        );
+
+        let info = stream.groups_info();
+        assert_eq!(info[0].entries_read, Some(3));
+        assert_eq!(info[0].lag, Some(0));
+    }
+
+    #[test]
+    fn test_claim() {
+        let mut stream = stream_with(3);
+        stream
+            .create_group("g".to_string(), Some(StreamId::MIN), None)
+            .unwrap();
+        stream
+            .read_group("s", "g", "alice", None, None, false)
+            .unwrap();
+
+        let ids = [StreamId::new(1, 0), StreamId::new(2, 0)];
+        let claimed = stream
+            .claim("s", "g", "bob", 60_000, &ids, &ClaimOptions::default())
+            .unwrap();
+        assert!(claimed.is_empty());
+
+        let claimed = stream
+            .claim("s", "g", "bob", 0, &ids, &ClaimOptions::default())
+            .unwrap();
+        assert_eq!(claimed.len(), 2);
+        let filter = PendingFilter {
+            min_idle: 0,
+            start: StreamId::MIN,
+            end: StreamId::MAX,
+            count: 10,
+            consumer: Some("bob".to_string()),
+        };
+        let pending = stream.pending("s", "g", &filter).unwrap();
+        assert_eq!(pending.len(), 2);
+        assert_eq!(pending[0].delivery_count, 2);
+
+        // deleted entries are reported and dropped from the PEL
+        stream.delete(&[StreamId::new(3, 0)]);
+        let ret = stream
+            .auto_claim("s", "g", "carol", 0, StreamId::MIN, 1, false)
+            .unwrap();
+        assert_eq!(ret.claimed.len(), 1);
+        assert_eq!(ret.cursor, StreamId::new(2, 0));
+        let ret = stream
+            .auto_claim("s", "g", "carol", 0, ret.cursor, 10, true)
+            .unwrap();
+        assert_eq!(ret.claimed.len(), 1);
+        assert_eq!(ret.deleted, [StreamId::new(3, 0)]);
+        assert_eq!(ret.cursor, StreamId::MIN);
+        assert_eq!(stream.pending_summary("s", "g").unwrap().count, 2);
+    }
+}
@@ -0,0 +1,675 @@
+use std::collections::{BTreeSet, HashMap};
+
+use ordered_float::OrderedFloat;
+use thiserror::Error;
+
+use super::{Backend, ScanMap};
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ZSetError {
+    #[error("ERR resulting score is not a number (NaN)")]
+    NaN,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SortedSet {
+    // also gives the order of ZSCAN
+    scores: ScanMap<String, f64>,
+    ordered: BTreeSet<(OrderedFloat<f64>, String)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScoreBound {
+    Inclusive(f64),
+    Exclusive(f64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LexBound {
+    Min,
+    Max,
+    Inclusive(String),
+    Exclusive(String),
+}
+
+/// Which elements a range query selects. Bounds are always stored as
+/// (low, high), reverse iteration is expressed with `ZRangeSpec::rev`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ZRangeBy {
+    Rank(i64, i64),
+    Score(ScoreBound, ScoreBound),
+    Lex(LexBound, LexBound),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZRangeSpec {
+    pub by: ZRangeBy,
+    pub rev: bool,
+    pub limit: Option<(i64, i64)>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ZAddOptions {
+    pub nx: bool,
+    pub xx: bool,
+    pub gt: bool,
+    pub lt: bool,
+    pub ch: bool,
+    pub incr: bool,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Aggregate {
+    #[default]
+    Sum,
+    Min,
+    Max,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ZSetOp {
+    Union,
+    Inter,
+    Diff,
+}
+
+/// Result of a single member update in `ZADD`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ZAddOutcome {
+    pub score: f64,
+    pub added: bool,
+    pub updated: bool,
+}
+
+impl ScoreBound {
+    fn allows_min(&self, score: f64) -> bool {
+        match *self {
+            ScoreBound::Inclusive(v) => score >= v,
+            ScoreBound::Exclusive(v) => score > v,
+        }
+    }
+
+    fn allows_max(&self, score: f64) -> bool {
+        match *self {
+            ScoreBound::Inclusive(v) => score <= v,
+            ScoreBound::Exclusive(v) => score < v,
+        }
+    }
+}
+
+impl LexBound {
+    fn allows_min(&self, member: &str) -> bool {
+        match self {
+            LexBound::Min => true,
+            LexBound::Max => false,
+            LexBound::Inclusive(v) => member >= v.as_str(),
+            LexBound::Exclusive(v) => member > v.as_str(),
+        }
+    }
+
+    fn allows_max(&self, member: &str) -> bool {
+        match self {
+            LexBound::Min => false,
+            LexBound::Max => true,
+            LexBound::Inclusive(v) => member <= v.as_str(),
+            LexBound::Exclusive(v) => member < v.as_str(),
+        }
+    }
+}
+
+impl Aggregate {
+    fn apply(&self, a: f64, b: f64) -> f64 {
+        match self {
+            // inf + -inf is nan, redis treats it as 0
+            Aggregate::Sum => zero_nan(a + b),
+            Aggregate::Min => a.min(b),
+            Aggregate::Max => a.max(b),
+        }
+    }
+}
+
+impl ZRangeBy {
+    fn contains(&self, score: f64, member: &str) -> bool {
+        match self {
+            ZRangeBy::Rank(_, _) => true,
+            ZRangeBy::Score(min, max) => min.allows_min(score) && max.allows_max(score),
+            ZRangeBy::Lex(min, max) => min.allows_min(member) && max.allows_max(member),
+        }
+    }
+}
+
+impl SortedSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    pub fn score(&self, member: &str) -> Option<f64> {
+        self.scores.get(member).copied()
+    }
+
+    /// Insert or update a member, returns true if the member is new.
+    pub fn insert(&mut self, member: String, score: f64) -> bool {
+        match self.scores.insert(member.clone(), score) {
+            Some(old) => {
+                self.ordered.remove(&(OrderedFloat(old), member.clone()));
+                self.ordered.insert((OrderedFloat(score), member));
+                false
+            }
+            None => {
+                self.ordered.insert((OrderedFloat(score), member));
+                true
+            }
+        }
+    }
+
+    pub fn remove(&mut self, member: &str) -> bool {
+        match self.scores.remove(member) {
+            Some(score) => {
+                self.ordered
+                    .remove(&(OrderedFloat(score), member.to_string()));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Apply `ZADD` semantics for a single member. Returns `None` when the
+    /// update was skipped because of NX/XX/GT/LT, an error when INCR makes the
+    /// score NaN, the member is then left unchanged.
+    pub fn add(
+        &mut self,
+        member: String,
+        score: f64,
+        opts: &ZAddOptions,
+    ) -> Result<Option<ZAddOutcome>, ZSetError> {
+        match self.score(&member) {
+            Some(old) => {
+                if opts.nx {
+                    return Ok(None);
+                }
+                let score = if opts.incr { old + score } else { score };
+                if score.is_nan() {
+                    return Err(ZSetError::NaN);
+                }
+                if (opts.gt && score <= old) || (opts.lt && score >= old) {
+                    return Ok(None);
+                }
+                let updated = score != old;
+                if updated {
+                    self.insert(member, score);
+                }
+                Ok(Some(ZAddOutcome {
+                    score,
+                    added: false,
+                    updated,
+                }))
+            }
+            None => {
+                if opts.xx {
+                    return Ok(None);
+                }
+                self.insert(member, score);
+                Ok(Some(ZAddOutcome {
+                    score,
+                    added: true,
+                    updated: false,
+                }))
+            }
+        }
+    }
+
+    /// A page of ZSCAN, see `ScanMap::scan`.
+    pub fn scan(&self, cursor: u64, count: usize) -> (u64, Vec<(&str, f64)>) {
+        let (next, members) = self.scores.scan(cursor, count);
+        let members = members.into_iter().map(|(m, s)| (m.as_str(), *s)).collect();
+        (next, members)
+    }
+
+    /// Iterate members in ascending (score, member) order.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&str, f64)> {
+        self.ordered.iter().map(|(s, m)| (m.as_str(), s.0))
+    }
+
+    pub fn range(&self, spec: &ZRangeSpec) -> Vec<(String, f64)> {
+        let iter: Box<dyn Iterator<Item = (&str, f64)>> = if spec.rev {
+            Box::new(self.iter().rev())
+        } else {
+            Box::new(self.iter())
+        };
+
+        let selected: Box<dyn Iterator<Item = (&str, f64)>> = match &spec.by {
+            ZRangeBy::Rank(start, stop) => match normalize_rank(*start, *stop, self.len()) {
+                Some((start, stop)) => Box::new(iter.skip(start).take(stop - start + 1)),
+                None => return vec![],
+            },
+            // matching elements are contiguous in sorted order
+            by => Box::new(
+                iter.skip_while(move |(m, s)| !by.contains(*s, m))
+                    .take_while(move |(m, s)| by.contains(*s, m)),
+            ),
+        };
+
+        let (offset, count) = match spec.limit {
+            Some((offset, _)) if offset < 0 => return vec![],
+            Some((offset, count)) if count < 0 => (offset as usize, usize::MAX),
+            Some((offset, count)) => (offset as usize, count as usize),
+            None => (0, usize::MAX),
+        };
+
+        selected
+            .skip(offset)
+            .take(count)
+            .map(|(m, s)| (m.to_string(), s))
+            .collect()
+    }
+
+    /// Remove and return up to `count` members with the lowest (or highest) scores.
+    pub fn pop(&mut self, min: bool, count: usize) -> Vec<(String, f64)> {
+        let mut ret = Vec::with_capacity(count.min(self.len()));
+        while ret.len() < count {
+            let item = if min {
+                self.ordered.pop_first()
+            } else {
+                self.ordered.pop_last()
+            };
+            match item {
+                Some((score, member)) => {
+                    self.scores.remove(&member);
+                    ret.push((member, score.0));
+                }
+                None => break,
+            }
+        }
+        ret
+    }
+
+    pub fn count(&self, by: &ZRangeBy) -> usize {
+        self.range(&ZRangeSpec {
+            by: by.clone(),
+            rev: false,
+            limit: None,
+        })
+        .len()
+    }
+
+    pub fn remove_range(&mut self, by: &ZRangeBy) -> usize {
+        let items = self.range(&ZRangeSpec {
+            by: by.clone(),
+            rev: false,
+            limit: None,
+        });
+        for (member, _) in &items {
+            self.remove(member);
+        }
+        items.len()
+    }
+}
+
+impl FromIterator<(String, f64)> for SortedSet {
+    fn from_iter<T: IntoIterator<Item = (String, f64)>>(iter: T) -> Self {
+        let mut zset = SortedSet::new();
+        for (member, score) in iter {
+            zset.insert(member, score);
+        }
+        zset
+    }
+}
+
+fn zero_nan(v: f64) -> f64 {
+    if v.is_nan() {
+        0.0
+    } else {
+        v
+    }
+}
+
+/// Combine sorted sets for ZUNION/ZINTER/ZDIFF. Missing keys are empty sets,
+/// `weights` has one entry per set (ignored for diff).
+pub fn combine(
+    op: ZSetOp,
+    sets: &[Option<SortedSet>],
+    weights: &[f64],
+    aggregate: Aggregate,
+) -> SortedSet {
+    let weight = |i: usize| weights.get(i).copied().unwrap_or(1.0);
+    if op == ZSetOp::Union {
+        let mut ret: HashMap<String, f64> = HashMap::new();
+        for (i, set) in sets.iter().enumerate() {
+            for (m, s) in set.iter().flat_map(|v| v.iter()) {
+                let s = zero_nan(s * weight(i));
+                ret.entry(m.to_string())
+                    .and_modify(|v| *v = aggregate.apply(*v, s))
+                    .or_insert(s);
+            }
+        }
+        return ret.into_iter().collect();
+    }
+
+    // an intersection or a difference with a missing first set is empty
+    let mut sets = sets.iter().enumerate();
+    let Some((_, Some(first))) = sets.next() else {
+        return SortedSet::new();
+    };
+    if op == ZSetOp::Inter {
+        let others: Vec<_> = sets.collect();
+        first
+            .iter()
+            .filter_map(|(m, s)| {
+                let mut score = zero_nan(s * weight(0));
+                for (i, set) in &others {
+                    let s = set.as_ref()?.score(m)?;
+                    score = aggregate.apply(score, zero_nan(s * weight(*i)));
+                }
+                Some((m.to_string(), score))
+            })
+            .collect()
+    } else {
+        let others: Vec<_> = sets.filter_map(|(_, v)| v.as_ref()).collect();
+        first
+            .iter()
+            .filter(|(m, _)| others.iter().all(|v| v.score(m).is_none()))
+            .map(|(m, s)| (m.to_string(), s))
+            .collect()
+    }
+}
+
+// convert redis style (possibly negative) rank indexes into an inclusive range
+fn normalize_rank(start: i64, stop: i64, len: usize) -> Option<(usize, usize)> {
+    let len = len as i64;
+    let start = if start < 0 {
+        (len + start).max(0)
+    } else {
+        start
+    };
+    let stop = if stop < 0 {
+        len + stop
+    } else {
+        stop.min(len - 1)
+    };
+    if start > stop || start >= len {
+        None
+    } else {
+        Some((start as usize, stop as usize))
+    }
+}
+
+impl Backend {
+    pub fn zadd(
+        &self,
+        key: String,
+        members: Vec<(f64, String)>,
+        opts: &ZAddOptions,
+    ) -> Result<Vec<Option<ZAddOutcome>>, ZSetError> {
+        let mut zset = self.value_or_default(&self.zset, key.clone());
+        let ret: Result<Vec<_>, _> = members
+            .into_iter()
+            .map(|(score, member)| zset.add(member, score, opts))
+            .collect();
+        let empty = zset.is_empty();
+        drop(zset);
+        if empty {
+            self.remove_value_if(&self.zset, &key, |v| v.is_empty());
+        } else if ret.iter().flatten().flatten().any(|v| v.added) {
+            self.signal_key_ready(&key);
+        }
+        ret
+    }
+
+    pub fn zpop(&self, key: &str, min: bool, count: usize) -> Vec<(String, f64)> {
+        let ret = match self.zset.get_mut(key) {
+            Some(mut zset) => zset.pop(min, count),
+            None => vec![],
+        };
+        self.remove_value_if(&self.zset, key, |v| v.is_empty());
+        ret
+    }
+
+    pub fn zrem(&self, key: &str, members: &[String]) -> usize {
+        let removed = match self.zset.get_mut(key) {
+            Some(mut zset) => members.iter().filter(|m| zset.remove(m)).count(),
+            None => 0,
+        };
+        self.remove_value_if(&self.zset, key, |v| v.is_empty());
+        removed
+    }
+
+    pub fn zscore(&self, key: &str, member: &str) -> Option<f64> {
+        self.zset.get(key).and_then(|v| v.score(member))
+    }
+
+    pub fn zcard(&self, key: &str) -> usize {
+        self.zset.get(key).map(|v| v.len()).unwrap_or(0)
+    }
+
+    pub fn zrange(&self, key: &str, spec: &ZRangeSpec) -> Vec<(String, f64)> {
+        self.zset
+            .get(key)
+            .map(|v| v.range(spec))
+            .unwrap_or_default()
+    }
+
+    pub fn zrangestore(&self, dst: String, src: &str, spec: &ZRangeSpec) -> usize {
+        let items = self.zrange(src, spec);
+        let len = items.len();
+        if items.is_empty() {
+            self.remove_value_if(&self.zset, &dst, |_| true);
+        } else {
+            self.insert_value(&self.zset, dst.clone(), items.into_iter().collect());
+            self.signal_key_ready(&dst);
+        }
+        len
+    }
+
+    pub fn zcombine(
+        &self,
+        op: ZSetOp,
+        keys: &[String],
+        weights: &[f64],
+        aggregate: Aggregate,
+    ) -> SortedSet {
+        // snapshot the inputs so no shard lock is held while writing the result
+        let sets: Vec<_> = keys
+            .iter()
+            .map(|k| self.zset.get(k).map(|v| v.clone()))
+            .collect();
+        combine(op, &sets, weights, aggregate)
+    }
+
+    pub fn zcombinestore(
+        &self,
+        dst: String,
+        op: ZSetOp,
+        keys: &[String],
+        weights: &[f64],
+        aggregate: Aggregate,
+    ) -> usize {
+        let zset = self.zcombine(op, keys, weights, aggregate);
+        let len = zset.len();
+        if zset.is_empty() {
+            self.remove_value_if(&self.zset, &dst, |_| true);
+        } else {
+            self.insert_value(&self.zset, dst.clone(), zset);
+            self.signal_key_ready(&dst);
+        }
+        len
+    }
+
+    pub fn zcount(&self, key: &str, by: &ZRangeBy) -> usize {
+        self.zset.get(key).map(|v| v.count(by)).unwrap_or(0)
+    }
+
+    pub fn zremrange(&self, key: &str, by: &ZRangeBy) -> usize {
+        let removed = match self.zset.get_mut(key) {
+            Some(mut zset) => zset.remove_range(by),
+            None => 0,
+        };
+        self.remove_value_if(&self.zset, key, |v| v.is_empty());
+        removed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> SortedSet {
+        [("a", 1.0), ("b", 2.0), ("c", 3.0), ("d", 4.0)]
+            .into_iter()
+            .map(|(m, s)| (m.to_string(), s))
+            .collect()
+    }
+
+    fn members(items: Vec<(String, f64)>) -> Vec<String> {
+        items.into_iter().map(|(m, _)| m).collect()
+    }
+
+    #[test]
+    fn test_range_by_rank() {
+        let zset = sample();
+        let spec = ZRangeSpec {
+            by: ZRangeBy::Rank(1, -2),
+            rev: false,
+            limit: None,
+        };
+        assert_eq!(members(zset.range(&spec)), ["b", "c"]);
+
+        let spec = ZRangeSpec {
+            by: ZRangeBy::Rank(0, 0),
+            rev: true,
+            limit: None,
+        };
+        assert_eq!(members(zset.range(&spec)), ["d"]);
+
+        let spec = ZRangeSpec {
+            by: ZRangeBy::Rank(5, 10),
+            rev: false,
+            limit: None,
+        };
+        assert!(zset.range(&spec).is_empty());
+    }
+
+    #[test]
+    fn test_range_by_score() {
+        let zset = sample();
+        let spec = ZRangeSpec {
+            by: ZRangeBy::Score(
+                ScoreBound::Exclusive(1.0),
+                ScoreBound::Inclusive(f64::INFINITY),
+            ),
+            rev: false,
+            limit: Some((1, 1)),
+        };
+        assert_eq!(members(zset.range(&spec)), ["c"]);
+
+        let spec = ZRangeSpec {
+            by: ZRangeBy::Score(ScoreBound::Inclusive(2.0), ScoreBound::Exclusive(4.0)),
+            rev: true,
+            limit: None,
+        };
+        assert_eq!(members(zset.range(&spec)), ["c", "b"]);
+    }
+
+    #[test]
+    fn test_range_by_lex() {
+        let zset: SortedSet = ["a", "b", "c", "d"]
+            .into_iter()
+            .map(|m| (m.to_string(), 0.0))
+            .collect();
+        let by = ZRangeBy::Lex(LexBound::Exclusive("a".to_string()), LexBound::Max);
+        assert_eq!(zset.count(&by), 3);
+
+        let spec = ZRangeSpec {
+            by: ZRangeBy::Lex(LexBound::Min, LexBound::Inclusive("c".to_string())),
+            rev: true,
+            limit: None,
+        };
+        assert_eq!(members(zset.range(&spec)), ["c", "b", "a"]);
+    }
+
+    #[test]
+    fn test_add_options() {
+        let mut zset = sample();
+        let opts = ZAddOptions {
+            gt: true,
+            ..Default::default()
+        };
+        assert_eq!(zset.add("a".to_string(), 0.5, &opts), Ok(None));
+        let opts = ZAddOptions {
+            incr: true,
+            ..Default::default()
+        };
+        let ret = zset.add("a".to_string(), 2.0, &opts).unwrap().unwrap();
+        assert_eq!(ret.score, 3.0);
+        assert_eq!(zset.score("a"), Some(3.0));
+
+        // +inf then -inf makes NaN, which is refused
+        zset.add("inf".to_string(), f64::INFINITY, &opts).unwrap();
+        assert_eq!(
+            zset.add("inf".to_string(), f64::NEG_INFINITY, &opts),
+            Err(ZSetError::NaN)
+        );
+        assert_eq!(zset.score("inf"), Some(f64::INFINITY));
+    }
+
+    #[test]
+    fn test_combine() {
+        let a = sample();
+        let b: SortedSet = [("b", 10.0), ("c", 1.0), ("e", 5.0)]
+            .into_iter()
+            .map(|(m, s)| (m.to_string(), s))
+            .collect();
+        let sets = [Some(a), Some(b)];
+
+        let ret = combine(ZSetOp::Union, &sets, &[2.0, 1.0], Aggregate::Sum);
+        assert_eq!(ret.len(), 5);
+        assert_eq!(ret.score("b"), Some(14.0));
+        assert_eq!(ret.score("e"), Some(5.0));
+
+        let ret = combine(ZSetOp::Inter, &sets, &[], Aggregate::Max);
+        assert_eq!(ret.iter().collect::<Vec<_>>(), [("c", 3.0), ("b", 10.0)]);
+
+        let ret = combine(ZSetOp::Diff, &sets, &[], Aggregate::Sum);
+        assert_eq!(ret.iter().collect::<Vec<_>>(), [("a", 1.0), ("d", 4.0)]);
+
+        let ret = combine(ZSetOp::Inter, &[sets[0].clone(), None], &[], Aggregate::Sum);
+        assert!(ret.is_empty());
+        let ret = combine(ZSetOp::Diff, &[None, sets[0].clone()], &[], Aggregate::Sum);
+        assert!(ret.is_empty());
+
+        // a missing set is empty for the union, wherever it is
+        let ret = combine(ZSetOp::Union, &[None, sets[1].clone()], &[], Aggregate::Sum);
+        assert_eq!(ret.len(), 3);
+        assert_eq!(ret.score("b"), Some(10.0));
+    }
+
+    #[test]
+    fn test_pop() {
+        let mut zset = sample();
+        assert_eq!(
+            zset.pop(true, 2),
+            [("a".to_string(), 1.0), ("b".to_string(), 2.0)]
+        );
+        assert_eq!(
+            zset.pop(false, 5),
+            [("d".to_string(), 4.0), ("c".to_string(), 3.0)]
+        );
+        assert!(zset.is_empty());
+    }
+
+    #[test]
+    fn test_remove_range() {
+        let mut zset = sample();
+        let by = ZRangeBy::Score(ScoreBound::Inclusive(2.0), ScoreBound::Inclusive(3.0));
+        assert_eq!(zset.remove_range(&by), 2);
+        assert_eq!(zset.len(), 2);
+        assert_eq!(zset.score("b"), None);
+    }
+}
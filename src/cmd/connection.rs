@@ -0,0 +1,182 @@
+use super::{
+    extract_strings, parse_int, validate_command_at_least, CommandError, CommandExecutor, Hello,
+    Ping,
+};
+use crate::{
+    backend::{Backend, ClientError, Protocol},
+    BulkString, RespArray, RespFrame, RespMap, SimpleError, SimpleString,
+};
+
+const SERVER_VERSION: &str = "7.2.0";
+
+impl Hello {
+    fn run(self, backend: &Backend) -> Result<RespFrame, ClientError> {
+        let protocol = self.protover.map(Protocol::from_version).transpose()?;
+        if let Some((user, password)) = &self.auth {
+            backend.authenticate(user, password)?;
+        }
+        if let Some(name) = self.setname {
+            backend.set_client_name(name)?;
+        }
+        if let Some(protocol) = protocol {
+            backend.set_protocol(protocol);
+        }
+
+        let mut map = RespMap::new();
+        map.insert(BulkString::new("server"), BulkString::new("redis"));
+        map.insert(BulkString::new("version"), BulkString::new(SERVER_VERSION));
+        map.insert(
+            BulkString::new("proto"),
+            RespFrame::Integer(backend.protocol().version()),
+        );
+        map.insert(
+            BulkString::new("id"),
+            RespFrame::Integer(backend.client_id() as i64),
+        );
+        map.insert(BulkString::new("mode"), BulkString::new("standalone"));
+        map.insert(BulkString::new("role"), BulkString::new("master"));
+        map.insert(BulkString::new("modules"), RespArray::new([]));
+        Ok(map.into())
+    }
+}
+
+impl CommandExecutor for Hello {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        self.run(backend)
+            .unwrap_or_else(|e| SimpleError::new(e.to_string()).into())
+    }
+}
+
+impl CommandExecutor for Ping {
+    fn execute(self, _: &Backend) -> RespFrame {
+        match self.message {
+            Some(message) => BulkString::new(message).into(),
+            None => SimpleString::new("PONG").into(),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for Ping {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["ping"], 0)?;
+
+        let mut args = extract_strings(value, 1)?.into_iter();
+        let message = args.next();
+        if args.next().is_some() {
+            return Err(CommandError::InvalidCommand(
+                "ping command must have at most 1 argument".to_string(),
+            ));
+        }
+        Ok(Ping { message })
+    }
+}
+
+impl TryFrom<RespArray> for Hello {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["hello"], 0)?;
+
+        let mut args = extract_strings(value, 1)?.into_iter();
+        let protover = match args.next() {
+            Some(v) => Some(parse_int(&v).map_err(|_| {
+                CommandError::InvalidArgument(
+                    "Protocol version is not an integer or out of range".to_string(),
+                )
+            })?),
+            None => None,
+        };
+
+        let mut auth = None;
+        let mut setname = None;
+        while let Some(opt) = args.next() {
+            match (opt.to_ascii_lowercase().as_str(), args.len()) {
+                ("auth", 2..) => auth = args.next().zip(args.next()),
+                ("setname", 1..) => setname = args.next(),
+                _ => {
+                    return Err(CommandError::InvalidArgument(format!(
+                        "Syntax error in HELLO option '{}'",
+                        opt
+                    )))
+                }
+            }
+        }
+
+        Ok(Hello {
+            protover,
+            auth,
+            setname,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RespDecode;
+    use bytes::BytesMut;
+
+    fn decode(cmd: &[u8]) -> anyhow::Result<RespArray> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(cmd);
+        Ok(RespArray::decode(&mut buf)?)
+    }
+
+    #[test]
+    fn test_hello_from_resp_array() -> anyhow::Result<()> {
+        let frame = decode(b"*6\r\n$5\r\nhello\r\n$1\r\n3\r\n$4\r\nAUTH\r\n$7\r\ndefault\r\n$2\r\npw\r\n$7\r\nSETNAME\r\n")?;
+        assert!(Hello::try_from(frame).is_err());
+
+        let frame = decode(b"*7\r\n$5\r\nhello\r\n$1\r\n3\r\n$4\r\nAUTH\r\n$7\r\ndefault\r\n$2\r\npw\r\n$7\r\nSETNAME\r\n$3\r\napp\r\n")?;
+        let result: Hello = frame.try_into()?;
+        assert_eq!(result.protover, Some(3));
+        assert_eq!(result.auth, Some(("default".to_string(), "pw".to_string())));
+        assert_eq!(result.setname, Some("app".to_string()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_inline_ping() -> anyhow::Result<()> {
+        let mut buf = BytesMut::from(&b"PING\r\n"[..]);
+        let frame = RespArray::decode_inline(&mut buf)?;
+        let cmd: Ping = frame.try_into()?;
+        assert_eq!(
+            cmd.execute(&Backend::new()),
+            SimpleString::new("PONG").into()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_hello_cmd() {
+        let backend = Backend::new();
+        let hello = |protover| Hello {
+            protover,
+            auth: None,
+            setname: None,
+        };
+
+        assert_eq!(
+            hello(Some(4)).execute(&backend),
+            SimpleError::new("NOPROTO unsupported protocol version").into()
+        );
+        assert_eq!(backend.protocol(), Protocol::Resp2);
+
+        let ret = hello(Some(3)).execute(&backend);
+        assert_eq!(backend.protocol(), Protocol::Resp3);
+        let RespFrame::Map(map) = ret else {
+            panic!("expect a map, got {:?}", ret);
+        };
+        assert_eq!(map.get(&b"proto".into()), Some(&RespFrame::Integer(3)));
+
+        let cmd = Hello {
+            protover: Some(2),
+            auth: Some(("admin".to_string(), "pw".to_string())),
+            setname: None,
+        };
+        assert!(matches!(cmd.execute(&backend), RespFrame::Error(_)));
+        assert_eq!(backend.protocol(), Protocol::Resp3);
+    }
+}
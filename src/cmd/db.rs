@@ -0,0 +1,187 @@
+use super::{
+    command_name, extract_strings, parse_int, validate_command, validate_command_at_least,
+    CommandError, CommandExecutor, DbSize, FlushDb, Move, Select, SwapDb, RESP_OK,
+};
+use crate::{
+    backend::{Backend, DbError},
+    RespArray, RespFrame, SimpleError,
+};
+
+fn db_index(index: i64) -> Result<usize, DbError> {
+    usize::try_from(index).map_err(|_| DbError::OutOfRange)
+}
+
+fn error_reply(e: DbError) -> RespFrame {
+    SimpleError::new(e.to_string()).into()
+}
+
+impl CommandExecutor for Select {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match db_index(self.index).and_then(|index| backend.select(index)) {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => error_reply(e),
+        }
+    }
+}
+
+impl CommandExecutor for Move {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match db_index(self.index).and_then(|index| backend.move_key(&self.key, index)) {
+            Ok(moved) => RespFrame::Integer(moved as i64),
+            Err(e) => error_reply(e),
+        }
+    }
+}
+
+impl CommandExecutor for SwapDb {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let ret = db_index(self.a)
+            .and_then(|a| Ok((a, db_index(self.b)?)))
+            .and_then(|(a, b)| backend.swapdb(a, b));
+        match ret {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => error_reply(e),
+        }
+    }
+}
+
+impl CommandExecutor for FlushDb {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if self.all {
+            backend.flushall(self.lazy);
+        } else {
+            backend.flushdb(self.lazy);
+        }
+        RESP_OK.clone()
+    }
+}
+
+impl CommandExecutor for DbSize {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.dbsize() as i64)
+    }
+}
+
+impl TryFrom<RespArray> for Select {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["select"], 1)?;
+
+        let args = extract_strings(value, 1)?;
+        Ok(Select {
+            index: parse_int(&args[0])?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Move {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["move"], 2)?;
+
+        let mut args = extract_strings(value, 1)?.into_iter();
+        let key = args.next().unwrap_or_default();
+        Ok(Move {
+            key,
+            index: parse_int(&args.next().unwrap_or_default())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for SwapDb {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["swapdb"], 2)?;
+
+        let args = extract_strings(value, 1)?;
+        Ok(SwapDb {
+            a: parse_int(&args[0])?,
+            b: parse_int(&args[1])?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for FlushDb {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, all) = match command_name(&value).as_str() {
+            "flushdb" => ("flushdb", false),
+            "flushall" => ("flushall", true),
+            v => return Err(CommandError::InvalidCommand(v.to_string())),
+        };
+        validate_command_at_least(&value, &[name], 0)?;
+
+        let mut args = extract_strings(value, 1)?.into_iter();
+        let lazy = match args.next() {
+            None => false,
+            Some(mode) if mode.eq_ignore_ascii_case("async") => true,
+            Some(mode) if mode.eq_ignore_ascii_case("sync") => false,
+            Some(_) => {
+                return Err(CommandError::InvalidArgument("syntax error".to_string()));
+            }
+        };
+        if args.next().is_some() {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        Ok(FlushDb { all, lazy })
+    }
+}
+
+impl TryFrom<RespArray> for DbSize {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["dbsize"], 0)?;
+        Ok(DbSize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BulkString, RespDecode};
+    use bytes::BytesMut;
+
+    fn decode(cmd: &[u8]) -> anyhow::Result<RespArray> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(cmd);
+        Ok(RespArray::decode(&mut buf)?)
+    }
+
+    #[test]
+    fn test_flushall_from_resp_array() -> anyhow::Result<()> {
+        let frame = decode(b"*2\r\n$8\r\nFLUSHALL\r\n$5\r\nASYNC\r\n")?;
+        let result: FlushDb = frame.try_into()?;
+        assert!(result.all);
+        assert!(result.lazy);
+
+        let frame = decode(b"*2\r\n$7\r\nflushdb\r\n$4\r\nsoon\r\n")?;
+        assert!(FlushDb::try_from(frame).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_select_cmd() {
+        let backend = Backend::new();
+        backend.set("k".to_string(), BulkString::new("v").into());
+
+        assert!(matches!(
+            Select { index: 16 }.execute(&backend),
+            RespFrame::Error(_)
+        ));
+        assert!(matches!(
+            Select { index: -1 }.execute(&backend),
+            RespFrame::Error(_)
+        ));
+        assert_eq!(Select { index: 1 }.execute(&backend), RESP_OK.clone());
+
+        let backend = backend.current();
+        assert_eq!(DbSize.execute(&backend), RespFrame::Integer(0));
+        assert_eq!(SwapDb { a: 0, b: 1 }.execute(&backend), RESP_OK.clone());
+        assert_eq!(DbSize.execute(&backend.current()), RespFrame::Integer(1));
+    }
+}
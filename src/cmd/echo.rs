@@ -16,7 +16,7 @@ impl TryFrom<RespArray> for Echo {
         let mut args = extract_args(value, 1)?.into_iter();
         match args.next() {
             Some(RespFrame::BulkString(BulkString(Some(v)))) => Ok(Echo {
-                value: String::from_utf8(v)?,
+                value: String::from_utf8(v.into())?,
             }),
             _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
         }
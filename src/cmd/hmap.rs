@@ -1,4 +1,7 @@
-use super::{CommandExecutor, HGetAll, HMGet, HSet, RESP_OK};
+use super::{
+    extract_strings, parse_scan_args, scan_reply, validate_command_at_least, CommandExecutor,
+    HGetAll, HMGet, HScan, HSet, RESP_OK,
+};
 use crate::{
     backend::Backend,
     cmd::{extract_args, validate_command, CommandError, HGet},
@@ -20,10 +23,9 @@ impl CommandExecutor for HGetAll {
         match hmap {
             Some(hmap) => {
                 let mut ret = Vec::with_capacity(hmap.len() * 2);
-                for v in hmap.iter() {
-                    let key = v.key().to_owned();
-                    ret.push(BulkString::new(key).into());
-                    ret.push(v.value().clone())
+                for (key, value) in hmap.iter() {
+                    ret.push(BulkString::new(key.as_str()).into());
+                    ret.push(value.clone())
                 }
                 RespArray::new(ret).into()
             }
@@ -65,14 +67,28 @@ impl TryFrom<RespArray> for HGet {
                 Some(RespFrame::BulkString(BulkString(Some(key)))),
                 Some(RespFrame::BulkString(BulkString(Some(field)))),
             ) => Ok(HGet {
-                key: String::from_utf8(key)?,
-                field: String::from_utf8(field)?,
+                key: String::from_utf8(key.into())?,
+                field: String::from_utf8(field.into())?,
             }),
             _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
         }
     }
 }
 
+impl CommandExecutor for HScan {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let (cursor, fields) = backend.hscan(&self.key, self.cursor, &self.opts);
+        let items = fields
+            .into_iter()
+            .flat_map(|(f, v)| {
+                let value = (!self.novalues).then_some(v);
+                std::iter::once(BulkString::new(f).into()).chain(value)
+            })
+            .collect();
+        scan_reply(cursor, items)
+    }
+}
+
 impl TryFrom<RespArray> for HGetAll {
     type Error = CommandError;
 
@@ -82,7 +98,7 @@ impl TryFrom<RespArray> for HGetAll {
         let mut args = extract_args(value, 1)?.into_iter();
         match args.next() {
             Some(RespFrame::BulkString(BulkString(Some(key)))) => Ok(HGetAll {
-                key: String::from_utf8(key)?,
+                key: String::from_utf8(key.into())?,
             }),
             _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
         }
@@ -100,7 +116,7 @@ impl TryFrom<RespArray> for HMGet {
         let mut fields = Vec::with_capacity(capacity);
 
         let key = if let Some(RespFrame::BulkString(BulkString(Some(k)))) = args.next() {
-            String::from_utf8(k)?
+            String::from_utf8(k.into())?
         } else {
             return Err(CommandError::InvalidArgument("Invalid key".to_string()));
         };
@@ -108,7 +124,7 @@ impl TryFrom<RespArray> for HMGet {
         for frame in args {
             match frame {
                 RespFrame::BulkString(BulkString(Some(field))) => {
-                    fields.push(String::from_utf8(field)?)
+                    fields.push(String::from_utf8(field.into())?)
                 }
                 _ => return Err(CommandError::InvalidArgument("Invalid field".to_string())),
             };
@@ -131,8 +147,8 @@ impl TryFrom<RespArray> for HSet {
                 Some(RespFrame::BulkString(BulkString(Some(field)))),
                 Some(value),
             ) => Ok(HSet {
-                key: String::from_utf8(key)?,
-                field: String::from_utf8(field)?,
+                key: String::from_utf8(key.into())?,
+                field: String::from_utf8(field.into())?,
                 value,
             }),
             _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
@@ -140,10 +156,31 @@ impl TryFrom<RespArray> for HSet {
     }
 }
 
+impl TryFrom<RespArray> for HScan {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["hscan"], 2)?;
+
+        let mut args = extract_strings(value, 1)?.into_iter();
+        let key = args.next().unwrap_or_default();
+        let mut novalues = false;
+        let (cursor, opts) = parse_scan_args(&mut args, |opt, _| {
+            novalues |= opt == "novalues";
+            Ok(opt == "novalues")
+        })?;
+        Ok(HScan {
+            key,
+            cursor,
+            opts,
+            novalues,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use bytes::BytesMut;
-    use dashmap::DashMap;
 
     use crate::{
         backend::Backend,
@@ -194,10 +231,16 @@ mod tests {
     #[test]
     fn test_hgetall_existing_key() {
         let backend = Backend::new();
-        let hmap = DashMap::new();
-        hmap.insert("field1".to_string(), BulkString::new("value1").into());
-        hmap.insert("field2".to_string(), BulkString::new("value2").into());
-        backend.hmap.insert("myhash".to_string(), hmap);
+        backend.hset(
+            "myhash".to_string(),
+            "field1".to_string(),
+            BulkString::new("value1").into(),
+        );
+        backend.hset(
+            "myhash".to_string(),
+            "field2".to_string(),
+            BulkString::new("value2").into(),
+        );
 
         let command = HGetAll {
             key: "myhash".to_string(),
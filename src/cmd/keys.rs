@@ -0,0 +1,125 @@
+use super::{
+    extract_strings, parse_scan_args, scan_reply, validate_command, validate_command_at_least,
+    CommandError, CommandExecutor, Keys, Scan,
+};
+use crate::{
+    backend::{Backend, KeyType},
+    BulkString, RespArray, RespFrame,
+};
+
+impl CommandExecutor for Keys {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let keys = backend
+            .keys(&self.pattern)
+            .into_iter()
+            .map(|k| BulkString::new(k).into())
+            .collect::<Vec<RespFrame>>();
+        RespArray::new(keys).into()
+    }
+}
+
+impl CommandExecutor for Scan {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let (cursor, keys) = backend.scan(self.cursor, &self.opts, self.key_type);
+        let keys = keys
+            .into_iter()
+            .map(|k| BulkString::new(k).into())
+            .collect();
+        scan_reply(cursor, keys)
+    }
+}
+
+impl TryFrom<RespArray> for Keys {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["keys"], 1)?;
+
+        let mut args = extract_strings(value, 1)?.into_iter();
+        Ok(Keys {
+            pattern: args.next().unwrap_or_default(),
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Scan {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["scan"], 1)?;
+
+        let mut args = extract_strings(value, 1)?.into_iter();
+        let mut key_type = None;
+        let (cursor, opts) = parse_scan_args(&mut args, |opt, args| {
+            if opt != "type" {
+                return Ok(false);
+            }
+            let name = args.next().unwrap_or_default();
+            key_type = Some(KeyType::from_name(&name).ok_or_else(|| {
+                CommandError::InvalidArgument(format!("unknown type name '{}'", name))
+            })?);
+            Ok(true)
+        })?;
+
+        Ok(Scan {
+            cursor,
+            opts,
+            key_type,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{backend::ScanOptions, RespDecode};
+    use bytes::BytesMut;
+
+    fn decode(cmd: &[u8]) -> anyhow::Result<RespArray> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(cmd);
+        Ok(RespArray::decode(&mut buf)?)
+    }
+
+    #[test]
+    fn test_scan_from_resp_array() -> anyhow::Result<()> {
+        let frame = decode(b"*8\r\n$4\r\nscan\r\n$2\r\n17\r\n$5\r\nMATCH\r\n$3\r\nk:*\r\n$5\r\nCOUNT\r\n$3\r\n100\r\n$4\r\nTYPE\r\n$4\r\nhash\r\n")?;
+        let result: Scan = frame.try_into()?;
+        assert_eq!(result.cursor, 17);
+        assert_eq!(
+            result.opts,
+            ScanOptions {
+                pattern: Some("k:*".to_string()),
+                count: 100,
+            }
+        );
+        assert_eq!(result.key_type, Some(KeyType::Hash));
+
+        let frame = decode(b"*4\r\n$4\r\nscan\r\n$1\r\n0\r\n$4\r\nTYPE\r\n$4\r\nlist\r\n")?;
+        assert!(Scan::try_from(frame).is_err());
+        let frame = decode(b"*2\r\n$4\r\nscan\r\n$2\r\n-1\r\n")?;
+        assert!(Scan::try_from(frame).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_cmd() {
+        let backend = Backend::new();
+        backend.set("k".to_string(), BulkString::new("v").into());
+
+        let cmd = Scan {
+            cursor: 0,
+            opts: ScanOptions::default(),
+            key_type: None,
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([b"0".into(), RespArray::new([b"k".into()]).into()]).into()
+        );
+
+        let cmd = Keys {
+            pattern: "x*".to_string(),
+        };
+        assert_eq!(cmd.execute(&backend), RespArray::new([]).into());
+    }
+}
@@ -30,7 +30,7 @@ impl TryFrom<RespArray> for Get {
         let mut args = extract_args(value, 1)?.into_iter();
         match args.next() {
             Some(RespFrame::BulkString(BulkString(Some(key)))) => Ok(Get {
-                key: String::from_utf8(key)?,
+                key: String::from_utf8(key.into())?,
             }),
             _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
         }
@@ -46,7 +46,7 @@ impl TryFrom<RespArray> for Set {
         let mut args = extract_args(value, 1)?.into_iter();
         match (args.next(), args.next()) {
             (Some(RespFrame::BulkString(BulkString(Some(key)))), Some(value)) => Ok(Set {
-                key: String::from_utf8(key)?,
+                key: String::from_utf8(key.into())?,
                 value,
             }),
             _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
@@ -1,12 +1,26 @@
-use crate::{backend::Backend, RespArray, RespError, RespFrame, SimpleString};
+use crate::{
+    backend::{
+        Aggregate, Backend, ClaimOptions, KeyType, PendingFilter, ScanOptions, StreamId,
+        StreamTrim, XAddId, ZAddOptions, ZRangeBy, ZRangeSpec, ZSetOp,
+    },
+    BulkString, RespArray, RespError, RespFrame, SimpleError, SimpleString,
+};
 use enum_dispatch::enum_dispatch;
 use lazy_static::lazy_static;
+use std::{sync::Arc, time::Duration};
 use thiserror::Error;
+use tokio::{sync::Notify, time::Instant};
 
+mod connection;
+mod db;
 mod echo;
 mod hmap;
+mod keys;
 mod map;
+mod server;
 mod set;
+mod stream;
+mod zset;
 
 #[derive(Error, Debug)]
 pub enum CommandError {
@@ -20,6 +34,17 @@ pub enum CommandError {
     Utf8Error(#[from] std::string::FromUtf8Error),
 }
 
+// the reply to a request that can't be parsed, the connection goes on
+impl From<CommandError> for RespFrame {
+    fn from(e: CommandError) -> Self {
+        let msg = match e {
+            CommandError::InvalidCommand(msg) | CommandError::InvalidArgument(msg) => msg,
+            e => e.to_string(),
+        };
+        SimpleError::new(format!("ERR {}", msg)).into()
+    }
+}
+
 lazy_static! {
     static ref RESP_OK: RespFrame = SimpleString::new("OK").into();
 }
@@ -29,6 +54,18 @@ pub trait CommandExecutor {
     fn execute(self, backend: &Backend) -> RespFrame;
 }
 
+/// Commands that wait for data on a set of keys when there is nothing to serve.
+pub trait BlockingCommand: Send + Sync {
+    fn keys(&self) -> &[String];
+    /// `None` blocks forever.
+    fn timeout(&self) -> Option<Duration>;
+    /// Try to serve the command, `None` means the client should keep waiting.
+    fn try_execute(&self, backend: &Backend) -> Option<RespFrame>;
+    fn timeout_reply(&self) -> RespFrame {
+        RespArray::null().into()
+    }
+}
+
 #[enum_dispatch(CommandExecutor)]
 #[derive(Debug)]
 pub enum Command {
@@ -41,6 +78,44 @@ pub enum Command {
     Echo(Echo),
     SAdd(SAdd),
     SIsmember(SIsmember),
+    ZAdd(ZAdd),
+    ZRem(ZRem),
+    ZScore(ZScore),
+    ZCard(ZCard),
+    ZRange(ZRange),
+    ZRangeStore(ZRangeStore),
+    ZCount(ZCount),
+    ZRemRange(ZRemRange),
+    ZCombine(ZCombine),
+    ZPop(ZPop),
+    BZPop(BZPop),
+    ZMPop(ZMPop),
+    XAdd(XAdd),
+    XRange(XRange),
+    XLen(XLen),
+    XDel(XDel),
+    XTrim(XTrim),
+    XGroup(XGroup),
+    XReadGroup(XReadGroup),
+    XAck(XAck),
+    XPending(XPending),
+    XClaim(XClaim),
+    XAutoClaim(XAutoClaim),
+    XInfo(XInfo),
+    Keys(Keys),
+    Scan(Scan),
+    HScan(HScan),
+    SScan(SScan),
+    ZScan(ZScan),
+    Select(Select),
+    Move(Move),
+    SwapDb(SwapDb),
+    FlushDb(FlushDb),
+    DbSize(DbSize),
+    Hello(Hello),
+    Ping(Ping),
+    Config(Config),
+    Shutdown(Shutdown),
     Unrecognized(Unrecognized),
 }
 
@@ -99,6 +174,318 @@ pub struct HGetAll {
     key: String,
 }
 
+#[derive(Debug)]
+pub struct ZAdd {
+    key: String,
+    options: ZAddOptions,
+    members: Vec<(f64, String)>,
+}
+
+#[derive(Debug)]
+pub struct ZRem {
+    key: String,
+    members: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct ZScore {
+    key: String,
+    member: String,
+}
+
+#[derive(Debug)]
+pub struct ZCard {
+    key: String,
+}
+
+// ZRANGE and its legacy ZREVRANGE / Z[REV]RANGEBYSCORE / Z[REV]RANGEBYLEX forms
+#[derive(Debug)]
+pub struct ZRange {
+    key: String,
+    spec: ZRangeSpec,
+    withscores: bool,
+}
+
+#[derive(Debug)]
+pub struct ZRangeStore {
+    dst: String,
+    src: String,
+    spec: ZRangeSpec,
+}
+
+// ZCOUNT and ZLEXCOUNT
+#[derive(Debug)]
+pub struct ZCount {
+    key: String,
+    by: ZRangeBy,
+}
+
+// ZREMRANGEBYRANK, ZREMRANGEBYSCORE and ZREMRANGEBYLEX
+#[derive(Debug)]
+pub struct ZRemRange {
+    key: String,
+    by: ZRangeBy,
+}
+
+// ZUNION, ZINTER, ZDIFF and their STORE variants
+#[derive(Debug)]
+pub struct ZCombine {
+    op: ZSetOp,
+    dst: Option<String>,
+    keys: Vec<String>,
+    weights: Vec<f64>,
+    aggregate: Aggregate,
+    withscores: bool,
+}
+
+// ZPOPMIN and ZPOPMAX
+#[derive(Debug)]
+pub struct ZPop {
+    key: String,
+    min: bool,
+    count: Option<usize>,
+}
+
+// BZPOPMIN and BZPOPMAX
+#[derive(Debug)]
+pub struct BZPop {
+    keys: Vec<String>,
+    min: bool,
+    timeout: Option<Duration>,
+}
+
+// ZMPOP and BZMPOP
+#[derive(Debug)]
+pub struct ZMPop {
+    keys: Vec<String>,
+    min: bool,
+    count: usize,
+    blocking: bool,
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug)]
+pub struct XAdd {
+    key: String,
+    nomkstream: bool,
+    trim: Option<StreamTrim>,
+    id: XAddId,
+    fields: Vec<(String, String)>,
+}
+
+// XRANGE and XREVRANGE
+#[derive(Debug)]
+pub struct XRange {
+    key: String,
+    start: StreamId,
+    end: StreamId,
+    rev: bool,
+    count: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct XLen {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct XDel {
+    key: String,
+    ids: Vec<StreamId>,
+}
+
+#[derive(Debug)]
+pub struct XTrim {
+    key: String,
+    trim: StreamTrim,
+}
+
+#[derive(Debug)]
+pub enum XGroupAction {
+    // `None` as id means `$`
+    Create {
+        id: Option<StreamId>,
+        mkstream: bool,
+        entries_read: Option<u64>,
+    },
+    SetId {
+        id: Option<StreamId>,
+        entries_read: Option<u64>,
+    },
+    Destroy,
+    CreateConsumer(String),
+    DelConsumer(String),
+}
+
+#[derive(Debug)]
+pub struct XGroup {
+    key: String,
+    group: String,
+    action: XGroupAction,
+}
+
+#[derive(Debug)]
+pub struct XReadGroup {
+    group: String,
+    consumer: String,
+    count: Option<usize>,
+    noack: bool,
+    keys: Vec<String>,
+    // `None` means `>`, only new entries
+    ids: Vec<Option<StreamId>>,
+    blocking: bool,
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug)]
+pub struct XAck {
+    key: String,
+    group: String,
+    ids: Vec<StreamId>,
+}
+
+#[derive(Debug)]
+pub struct XPending {
+    key: String,
+    group: String,
+    // `None` is the summary form
+    filter: Option<PendingFilter>,
+}
+
+#[derive(Debug)]
+pub struct XClaim {
+    key: String,
+    group: String,
+    consumer: String,
+    min_idle: u64,
+    ids: Vec<StreamId>,
+    options: ClaimOptions,
+}
+
+#[derive(Debug)]
+pub struct XAutoClaim {
+    key: String,
+    group: String,
+    consumer: String,
+    min_idle: u64,
+    start: StreamId,
+    count: usize,
+    justid: bool,
+}
+
+#[derive(Debug)]
+pub enum XInfoKind {
+    Stream,
+    Groups,
+    Consumers(String),
+}
+
+#[derive(Debug)]
+pub struct XInfo {
+    key: String,
+    kind: XInfoKind,
+}
+
+#[derive(Debug)]
+pub struct Keys {
+    pattern: String,
+}
+
+#[derive(Debug)]
+pub struct Scan {
+    cursor: u64,
+    opts: ScanOptions,
+    key_type: Option<KeyType>,
+}
+
+#[derive(Debug)]
+pub struct HScan {
+    key: String,
+    cursor: u64,
+    opts: ScanOptions,
+    novalues: bool,
+}
+
+#[derive(Debug)]
+pub struct SScan {
+    key: String,
+    cursor: u64,
+    opts: ScanOptions,
+}
+
+#[derive(Debug)]
+pub struct ZScan {
+    key: String,
+    cursor: u64,
+    opts: ScanOptions,
+    noscores: bool,
+}
+
+#[derive(Debug)]
+pub struct Select {
+    index: i64,
+}
+
+#[derive(Debug)]
+pub struct Move {
+    key: String,
+    index: i64,
+}
+
+#[derive(Debug)]
+pub struct SwapDb {
+    a: i64,
+    b: i64,
+}
+
+/// FLUSHDB, or FLUSHALL when `all` is set.
+#[derive(Debug)]
+pub struct FlushDb {
+    all: bool,
+    lazy: bool,
+}
+
+#[derive(Debug)]
+pub struct DbSize;
+
+#[derive(Debug)]
+pub struct Ping {
+    message: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct Hello {
+    protover: Option<i64>,
+    auth: Option<(String, String)>,
+    setname: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum ConfigAction {
+    Get(Vec<String>),
+    Set(Vec<(String, String)>),
+    ResetStat,
+    Rewrite,
+}
+
+#[derive(Debug)]
+pub struct Config {
+    action: ConfigAction,
+}
+
+#[derive(Debug)]
+pub enum ShutdownAction {
+    Shutdown(crate::backend::Shutdown),
+    Abort,
+}
+
+/// `SHUTDOWN [NOSAVE] [NOW] [FORCE]` or `SHUTDOWN ABORT`. There is no
+/// persistence, so `SAVE` is refused instead of failing as a save would.
+#[derive(Debug)]
+pub struct Shutdown {
+    action: ShutdownAction,
+}
+
 impl TryFrom<RespFrame> for Command {
     type Error = CommandError;
     fn try_from(v: RespFrame) -> Result<Self, Self::Error> {
@@ -125,6 +512,48 @@ impl TryFrom<RespArray> for Command {
                 b"echo" => Ok(Echo::try_from(v)?.into()),
                 b"sadd" => Ok(SAdd::try_from(v)?.into()),
                 b"sismember" => Ok(SIsmember::try_from(v)?.into()),
+                b"zadd" => Ok(ZAdd::try_from(v)?.into()),
+                b"zrem" => Ok(ZRem::try_from(v)?.into()),
+                b"zscore" => Ok(ZScore::try_from(v)?.into()),
+                b"zcard" => Ok(ZCard::try_from(v)?.into()),
+                b"zrange" | b"zrevrange" | b"zrangebyscore" | b"zrevrangebyscore"
+                | b"zrangebylex" | b"zrevrangebylex" => Ok(ZRange::try_from(v)?.into()),
+                b"zrangestore" => Ok(ZRangeStore::try_from(v)?.into()),
+                b"zcount" | b"zlexcount" => Ok(ZCount::try_from(v)?.into()),
+                b"zremrangebyrank" | b"zremrangebyscore" | b"zremrangebylex" => {
+                    Ok(ZRemRange::try_from(v)?.into())
+                }
+                b"zunion" | b"zinter" | b"zdiff" | b"zunionstore" | b"zinterstore"
+                | b"zdiffstore" => Ok(ZCombine::try_from(v)?.into()),
+                b"zpopmin" | b"zpopmax" => Ok(ZPop::try_from(v)?.into()),
+                b"bzpopmin" | b"bzpopmax" => Ok(BZPop::try_from(v)?.into()),
+                b"zmpop" | b"bzmpop" => Ok(ZMPop::try_from(v)?.into()),
+                b"xadd" => Ok(XAdd::try_from(v)?.into()),
+                b"xrange" | b"xrevrange" => Ok(XRange::try_from(v)?.into()),
+                b"xlen" => Ok(XLen::try_from(v)?.into()),
+                b"xdel" => Ok(XDel::try_from(v)?.into()),
+                b"xtrim" => Ok(XTrim::try_from(v)?.into()),
+                b"xgroup" => Ok(XGroup::try_from(v)?.into()),
+                b"xreadgroup" => Ok(XReadGroup::try_from(v)?.into()),
+                b"xack" => Ok(XAck::try_from(v)?.into()),
+                b"xpending" => Ok(XPending::try_from(v)?.into()),
+                b"xclaim" => Ok(XClaim::try_from(v)?.into()),
+                b"xautoclaim" => Ok(XAutoClaim::try_from(v)?.into()),
+                b"xinfo" => Ok(XInfo::try_from(v)?.into()),
+                b"keys" => Ok(Keys::try_from(v)?.into()),
+                b"scan" => Ok(Scan::try_from(v)?.into()),
+                b"hscan" => Ok(HScan::try_from(v)?.into()),
+                b"sscan" => Ok(SScan::try_from(v)?.into()),
+                b"zscan" => Ok(ZScan::try_from(v)?.into()),
+                b"select" => Ok(Select::try_from(v)?.into()),
+                b"move" => Ok(Move::try_from(v)?.into()),
+                b"swapdb" => Ok(SwapDb::try_from(v)?.into()),
+                b"flushdb" | b"flushall" => Ok(FlushDb::try_from(v)?.into()),
+                b"dbsize" => Ok(DbSize::try_from(v)?.into()),
+                b"hello" => Ok(Hello::try_from(v)?.into()),
+                b"ping" => Ok(Ping::try_from(v)?.into()),
+                b"config" => Ok(Config::try_from(v)?.into()),
+                b"shutdown" => Ok(Shutdown::try_from(v)?.into()),
                 _ => Ok(Unrecognized.into()),
             },
             _ => Err(CommandError::InvalidCommand(
@@ -134,6 +563,107 @@ impl TryFrom<RespArray> for Command {
     }
 }
 
+impl Command {
+    /// Number of keys the command accesses.
+    pub fn key_count(&self) -> usize {
+        match self {
+            Command::Echo(_)
+            | Command::Keys(_)
+            | Command::Scan(_)
+            | Command::Select(_)
+            | Command::SwapDb(_)
+            | Command::FlushDb(_)
+            | Command::DbSize(_)
+            | Command::Hello(_)
+            | Command::Ping(_)
+            | Command::Config(_)
+            | Command::Shutdown(_)
+            | Command::Unrecognized(_) => 0,
+            Command::ZRangeStore(_) => 2,
+            Command::ZCombine(cmd) => cmd.keys.len() + usize::from(cmd.dst.is_some()),
+            Command::BZPop(cmd) => cmd.keys.len(),
+            Command::ZMPop(cmd) => cmd.keys.len(),
+            Command::XReadGroup(cmd) => cmd.keys.len(),
+            _ => 1,
+        }
+    }
+
+    /// Whether the command asks the server to shut down, the connection is
+    /// then closed instead of replying OK.
+    pub fn is_shutdown(&self) -> bool {
+        matches!(
+            self,
+            Command::Shutdown(Shutdown {
+                action: ShutdownAction::Shutdown(_)
+            })
+        )
+    }
+
+    /// Blocking view of the command, if it may wait for data.
+    pub fn as_blocking(&self) -> Option<&dyn BlockingCommand> {
+        match self {
+            Command::BZPop(cmd) => Some(cmd),
+            Command::ZMPop(cmd) if cmd.blocking => Some(cmd),
+            Command::XReadGroup(cmd) if cmd.blocking => Some(cmd),
+            _ => None,
+        }
+    }
+}
+
+// the registration of a blocked client, dropped however the wait ends: the
+// future may be dropped when the client disconnects
+struct Blocked<'a> {
+    backend: &'a Backend,
+    keys: &'a [String],
+    notify: Arc<Notify>,
+}
+
+impl Drop for Blocked<'_> {
+    fn drop(&mut self) {
+        self.backend.unblock_keys(self.keys, &self.notify);
+    }
+}
+
+/// Serve a blocking command, waiting until one of its keys is signaled or the
+/// timeout expires. `None` when the server shuts down first.
+pub async fn execute_blocking(cmd: &dyn BlockingCommand, backend: &Backend) -> Option<RespFrame> {
+    let blocked = Blocked {
+        backend,
+        keys: cmd.keys(),
+        notify: backend.block_on_keys(cmd.keys()),
+    };
+    let notify = &blocked.notify;
+    let mut shutdown = backend.subscribe_shutdown();
+    // parse_timeout made sure the deadline fits, if not block forever
+    let deadline = cmd.timeout().and_then(|t| Instant::now().checked_add(t));
+    loop {
+        // the selected db may have been swapped or flushed while waiting
+        if let Some(frame) = cmd.try_execute(&backend.current()) {
+            break Some(frame);
+        }
+        let notified = async {
+            match deadline {
+                Some(deadline) => tokio::time::timeout_at(deadline, notify.notified())
+                    .await
+                    .is_ok(),
+                None => {
+                    notify.notified().await;
+                    true
+                }
+            }
+        };
+        tokio::select! {
+            notified = notified => {
+                if !notified {
+                    break Some(cmd.timeout_reply());
+                }
+            }
+            // a blocked client must not hold back the shutdown
+            _ = shutdown.wait_for(Option::is_some) => break None,
+        }
+    }
+}
+
 impl CommandExecutor for Unrecognized {
     fn execute(self, _: &Backend) -> RespFrame {
         RESP_OK.clone()
@@ -175,9 +705,125 @@ fn validate_command(
     Ok(())
 }
 
+fn validate_command_at_least(
+    value: &RespArray,
+    names: &[&'static str],
+    n_args: usize,
+) -> Result<(), CommandError> {
+    if value.len() < n_args + 1 {
+        return Err(CommandError::InvalidCommand(format!(
+            "{} command must have at least {} argument",
+            names.join(" "),
+            n_args
+        )));
+    }
+    validate_command(value, names, value.len() - 1)
+}
+
+// lowercase name of the command, for commands sharing a parser
+fn command_name(value: &RespArray) -> String {
+    match value.first() {
+        Some(RespFrame::BulkString(cmd)) => String::from_utf8_lossy(cmd).to_ascii_lowercase(),
+        _ => String::new(),
+    }
+}
+
 fn extract_args(value: RespArray, start: usize) -> Result<Vec<RespFrame>, CommandError> {
     match value.0 {
         Some(arr) => Ok(arr.into_iter().skip(start).collect::<Vec<RespFrame>>()),
-        None => Err(CommandError::InvalidCommand(format!("{:?}", value))),
+        None => Err(CommandError::InvalidCommand(
+            "Command must not be a null array".to_string(),
+        )),
+    }
+}
+
+fn extract_string(frame: RespFrame) -> Result<String, CommandError> {
+    match frame {
+        RespFrame::BulkString(BulkString(Some(v))) => Ok(String::from_utf8(v.into())?),
+        // the argument is user data, it is kept out of the message
+        _ => Err(CommandError::InvalidArgument(
+            "expect BulkString arguments".to_string(),
+        )),
+    }
+}
+
+fn extract_strings(value: RespArray, start: usize) -> Result<Vec<String>, CommandError> {
+    extract_args(value, start)?
+        .into_iter()
+        .map(extract_string)
+        .collect()
+}
+
+fn parse_int(s: &str) -> Result<i64, CommandError> {
+    s.parse().map_err(|_| {
+        CommandError::InvalidArgument("value is not an integer or out of range".to_string())
+    })
+}
+
+// blocking timeouts are given in (fractional) seconds, 0 blocks forever
+fn parse_timeout(s: &str) -> Result<Option<Duration>, CommandError> {
+    let timeout = s.parse::<f64>().map_err(|_| {
+        CommandError::InvalidArgument("timeout is not a float or out of range".to_string())
+    })?;
+    if timeout < 0.0 {
+        return Err(CommandError::InvalidArgument(
+            "timeout is negative".to_string(),
+        ));
+    }
+    if timeout == 0.0 {
+        return Ok(None);
+    }
+    // the deadline must be representable too
+    Duration::try_from_secs_f64(timeout)
+        .ok()
+        .filter(|timeout| Instant::now().checked_add(*timeout).is_some())
+        .map(Some)
+        .ok_or_else(|| CommandError::InvalidArgument("timeout is out of range".to_string()))
+}
+
+// parse `cursor [MATCH pattern] [COUNT count]`, other options are handed to
+// `other` which returns false when it doesn't know them either
+fn parse_scan_args(
+    args: &mut std::vec::IntoIter<String>,
+    mut other: impl FnMut(&str, &mut std::vec::IntoIter<String>) -> Result<bool, CommandError>,
+) -> Result<(u64, ScanOptions), CommandError> {
+    let syntax_error = || CommandError::InvalidArgument("syntax error".to_string());
+    let cursor = args
+        .next()
+        .and_then(|v| v.parse().ok())
+        .ok_or_else(|| CommandError::InvalidArgument("invalid cursor".to_string()))?;
+
+    let mut opts = ScanOptions::default();
+    while let Some(opt) = args.next() {
+        match opt.to_ascii_lowercase().as_str() {
+            "match" => opts.pattern = Some(args.next().ok_or_else(syntax_error)?),
+            "count" => match parse_int(&args.next().ok_or_else(syntax_error)?)? {
+                v if v > 0 => opts.count = v as usize,
+                _ => return Err(syntax_error()),
+            },
+            v => {
+                if !other(v, args)? {
+                    return Err(syntax_error());
+                }
+            }
+        }
+    }
+    Ok((cursor, opts))
+}
+
+fn scan_reply(cursor: u64, items: Vec<RespFrame>) -> RespFrame {
+    RespArray::new([
+        BulkString::new(cursor.to_string()).into(),
+        RespArray::new(items).into(),
+    ])
+    .into()
+}
+
+fn parse_float(s: &str) -> Result<f64, CommandError> {
+    match s.parse::<f64>() {
+        Ok(v) if !v.is_nan() => Ok(v),
+        _ => Err(CommandError::InvalidArgument(
+            "value is not a valid float".to_string(),
+        )),
     }
 }
@@ -0,0 +1,240 @@
+use super::{
+    extract_strings, validate_command_at_least, CommandError, CommandExecutor, Config,
+    ConfigAction, Shutdown, ShutdownAction, RESP_OK,
+};
+use crate::{
+    backend::{self, glob_match, Backend},
+    config, BulkString, RespArray, RespFrame, RespMap, SimpleError,
+};
+
+impl CommandExecutor for Config {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let ret = match self.action {
+            ConfigAction::Get(patterns) => {
+                let config = backend.config();
+                let patterns = patterns
+                    .iter()
+                    .map(|p| p.to_ascii_lowercase())
+                    .collect::<Vec<_>>();
+                let mut map = RespMap::new();
+                for name in config::Config::DIRECTIVES {
+                    if patterns
+                        .iter()
+                        .any(|p| glob_match(p.as_bytes(), name.as_bytes()))
+                    {
+                        let value = config.get(name).unwrap_or_default();
+                        map.insert(BulkString::new(*name), BulkString::new(value));
+                    }
+                }
+                return map.into();
+            }
+            ConfigAction::Set(params) => backend.config_set(&params),
+            ConfigAction::ResetStat => {
+                backend.reset_stats();
+                Ok(())
+            }
+            ConfigAction::Rewrite => backend.config_rewrite(),
+        };
+        match ret {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => SimpleError::new(e.to_string()).into(),
+        }
+    }
+}
+
+impl CommandExecutor for Shutdown {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let ret = match self.action {
+            ShutdownAction::Shutdown(shutdown) => {
+                backend.shutdown(shutdown);
+                Ok(())
+            }
+            ShutdownAction::Abort => backend.abort_shutdown(),
+        };
+        match ret {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => SimpleError::new(e.to_string()).into(),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for Config {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["config"], 1)?;
+
+        let mut args = extract_strings(value, 1)?.into_iter();
+        let subcommand = args.next().unwrap_or_default().to_ascii_lowercase();
+        let args = args.collect::<Vec<_>>();
+        let wrong_args = || {
+            CommandError::InvalidArgument(format!(
+                "wrong number of arguments for 'config|{}' command",
+                subcommand
+            ))
+        };
+        let action = match subcommand.as_str() {
+            "get" if !args.is_empty() => ConfigAction::Get(args),
+            "set" if !args.is_empty() && args.len().is_multiple_of(2) => {
+                let mut args = args.into_iter();
+                let mut params = Vec::new();
+                while let (Some(name), Some(value)) = (args.next(), args.next()) {
+                    params.push((name, value));
+                }
+                ConfigAction::Set(params)
+            }
+            "resetstat" if args.is_empty() => ConfigAction::ResetStat,
+            "rewrite" if args.is_empty() => ConfigAction::Rewrite,
+            "get" | "set" | "resetstat" | "rewrite" => return Err(wrong_args()),
+            v => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "unknown subcommand '{}'",
+                    v
+                )))
+            }
+        };
+        Ok(Config { action })
+    }
+}
+
+impl TryFrom<RespArray> for Shutdown {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["shutdown"], 0)?;
+
+        let syntax_error = || CommandError::InvalidArgument("syntax error".to_string());
+        let mut shutdown = backend::Shutdown::default();
+        let mut abort = false;
+        for arg in extract_strings(value, 1)? {
+            match arg.to_ascii_lowercase().as_str() {
+                // there is no persistence, nothing could be saved
+                "save" => {
+                    return Err(CommandError::InvalidArgument(
+                        "SAVE is not supported, the server has no persistence".to_string(),
+                    ))
+                }
+                "nosave" => shutdown.nosave = true,
+                "now" => shutdown.now = true,
+                "force" => shutdown.force = true,
+                "abort" => abort = true,
+                _ => return Err(syntax_error()),
+            }
+        }
+        let action = match abort {
+            // ABORT takes no other flag
+            true if shutdown != backend::Shutdown::default() => return Err(syntax_error()),
+            true => ShutdownAction::Abort,
+            false => ShutdownAction::Shutdown(shutdown),
+        };
+        Ok(Shutdown { action })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::Command;
+
+    fn command(args: &[&str]) -> Result<Command, CommandError> {
+        RespArray::new(
+            args.iter()
+                .map(|arg| BulkString::new(*arg).into())
+                .collect::<Vec<_>>(),
+        )
+        .try_into()
+    }
+
+    fn run(backend: &Backend, args: &[&str]) -> RespFrame {
+        command(args).unwrap().execute(backend)
+    }
+
+    #[test]
+    fn test_config_get_set() {
+        let backend = Backend::new();
+        assert_eq!(
+            run(
+                &backend,
+                &[
+                    "config",
+                    "set",
+                    "maxmemory",
+                    "10mb",
+                    "maxmemory-policy",
+                    "allkeys-lru"
+                ]
+            ),
+            RESP_OK.clone()
+        );
+
+        // in the order of the directives, each once
+        let mut expected = RespMap::new();
+        expected.insert(BulkString::new("port"), BulkString::new("6378"));
+        expected.insert(BulkString::new("maxmemory"), BulkString::new("10485760"));
+        expected.insert(
+            BulkString::new("maxmemory-policy"),
+            BulkString::new("allkeys-lru"),
+        );
+        assert_eq!(
+            run(&backend, &["CONFIG", "GET", "MAXMEM*", "port", "maxmemory"]),
+            expected.into()
+        );
+        assert_eq!(
+            run(&backend, &["config", "get", "nope*"]),
+            RespMap::new().into()
+        );
+
+        assert!(matches!(
+            run(&backend, &["config", "set", "port", "1"]),
+            RespFrame::Error(_)
+        ));
+        assert_eq!(run(&backend, &["config", "resetstat"]), RESP_OK.clone());
+        assert_eq!(
+            run(&backend, &["config", "rewrite"]),
+            SimpleError::new("ERR The server is running without a config file").into()
+        );
+    }
+
+    #[test]
+    fn test_shutdown() {
+        let backend = Backend::new();
+        assert_eq!(
+            run(&backend, &["shutdown", "abort"]),
+            SimpleError::new("ERR No shutdown in progress.").into()
+        );
+        // refused when parsed, not as a failed save
+        assert_eq!(
+            RespFrame::from(command(&["SHUTDOWN", "SAVE", "NOW"]).unwrap_err()),
+            SimpleError::new("ERR SAVE is not supported, the server has no persistence").into()
+        );
+        assert!(!backend.is_shutting_down());
+
+        let cmd = command(&["shutdown", "nosave", "now"]).unwrap();
+        assert!(cmd.is_shutdown());
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        assert_eq!(
+            *backend.subscribe_shutdown().borrow(),
+            Some(backend::Shutdown {
+                nosave: true,
+                now: true,
+                force: false
+            })
+        );
+        assert!(!command(&["shutdown", "abort"]).unwrap().is_shutdown());
+        assert_eq!(run(&backend, &["shutdown", "abort"]), RESP_OK.clone());
+        assert!(!backend.is_shutting_down());
+
+        assert!(command(&["shutdown", "save", "nosave"]).is_err());
+        assert!(command(&["shutdown", "abort", "now"]).is_err());
+        assert!(command(&["shutdown", "later"]).is_err());
+    }
+
+    #[test]
+    fn test_config_parse_errors() {
+        assert!(command(&["config"]).is_err());
+        assert!(command(&["config", "get"]).is_err());
+        assert!(command(&["config", "set", "maxmemory"]).is_err());
+        assert!(command(&["config", "rewrite", "now"]).is_err());
+        assert!(command(&["config", "reload"]).is_err());
+    }
+}
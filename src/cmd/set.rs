@@ -1,4 +1,7 @@
-use super::{extract_args, validate_command, CommandError, CommandExecutor, SAdd, SIsmember};
+use super::{
+    extract_args, extract_strings, parse_scan_args, scan_reply, validate_command,
+    validate_command_at_least, CommandError, CommandExecutor, SAdd, SIsmember, SScan,
+};
 use crate::{backend::Backend, BulkString, RespArray, RespFrame, SimpleError};
 
 impl CommandExecutor for SAdd {
@@ -20,6 +23,13 @@ impl CommandExecutor for SIsmember {
     }
 }
 
+impl CommandExecutor for SScan {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let (cursor, members) = backend.sscan(&self.key, self.cursor, &self.opts);
+        scan_reply(cursor, members)
+    }
+}
+
 impl TryFrom<RespArray> for SAdd {
     type Error = CommandError;
     fn try_from(value: RespArray) -> Result<Self, Self::Error> {
@@ -28,7 +38,7 @@ impl TryFrom<RespArray> for SAdd {
         let mut args = extract_args(value, 1)?.into_iter();
         match (args.next(), args.next()) {
             (Some(RespFrame::BulkString(BulkString(Some(key)))), Some(value)) => Ok(SAdd {
-                key: String::from_utf8(key)?,
+                key: String::from_utf8(key.into())?,
                 value,
             }),
             _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
@@ -45,10 +55,23 @@ impl TryFrom<RespArray> for SIsmember {
         let mut args = extract_args(value, 1)?.into_iter();
         match (args.next(), args.next()) {
             (Some(RespFrame::BulkString(BulkString(Some(key)))), Some(value)) => Ok(SIsmember {
-                key: String::from_utf8(key)?,
+                key: String::from_utf8(key.into())?,
                 value,
             }),
             _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
         }
     }
 }
+
+impl TryFrom<RespArray> for SScan {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["sscan"], 2)?;
+
+        let mut args = extract_strings(value, 1)?.into_iter();
+        let key = args.next().unwrap_or_default();
+        let (cursor, opts) = parse_scan_args(&mut args, |_, _| Ok(false))?;
+        Ok(SScan { key, cursor, opts })
+    }
+}
@@ -0,0 +1,1195 @@
+use std::{iter::Peekable, time::Duration};
+
+use super::{
+    extract_strings, parse_int, validate_command, validate_command_at_least, BlockingCommand,
+    CommandError, CommandExecutor, XAck, XAdd, XAutoClaim, XClaim, XDel, XGroup, XGroupAction,
+    XInfo, XInfoKind, XLen, XPending, XRange, XReadGroup, XTrim, RESP_OK,
+};
+use crate::{
+    backend::{
+        Backend, ClaimOptions, PendingFilter, StreamEntry, StreamError, StreamId, StreamTrim,
+        TrimStrategy, XAddId,
+    },
+    BulkString, RespArray, RespFrame, RespNull, SimpleError,
+};
+
+impl CommandExecutor for XAdd {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.xadd(
+            self.key,
+            self.id,
+            self.fields,
+            self.nomkstream,
+            self.trim.as_ref(),
+        ) {
+            Ok(Some(id)) => BulkString::new(id.to_string()).into(),
+            Ok(None) => RespFrame::Null(RespNull),
+            Err(e) => SimpleError::new(e.to_string()).into(),
+        }
+    }
+}
+
+impl CommandExecutor for XRange {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let entries = backend.xrange(&self.key, self.start, self.end, self.rev, self.count);
+        entries_reply(entries)
+    }
+}
+
+impl CommandExecutor for XLen {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.xlen(&self.key) as i64)
+    }
+}
+
+impl CommandExecutor for XDel {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.xdel(&self.key, &self.ids) as i64)
+    }
+}
+
+impl CommandExecutor for XTrim {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.xtrim(&self.key, &self.trim) as i64)
+    }
+}
+
+impl CommandExecutor for XGroup {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let ret = match self.action {
+            XGroupAction::Create {
+                id,
+                mkstream,
+                entries_read,
+            } => backend
+                .xgroup_create(self.key, self.group, id, mkstream, entries_read)
+                .map(|_| RESP_OK.clone()),
+            XGroupAction::SetId { id, entries_read } => backend
+                .xgroup_setid(&self.key, &self.group, id, entries_read)
+                .map(|_| RESP_OK.clone()),
+            XGroupAction::Destroy => backend
+                .xgroup_destroy(&self.key, &self.group)
+                .map(|v| RespFrame::Integer(v as i64)),
+            XGroupAction::CreateConsumer(consumer) => backend
+                .xgroup_createconsumer(&self.key, &self.group, &consumer)
+                .map(|v| RespFrame::Integer(v as i64)),
+            XGroupAction::DelConsumer(consumer) => backend
+                .xgroup_delconsumer(&self.key, &self.group, &consumer)
+                .map(|v| RespFrame::Integer(v as i64)),
+        };
+        ret.unwrap_or_else(error_reply)
+    }
+}
+
+impl CommandExecutor for XReadGroup {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        self.try_execute(backend)
+            .unwrap_or_else(|| self.timeout_reply())
+    }
+}
+
+impl BlockingCommand for XReadGroup {
+    fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    fn try_execute(&self, backend: &Backend) -> Option<RespFrame> {
+        // a missing group fails the command before any stream is read from,
+        // so that none has entries delivered for a reply that is never sent
+        if let Err(e) = self
+            .keys
+            .iter()
+            .try_for_each(|key| backend.xgroup_check(key, &self.group))
+        {
+            return Some(error_reply(e));
+        }
+        let mut ret = vec![];
+        for (key, id) in self.keys.iter().zip(&self.ids) {
+            match backend.xreadgroup(
+                key,
+                &self.group,
+                &self.consumer,
+                *id,
+                self.count,
+                self.noack,
+            ) {
+                // history reads always report the stream, new entries only when there are any
+                Ok(entries) if id.is_some() || !entries.is_empty() => {
+                    let entries = entries
+                        .into_iter()
+                        .map(|(id, fields)| entry_reply(id, fields))
+                        .collect::<Vec<RespFrame>>();
+                    ret.push(
+                        RespArray::new([
+                            BulkString::new(key.as_str()).into(),
+                            RespArray::new(entries).into(),
+                        ])
+                        .into(),
+                    );
+                }
+                Ok(_) => {}
+                Err(e) => return Some(error_reply(e)),
+            }
+        }
+        if ret.is_empty() {
+            None
+        } else {
+            Some(RespArray::new(ret).into())
+        }
+    }
+}
+
+impl CommandExecutor for XAck {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.xack(&self.key, &self.group, &self.ids) as i64)
+    }
+}
+
+impl CommandExecutor for XPending {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match self.filter {
+            Some(filter) => match backend.xpending(&self.key, &self.group, &filter) {
+                Ok(pending) => {
+                    let pending = pending
+                        .into_iter()
+                        .map(|v| {
+                            RespArray::new([
+                                BulkString::new(v.id.to_string()).into(),
+                                BulkString::new(v.consumer).into(),
+                                RespFrame::Integer(v.idle as i64),
+                                RespFrame::Integer(v.delivery_count as i64),
+                            ])
+                            .into()
+                        })
+                        .collect::<Vec<RespFrame>>();
+                    RespArray::new(pending).into()
+                }
+                Err(e) => error_reply(e),
+            },
+            None => match backend.xpending_summary(&self.key, &self.group) {
+                Ok(summary) if summary.count == 0 => RespArray::new([
+                    RespFrame::Integer(0),
+                    BulkString::null().into(),
+                    BulkString::null().into(),
+                    RespArray::null().into(),
+                ])
+                .into(),
+                Ok(summary) => {
+                    let id = |v: Option<StreamId>| -> RespFrame {
+                        BulkString::new(v.unwrap_or_default().to_string()).into()
+                    };
+                    let consumers = summary
+                        .consumers
+                        .into_iter()
+                        .map(|(name, count)| {
+                            RespArray::new([
+                                BulkString::new(name).into(),
+                                BulkString::new(count.to_string()).into(),
+                            ])
+                            .into()
+                        })
+                        .collect::<Vec<RespFrame>>();
+                    RespArray::new([
+                        RespFrame::Integer(summary.count as i64),
+                        id(summary.min),
+                        id(summary.max),
+                        RespArray::new(consumers).into(),
+                    ])
+                    .into()
+                }
+                Err(e) => error_reply(e),
+            },
+        }
+    }
+}
+
+impl CommandExecutor for XClaim {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.xclaim(
+            &self.key,
+            &self.group,
+            &self.consumer,
+            self.min_idle,
+            &self.ids,
+            &self.options,
+        ) {
+            Ok(entries) if self.options.justid => ids_reply(entries.into_iter().map(|(id, _)| id)),
+            Ok(entries) => entries_reply(entries),
+            Err(e) => error_reply(e),
+        }
+    }
+}
+
+impl CommandExecutor for XAutoClaim {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.xautoclaim(
+            &self.key,
+            &self.group,
+            &self.consumer,
+            self.min_idle,
+            self.start,
+            self.count,
+            self.justid,
+        ) {
+            Ok(ret) => {
+                let claimed = if self.justid {
+                    ids_reply(ret.claimed.into_iter().map(|(id, _)| id))
+                } else {
+                    entries_reply(ret.claimed)
+                };
+                RespArray::new([
+                    BulkString::new(ret.cursor.to_string()).into(),
+                    claimed,
+                    ids_reply(ret.deleted.into_iter()),
+                ])
+                .into()
+            }
+            Err(e) => error_reply(e),
+        }
+    }
+}
+
+impl CommandExecutor for XInfo {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let no_such_key = || SimpleError::new("ERR no such key").into();
+        match self.kind {
+            XInfoKind::Stream => match backend.xinfo_stream(&self.key) {
+                Some(info) => {
+                    let entry = |v: Option<(StreamId, Vec<(String, String)>)>| match v {
+                        Some((id, fields)) => entry_reply(id, Some(fields)),
+                        None => BulkString::null().into(),
+                    };
+                    info_reply(vec![
+                        ("length", RespFrame::Integer(info.length as i64)),
+                        (
+                            "last-generated-id",
+                            BulkString::new(info.last_generated_id.to_string()).into(),
+                        ),
+                        (
+                            "max-deleted-entry-id",
+                            BulkString::new(info.max_deleted_entry_id.to_string()).into(),
+                        ),
+                        (
+                            "entries-added",
+                            RespFrame::Integer(info.entries_added as i64),
+                        ),
+                        (
+                            "recorded-first-entry-id",
+                            BulkString::new(info.recorded_first_entry_id.to_string()).into(),
+                        ),
+                        ("groups", RespFrame::Integer(info.groups as i64)),
+                        ("first-entry", entry(info.first_entry)),
+                        ("last-entry", entry(info.last_entry)),
+                    ])
+                }
+                None => no_such_key(),
+            },
+            XInfoKind::Groups => match backend.xinfo_groups(&self.key) {
+                Some(groups) => {
+                    let optional = |v: Option<u64>| match v {
+                        Some(v) => RespFrame::Integer(v as i64),
+                        None => BulkString::null().into(),
+                    };
+                    let groups = groups
+                        .into_iter()
+                        .map(|g| {
+                            info_reply(vec![
+                                ("name", BulkString::new(g.name).into()),
+                                ("consumers", RespFrame::Integer(g.consumers as i64)),
+                                ("pending", RespFrame::Integer(g.pending as i64)),
+                                (
+                                    "last-delivered-id",
+                                    BulkString::new(g.last_delivered_id.to_string()).into(),
+                                ),
+                                ("entries-read", optional(g.entries_read)),
+                                ("lag", optional(g.lag)),
+                            ])
+                        })
+                        .collect::<Vec<RespFrame>>();
+                    RespArray::new(groups).into()
+                }
+                None => no_such_key(),
+            },
+            XInfoKind::Consumers(group) => match backend.xinfo_consumers(&self.key, &group) {
+                Ok(consumers) => {
+                    let consumers = consumers
+                        .into_iter()
+                        .map(|c| {
+                            info_reply(vec![
+                                ("name", BulkString::new(c.name).into()),
+                                ("pending", RespFrame::Integer(c.pending as i64)),
+                                ("idle", RespFrame::Integer(c.idle as i64)),
+                                (
+                                    "inactive",
+                                    RespFrame::Integer(c.inactive.map(|v| v as i64).unwrap_or(-1)),
+                                ),
+                            ])
+                        })
+                        .collect::<Vec<RespFrame>>();
+                    RespArray::new(consumers).into()
+                }
+                Err(e) => error_reply(e),
+            },
+        }
+    }
+}
+
+impl TryFrom<RespArray> for XAdd {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["xadd"], 4)?;
+
+        let mut args = extract_strings(value, 1)?.into_iter().peekable();
+        let key = args.next().unwrap_or_default();
+        let mut nomkstream = false;
+        if args
+            .next_if(|v| v.eq_ignore_ascii_case("nomkstream"))
+            .is_some()
+        {
+            nomkstream = true;
+        }
+        let trim = parse_trim(&mut args)?;
+
+        let id = match args.next() {
+            Some(id) if id == "*" => XAddId::Auto,
+            Some(id) => match id.strip_suffix("-*") {
+                Some(ms) => XAddId::AutoSeq(ms.parse().map_err(|_| invalid_stream_id())?),
+                None => XAddId::Explicit(parse_stream_id(&id, 0)?),
+            },
+            None => return Err(syntax_error()),
+        };
+
+        let rest: Vec<String> = args.collect();
+        if rest.is_empty() || !rest.len().is_multiple_of(2) {
+            return Err(CommandError::InvalidArgument(
+                "wrong number of arguments for 'xadd' command".to_string(),
+            ));
+        }
+        let fields = rest
+            .chunks(2)
+            .map(|pair| (pair[0].clone(), pair[1].clone()))
+            .collect();
+
+        Ok(XAdd {
+            key,
+            nomkstream,
+            trim,
+            id,
+            fields,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XRange {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let rev = match value.first() {
+            Some(RespFrame::BulkString(cmd)) => cmd.eq_ignore_ascii_case(b"xrevrange"),
+            _ => false,
+        };
+        let name = if rev { "xrevrange" } else { "xrange" };
+        validate_command_at_least(&value, &[name], 3)?;
+
+        let mut args = extract_strings(value, 1)?.into_iter();
+        let key = args.next().unwrap_or_default();
+        let (first, second) = (
+            args.next().unwrap_or_default(),
+            args.next().unwrap_or_default(),
+        );
+        // XREVRANGE takes `end start`
+        let (start, end) = if rev {
+            (second, first)
+        } else {
+            (first, second)
+        };
+        let start = parse_range_id(&start, true)?;
+        let end = parse_range_id(&end, false)?;
+
+        let count = match (args.next(), args.next()) {
+            (Some(opt), Some(count)) if opt.eq_ignore_ascii_case("count") => {
+                Some(parse_int(&count)?.max(0) as usize)
+            }
+            (None, None) => None,
+            _ => return Err(syntax_error()),
+        };
+        if args.next().is_some() {
+            return Err(syntax_error());
+        }
+
+        Ok(XRange {
+            key,
+            start,
+            end,
+            rev,
+            count,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XLen {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["xlen"], 1)?;
+
+        let mut args = extract_strings(value, 1)?.into_iter();
+        match args.next() {
+            Some(key) => Ok(XLen { key }),
+            _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for XDel {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["xdel"], 2)?;
+
+        let mut args = extract_strings(value, 1)?.into_iter();
+        let key = args.next().unwrap_or_default();
+        let ids = args
+            .map(|id| parse_stream_id(&id, 0))
+            .collect::<Result<_, _>>()?;
+        Ok(XDel { key, ids })
+    }
+}
+
+impl TryFrom<RespArray> for XTrim {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["xtrim"], 3)?;
+
+        let mut args = extract_strings(value, 1)?.into_iter().peekable();
+        let key = args.next().unwrap_or_default();
+        let trim = parse_trim(&mut args)?.ok_or_else(syntax_error)?;
+        if args.next().is_some() {
+            return Err(syntax_error());
+        }
+        Ok(XTrim { key, trim })
+    }
+}
+
+impl TryFrom<RespArray> for XGroup {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["xgroup"], 3)?;
+
+        let mut args = extract_strings(value, 1)?.into_iter().peekable();
+        let subcommand = args.next().unwrap_or_default().to_ascii_lowercase();
+        let key = args.next().unwrap_or_default();
+        let group = args.next().unwrap_or_default();
+        let action = match subcommand.as_str() {
+            "create" | "setid" => {
+                let id = match args.next().ok_or_else(syntax_error)?.as_str() {
+                    "$" => None,
+                    id => Some(parse_stream_id(id, 0)?),
+                };
+                let mut mkstream = false;
+                let mut entries_read = None;
+                while let Some(opt) = args.next() {
+                    match opt.to_ascii_lowercase().as_str() {
+                        "mkstream" if subcommand == "create" => mkstream = true,
+                        "entriesread" => {
+                            let v = parse_int(&args.next().ok_or_else(syntax_error)?)?;
+                            if v < 0 {
+                                return Err(CommandError::InvalidArgument(
+                                    "value for ENTRIESREAD must be positive or -1".to_string(),
+                                ));
+                            }
+                            entries_read = Some(v as u64);
+                        }
+                        _ => return Err(syntax_error()),
+                    }
+                }
+                if subcommand == "create" {
+                    XGroupAction::Create {
+                        id,
+                        mkstream,
+                        entries_read,
+                    }
+                } else {
+                    XGroupAction::SetId { id, entries_read }
+                }
+            }
+            "destroy" => XGroupAction::Destroy,
+            "createconsumer" => XGroupAction::CreateConsumer(args.next().ok_or_else(syntax_error)?),
+            "delconsumer" => XGroupAction::DelConsumer(args.next().ok_or_else(syntax_error)?),
+            v => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "unknown subcommand '{}'",
+                    v
+                )))
+            }
+        };
+        if args.next().is_some() {
+            return Err(syntax_error());
+        }
+
+        Ok(XGroup { key, group, action })
+    }
+}
+
+impl TryFrom<RespArray> for XReadGroup {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["xreadgroup"], 6)?;
+
+        let mut args = extract_strings(value, 1)?.into_iter();
+        if !args
+            .next()
+            .unwrap_or_default()
+            .eq_ignore_ascii_case("group")
+        {
+            return Err(syntax_error());
+        }
+        let group = args.next().ok_or_else(syntax_error)?;
+        let consumer = args.next().ok_or_else(syntax_error)?;
+
+        let mut count = None;
+        let mut block = None;
+        let mut noack = false;
+        loop {
+            let opt = args.next().ok_or_else(syntax_error)?;
+            match opt.to_ascii_lowercase().as_str() {
+                "count" => {
+                    count = Some(parse_int(&args.next().ok_or_else(syntax_error)?)?.max(0) as usize)
+                }
+                "block" => match parse_int(&args.next().ok_or_else(syntax_error)?)? {
+                    ms if ms < 0 => {
+                        return Err(CommandError::InvalidArgument(
+                            "timeout is negative".to_string(),
+                        ))
+                    }
+                    0 => block = Some(None),
+                    ms => block = Some(Some(Duration::from_millis(ms as u64))),
+                },
+                "noack" => noack = true,
+                "streams" => break,
+                _ => return Err(syntax_error()),
+            }
+        }
+
+        let rest: Vec<String> = args.collect();
+        if rest.is_empty() || !rest.len().is_multiple_of(2) {
+            return Err(CommandError::InvalidArgument(
+                "Unbalanced 'xreadgroup' list of streams: for each stream key an ID or '>' must be specified.".to_string(),
+            ));
+        }
+        let (keys, ids) = rest.split_at(rest.len() / 2);
+        let ids = ids
+            .iter()
+            .map(|id| match id.as_str() {
+                ">" => Ok(None),
+                id => parse_stream_id(id, 0).map(Some),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // only reads of new entries wait for data
+        let blocking = block.is_some() && ids.iter().any(|v| v.is_none());
+        Ok(XReadGroup {
+            group,
+            consumer,
+            count,
+            noack,
+            keys: keys.to_vec(),
+            ids,
+            blocking,
+            timeout: block.flatten(),
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XAck {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["xack"], 3)?;
+
+        let mut args = extract_strings(value, 1)?.into_iter();
+        let key = args.next().unwrap_or_default();
+        let group = args.next().unwrap_or_default();
+        let ids = args
+            .map(|id| parse_stream_id(&id, 0))
+            .collect::<Result<_, _>>()?;
+        Ok(XAck { key, group, ids })
+    }
+}
+
+impl TryFrom<RespArray> for XPending {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["xpending"], 2)?;
+
+        let mut args = extract_strings(value, 1)?.into_iter().peekable();
+        let key = args.next().unwrap_or_default();
+        let group = args.next().unwrap_or_default();
+        if args.peek().is_none() {
+            return Ok(XPending {
+                key,
+                group,
+                filter: None,
+            });
+        }
+
+        let mut min_idle = 0;
+        if args.next_if(|v| v.eq_ignore_ascii_case("idle")).is_some() {
+            min_idle = parse_int(&args.next().ok_or_else(syntax_error)?)?.max(0) as u64;
+        }
+        let start = parse_range_id(&args.next().ok_or_else(syntax_error)?, true)?;
+        let end = parse_range_id(&args.next().ok_or_else(syntax_error)?, false)?;
+        let count = parse_int(&args.next().ok_or_else(syntax_error)?)?.max(0) as usize;
+        let consumer = args.next();
+        if args.next().is_some() {
+            return Err(syntax_error());
+        }
+
+        Ok(XPending {
+            key,
+            group,
+            filter: Some(PendingFilter {
+                min_idle,
+                start,
+                end,
+                count,
+                consumer,
+            }),
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XClaim {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["xclaim"], 5)?;
+
+        let mut args = extract_strings(value, 1)?.into_iter().peekable();
+        let key = args.next().unwrap_or_default();
+        let group = args.next().unwrap_or_default();
+        let consumer = args.next().unwrap_or_default();
+        let min_idle = parse_int(&args.next().unwrap_or_default())?.max(0) as u64;
+
+        // ids come first, options start at the first argument that isn't an id
+        let mut ids = vec![];
+        while let Some(id) = args.peek().and_then(|v| parse_stream_id(v, 0).ok()) {
+            ids.push(id);
+            args.next();
+        }
+        if ids.is_empty() {
+            return Err(invalid_stream_id());
+        }
+
+        let mut options = ClaimOptions::default();
+        let next_int = |v: Option<String>| -> Result<u64, CommandError> {
+            Ok(parse_int(&v.ok_or_else(syntax_error)?)?.max(0) as u64)
+        };
+        while let Some(opt) = args.next() {
+            match opt.to_ascii_lowercase().as_str() {
+                "idle" => options.idle = Some(next_int(args.next())?),
+                "time" => options.time = Some(next_int(args.next())?),
+                "retrycount" => options.retry_count = Some(next_int(args.next())?),
+                "force" => options.force = true,
+                "justid" => options.justid = true,
+                "lastid" => {
+                    options.last_id =
+                        Some(parse_stream_id(&args.next().ok_or_else(syntax_error)?, 0)?)
+                }
+                _ => {
+                    return Err(CommandError::InvalidArgument(format!(
+                        "Unrecognized XCLAIM option '{}'",
+                        opt
+                    )))
+                }
+            }
+        }
+
+        Ok(XClaim {
+            key,
+            group,
+            consumer,
+            min_idle,
+            ids,
+            options,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XAutoClaim {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["xautoclaim"], 5)?;
+
+        let mut args = extract_strings(value, 1)?.into_iter();
+        let key = args.next().unwrap_or_default();
+        let group = args.next().unwrap_or_default();
+        let consumer = args.next().unwrap_or_default();
+        let min_idle = parse_int(&args.next().unwrap_or_default())?.max(0) as u64;
+        let start = parse_range_id(&args.next().unwrap_or_default(), true)?;
+
+        let mut count = 100;
+        let mut justid = false;
+        while let Some(opt) = args.next() {
+            match opt.to_ascii_lowercase().as_str() {
+                "count" => match parse_int(&args.next().ok_or_else(syntax_error)?)? {
+                    v if v > 0 => count = v as usize,
+                    _ => {
+                        return Err(CommandError::InvalidArgument(
+                            "COUNT must be > 0".to_string(),
+                        ))
+                    }
+                },
+                "justid" => justid = true,
+                _ => return Err(syntax_error()),
+            }
+        }
+
+        Ok(XAutoClaim {
+            key,
+            group,
+            consumer,
+            min_idle,
+            start,
+            count,
+            justid,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XInfo {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["xinfo"], 2)?;
+
+        let mut args = extract_strings(value, 1)?.into_iter();
+        let subcommand = args.next().unwrap_or_default().to_ascii_lowercase();
+        let key = args.next().unwrap_or_default();
+        let kind = match (subcommand.as_str(), args.next()) {
+            ("stream", None) => XInfoKind::Stream,
+            ("groups", None) => XInfoKind::Groups,
+            ("consumers", Some(group)) => XInfoKind::Consumers(group),
+            _ => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "unknown subcommand or wrong number of arguments for '{}'",
+                    subcommand
+                )))
+            }
+        };
+        if args.next().is_some() {
+            return Err(syntax_error());
+        }
+        Ok(XInfo { key, kind })
+    }
+}
+
+// parse `[MAXLEN | MINID] [= | ~] threshold [LIMIT count]`
+fn parse_trim(
+    args: &mut Peekable<impl Iterator<Item = String>>,
+) -> Result<Option<StreamTrim>, CommandError> {
+    let maxlen = match args.peek().map(|v| v.to_ascii_lowercase()).as_deref() {
+        Some("maxlen") => true,
+        Some("minid") => false,
+        _ => return Ok(None),
+    };
+    args.next();
+
+    let mut approx = false;
+    if let Some(op) = args.next_if(|v| v == "~" || v == "=") {
+        approx = op == "~";
+    }
+    let threshold = args.next().ok_or_else(syntax_error)?;
+    let strategy = if maxlen {
+        match parse_int(&threshold)? {
+            v if v >= 0 => TrimStrategy::MaxLen(v as usize),
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "The MAXLEN argument must be >= 0.".to_string(),
+                ))
+            }
+        }
+    } else {
+        TrimStrategy::MinId(parse_stream_id(&threshold, 0)?)
+    };
+
+    let mut limit = None;
+    if args.next_if(|v| v.eq_ignore_ascii_case("limit")).is_some() {
+        if !approx {
+            return Err(CommandError::InvalidArgument(
+                "syntax error, LIMIT cannot be used without the special ~ option".to_string(),
+            ));
+        }
+        let count = parse_int(&args.next().ok_or_else(syntax_error)?)?;
+        if count < 0 {
+            return Err(CommandError::InvalidArgument(
+                "The LIMIT argument must be >= 0.".to_string(),
+            ));
+        }
+        limit = Some(count as usize);
+    }
+
+    Ok(Some(StreamTrim {
+        strategy,
+        approx,
+        limit,
+    }))
+}
+
+// parse `ms-seq`, or `ms` with the given default sequence
+fn parse_stream_id(s: &str, default_seq: u64) -> Result<StreamId, CommandError> {
+    let (ms, seq) = match s.split_once('-') {
+        Some((ms, seq)) => (ms, seq.parse().map_err(|_| invalid_stream_id())?),
+        None => (s, default_seq),
+    };
+    let ms = ms.parse().map_err(|_| invalid_stream_id())?;
+    Ok(StreamId::new(ms, seq))
+}
+
+// range ids accept `-`, `+` and a `(` prefix for exclusive bounds
+fn parse_range_id(s: &str, start: bool) -> Result<StreamId, CommandError> {
+    match s {
+        "-" => Ok(StreamId::MIN),
+        "+" => Ok(StreamId::MAX),
+        _ => {
+            let default_seq = if start { 0 } else { u64::MAX };
+            match s.strip_prefix('(') {
+                Some(v) => {
+                    let id = parse_stream_id(v, default_seq)?;
+                    let id = if start { id.next() } else { id.prev() };
+                    id.ok_or_else(|| {
+                        CommandError::InvalidArgument(format!(
+                            "invalid {} ID for the interval",
+                            if start { "start" } else { "end" }
+                        ))
+                    })
+                }
+                None => parse_stream_id(s, default_seq),
+            }
+        }
+    }
+}
+
+// `None` fields are entries deleted while pending, replied as nil
+fn entry_reply(id: StreamId, fields: Option<Vec<(String, String)>>) -> RespFrame {
+    let fields = match fields {
+        Some(fields) => RespArray::new(
+            fields
+                .into_iter()
+                .flat_map(|(f, v)| [BulkString::new(f).into(), BulkString::new(v).into()])
+                .collect::<Vec<RespFrame>>(),
+        ),
+        None => RespArray::null(),
+    };
+    RespArray::new([BulkString::new(id.to_string()).into(), fields.into()]).into()
+}
+
+fn entries_reply(entries: Vec<StreamEntry>) -> RespFrame {
+    let entries = entries
+        .into_iter()
+        .map(|(id, fields)| entry_reply(id, Some(fields)))
+        .collect::<Vec<RespFrame>>();
+    RespArray::new(entries).into()
+}
+
+fn ids_reply(ids: impl Iterator<Item = StreamId>) -> RespFrame {
+    let ids = ids
+        .map(|id| BulkString::new(id.to_string()).into())
+        .collect::<Vec<RespFrame>>();
+    RespArray::new(ids).into()
+}
+
+// XINFO replies are flat field/value lists
+fn info_reply(pairs: Vec<(&str, RespFrame)>) -> RespFrame {
+    let pairs = pairs
+        .into_iter()
+        .flat_map(|(k, v)| [BulkString::new(k).into(), v])
+        .collect::<Vec<RespFrame>>();
+    RespArray::new(pairs).into()
+}
+
+fn error_reply(e: StreamError) -> RespFrame {
+    SimpleError::new(e.to_string()).into()
+}
+
+fn invalid_stream_id() -> CommandError {
+    CommandError::InvalidArgument(
+        "Invalid stream ID specified as stream command argument".to_string(),
+    )
+}
+
+fn syntax_error() -> CommandError {
+    CommandError::InvalidArgument("syntax error".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cmd::execute_blocking, RespDecode};
+    use bytes::BytesMut;
+
+    fn decode(cmd: &[u8]) -> anyhow::Result<RespArray> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(cmd);
+        Ok(RespArray::decode(&mut buf)?)
+    }
+
+    #[test]
+    fn test_xadd_from_resp_array() -> anyhow::Result<()> {
+        let frame = decode(b"*9\r\n$4\r\nxadd\r\n$1\r\ns\r\n$6\r\nMAXLEN\r\n$1\r\n~\r\n$4\r\n1000\r\n$3\r\n5-*\r\n$1\r\nf\r\n$1\r\nv\r\n$1\r\nx\r\n")?;
+        assert!(XAdd::try_from(frame).is_err());
+
+        let frame = decode(b"*8\r\n$4\r\nxadd\r\n$1\r\ns\r\n$6\r\nMAXLEN\r\n$1\r\n~\r\n$4\r\n1000\r\n$3\r\n5-*\r\n$1\r\nf\r\n$1\r\nv\r\n")?;
+        let result: XAdd = frame.try_into()?;
+        assert_eq!(result.key, "s");
+        assert_eq!(result.id, XAddId::AutoSeq(5));
+        assert_eq!(
+            result.trim,
+            Some(StreamTrim {
+                strategy: TrimStrategy::MaxLen(1000),
+                approx: true,
+                limit: None,
+            })
+        );
+        assert_eq!(result.fields, [("f".to_string(), "v".to_string())]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_xrevrange_from_resp_array() -> anyhow::Result<()> {
+        let frame = decode(b"*6\r\n$9\r\nxrevrange\r\n$1\r\ns\r\n$1\r\n+\r\n$4\r\n(5-0\r\n$5\r\nCOUNT\r\n$1\r\n2\r\n")?;
+        let result: XRange = frame.try_into()?;
+        assert!(result.rev);
+        assert_eq!(result.start, StreamId::new(5, 1));
+        assert_eq!(result.end, StreamId::MAX);
+        assert_eq!(result.count, Some(2));
+        Ok(())
+    }
+
+    #[test]
+    fn test_xadd_xrange_cmd() {
+        let backend = Backend::new();
+        for id in ["1-1", "1-2", "2-0"] {
+            let cmd = XAdd {
+                key: "s".to_string(),
+                nomkstream: false,
+                trim: None,
+                id: XAddId::Explicit(parse_stream_id(id, 0).unwrap()),
+                fields: vec![("f".to_string(), id.to_string())],
+            };
+            assert_eq!(cmd.execute(&backend), BulkString::new(id).into());
+        }
+
+        let cmd = XAdd {
+            key: "s".to_string(),
+            nomkstream: false,
+            trim: None,
+            id: XAddId::Explicit(StreamId::new(1, 5)),
+            fields: vec![("f".to_string(), "v".to_string())],
+        };
+        assert!(matches!(cmd.execute(&backend), RespFrame::Error(_)));
+
+        let cmd = XRange {
+            key: "s".to_string(),
+            start: StreamId::new(1, 2),
+            end: StreamId::new(1, u64::MAX),
+            rev: false,
+            count: None,
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([RespArray::new([
+                b"1-2".into(),
+                RespArray::new([b"f".into(), b"1-2".into()]).into()
+            ])
+            .into()])
+            .into()
+        );
+    }
+
+    #[test]
+    fn test_xreadgroup_from_resp_array() -> anyhow::Result<()> {
+        let frame = decode(b"*9\r\n$10\r\nxreadgroup\r\n$5\r\nGROUP\r\n$1\r\ng\r\n$1\r\nc\r\n$7\r\nSTREAMS\r\n$1\r\na\r\n$1\r\nb\r\n$1\r\n>\r\n$1\r\n0\r\n")?;
+        let result: XReadGroup = frame.try_into()?;
+        assert_eq!(result.keys, ["a", "b"]);
+        assert_eq!(result.ids, [None, Some(StreamId::MIN)]);
+        assert!(!result.blocking);
+
+        let frame = decode(b"*9\r\n$10\r\nxreadgroup\r\n$5\r\nGROUP\r\n$1\r\ng\r\n$1\r\nc\r\n$5\r\nBLOCK\r\n$3\r\n100\r\n$7\r\nSTREAMS\r\n$1\r\na\r\n$1\r\n>\r\n")?;
+        let result: XReadGroup = frame.try_into()?;
+        assert!(result.blocking);
+        assert_eq!(result.timeout, Some(Duration::from_millis(100)));
+
+        let frame = decode(b"*7\r\n$10\r\nxreadgroup\r\n$5\r\nGROUP\r\n$1\r\ng\r\n$1\r\nc\r\n$7\r\nSTREAMS\r\n$1\r\na\r\n$1\r\nb\r\n")?;
+        assert!(XReadGroup::try_from(frame).is_err());
+        let frame = decode(b"*6\r\n$10\r\nxreadgroup\r\n$5\r\nGROUP\r\n$1\r\ng\r\n$1\r\nc\r\n$7\r\nSTREAMS\r\n$1\r\na\r\n")?;
+        assert!(XReadGroup::try_from(frame).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_group_cmds() {
+        let backend = Backend::new();
+        let group = |action| XGroup {
+            key: "s".to_string(),
+            group: "g".to_string(),
+            action,
+        };
+        let create = || {
+            group(XGroupAction::Create {
+                id: Some(StreamId::MIN),
+                mkstream: false,
+                entries_read: None,
+            })
+        };
+        assert!(matches!(create().execute(&backend), RespFrame::Error(_)));
+        backend
+            .xadd(
+                "s".to_string(),
+                XAddId::Explicit(StreamId::new(1, 0)),
+                vec![],
+                false,
+                None,
+            )
+            .unwrap();
+        assert_eq!(create().execute(&backend), RESP_OK.clone());
+        assert!(matches!(create().execute(&backend), RespFrame::Error(_)));
+
+        let read = XReadGroup {
+            group: "g".to_string(),
+            consumer: "c".to_string(),
+            count: None,
+            noack: false,
+            keys: vec!["s".to_string()],
+            ids: vec![None],
+            blocking: false,
+            timeout: None,
+        };
+        assert_eq!(
+            read.execute(&backend),
+            RespArray::new([RespArray::new([
+                b"s".into(),
+                RespArray::new([RespArray::new([b"1-0".into(), RespArray::new([]).into()]).into()])
+                    .into()
+            ])
+            .into()])
+            .into()
+        );
+
+        let pending = XPending {
+            key: "s".to_string(),
+            group: "g".to_string(),
+            filter: None,
+        };
+        assert_eq!(
+            pending.execute(&backend),
+            RespArray::new([
+                RespFrame::Integer(1),
+                b"1-0".into(),
+                b"1-0".into(),
+                RespArray::new([RespArray::new([b"c".into(), b"1".into()]).into()]).into(),
+            ])
+            .into()
+        );
+
+        let ack = XAck {
+            key: "s".to_string(),
+            group: "g".to_string(),
+            ids: vec![StreamId::new(1, 0), StreamId::new(2, 0)],
+        };
+        assert_eq!(ack.execute(&backend), RespFrame::Integer(1));
+        assert_eq!(
+            group(XGroupAction::DelConsumer("c".to_string())).execute(&backend),
+            RespFrame::Integer(0)
+        );
+        assert_eq!(
+            group(XGroupAction::Destroy).execute(&backend),
+            RespFrame::Integer(1)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_xreadgroup_wakes_on_xadd() {
+        let backend = Backend::new();
+        backend
+            .xgroup_create("s".to_string(), "g".to_string(), None, true, None)
+            .unwrap();
+        let cmd = XReadGroup {
+            group: "g".to_string(),
+            consumer: "c".to_string(),
+            count: None,
+            noack: true,
+            keys: vec!["s".to_string()],
+            ids: vec![None],
+            blocking: true,
+            timeout: Some(Duration::from_secs(5)),
+        };
+
+        let waiter = {
+            let backend = backend.clone();
+            tokio::spawn(async move { execute_blocking(&cmd, &backend).await })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        backend
+            .xadd(
+                "s".to_string(),
+                XAddId::Explicit(StreamId::new(7, 0)),
+                vec![],
+                false,
+                None,
+            )
+            .unwrap();
+
+        let ret = waiter.await.unwrap();
+        assert!(matches!(ret, Some(RespFrame::Array(_))));
+        assert_eq!(backend.xpending_summary("s", "g").unwrap().count, 0);
+    }
+
+    #[test]
+    fn test_xreadgroup_missing_group_reads_nothing() {
+        let backend = Backend::new();
+        backend
+            .xadd(
+                "a".to_string(),
+                XAddId::Explicit(StreamId::new(1, 0)),
+                vec![],
+                false,
+                None,
+            )
+            .unwrap();
+        backend
+            .xgroup_create(
+                "a".to_string(),
+                "g".to_string(),
+                Some(StreamId::MIN),
+                false,
+                None,
+            )
+            .unwrap();
+        let cmd = XReadGroup {
+            group: "g".to_string(),
+            consumer: "c".to_string(),
+            count: None,
+            noack: false,
+            keys: vec!["a".to_string(), "b".to_string()],
+            ids: vec![None, None],
+            blocking: false,
+            timeout: None,
+        };
+        assert!(matches!(cmd.execute(&backend), RespFrame::Error(_)));
+        // the stream before the missing one has nothing delivered
+        assert_eq!(backend.xpending_summary("a", "g").unwrap().count, 0);
+        let info = backend.xinfo_groups("a").unwrap();
+        assert_eq!(info[0].last_delivered_id, StreamId::MIN);
+    }
+}
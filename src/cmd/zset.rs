@@ -0,0 +1,978 @@
+use super::{
+    command_name, extract_strings, parse_float, parse_int, parse_scan_args, parse_timeout,
+    scan_reply, validate_command, validate_command_at_least, BZPop, BlockingCommand, CommandError,
+    CommandExecutor, ZAdd, ZCard, ZCombine, ZCount, ZMPop, ZPop, ZRange, ZRangeStore, ZRem,
+    ZRemRange, ZScan, ZScore,
+};
+use std::time::Duration;
+
+use crate::{
+    backend::{
+        Aggregate, Backend, LexBound, ScoreBound, ZAddOptions, ZRangeBy, ZRangeSpec, ZSetOp,
+    },
+    resp::double::format_double,
+    BulkString, RespArray, RespFrame, RespNull, SimpleError,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RangeKind {
+    Rank,
+    Score,
+    Lex,
+}
+
+impl CommandExecutor for ZAdd {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let ret = match backend.zadd(self.key, self.members, &self.options) {
+            Ok(ret) => ret,
+            Err(e) => return SimpleError::new(e.to_string()).into(),
+        };
+        if self.options.incr {
+            return match ret.first() {
+                Some(Some(outcome)) => BulkString::new(format_score(outcome.score)).into(),
+                _ => RespFrame::Null(RespNull),
+            };
+        }
+        let changed = ret
+            .iter()
+            .flatten()
+            .filter(|v| v.added || (self.options.ch && v.updated))
+            .count();
+        RespFrame::Integer(changed as i64)
+    }
+}
+
+impl CommandExecutor for ZRem {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.zrem(&self.key, &self.members) as i64)
+    }
+}
+
+impl CommandExecutor for ZScore {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.zscore(&self.key, &self.member) {
+            Some(score) => BulkString::new(format_score(score)).into(),
+            None => RespFrame::Null(RespNull),
+        }
+    }
+}
+
+impl CommandExecutor for ZCard {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.zcard(&self.key) as i64)
+    }
+}
+
+impl CommandExecutor for ZRange {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let items = backend.zrange(&self.key, &self.spec);
+        range_reply(items, self.withscores)
+    }
+}
+
+impl CommandExecutor for ZRangeStore {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.zrangestore(self.dst, &self.src, &self.spec) as i64)
+    }
+}
+
+impl CommandExecutor for ZCount {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.zcount(&self.key, &self.by) as i64)
+    }
+}
+
+impl CommandExecutor for ZRemRange {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.zremrange(&self.key, &self.by) as i64)
+    }
+}
+
+impl CommandExecutor for ZCombine {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match self.dst {
+            Some(dst) => {
+                let len =
+                    backend.zcombinestore(dst, self.op, &self.keys, &self.weights, self.aggregate);
+                RespFrame::Integer(len as i64)
+            }
+            None => {
+                let zset = backend.zcombine(self.op, &self.keys, &self.weights, self.aggregate);
+                let items = zset.iter().map(|(m, s)| (m.to_string(), s)).collect();
+                range_reply(items, self.withscores)
+            }
+        }
+    }
+}
+
+impl CommandExecutor for ZPop {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let items = backend.zpop(&self.key, self.min, self.count.unwrap_or(1));
+        range_reply(items, true)
+    }
+}
+
+impl CommandExecutor for BZPop {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        self.try_execute(backend)
+            .unwrap_or_else(|| self.timeout_reply())
+    }
+}
+
+impl BlockingCommand for BZPop {
+    fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    fn try_execute(&self, backend: &Backend) -> Option<RespFrame> {
+        self.keys.iter().find_map(|key| {
+            let (member, score) = backend.zpop(key, self.min, 1).pop()?;
+            Some(
+                RespArray::new([
+                    BulkString::new(key.as_str()).into(),
+                    BulkString::new(member).into(),
+                    BulkString::new(format_score(score)).into(),
+                ])
+                .into(),
+            )
+        })
+    }
+}
+
+impl CommandExecutor for ZMPop {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        self.try_execute(backend)
+            .unwrap_or_else(|| self.timeout_reply())
+    }
+}
+
+impl BlockingCommand for ZMPop {
+    fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    fn try_execute(&self, backend: &Backend) -> Option<RespFrame> {
+        self.keys.iter().find_map(|key| {
+            let items = backend.zpop(key, self.min, self.count);
+            if items.is_empty() {
+                return None;
+            }
+            let items = items
+                .into_iter()
+                .map(|(member, score)| {
+                    RespArray::new([
+                        BulkString::new(member).into(),
+                        BulkString::new(format_score(score)).into(),
+                    ])
+                    .into()
+                })
+                .collect::<Vec<RespFrame>>();
+            Some(
+                RespArray::new([
+                    BulkString::new(key.as_str()).into(),
+                    RespArray::new(items).into(),
+                ])
+                .into(),
+            )
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZAdd {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["zadd"], 3)?;
+
+        let mut args = extract_strings(value, 1)?.into_iter().peekable();
+        let key = args.next().unwrap_or_default();
+        let mut options = ZAddOptions::default();
+        while let Some(flag) = args.peek() {
+            match flag.to_ascii_lowercase().as_str() {
+                "nx" => options.nx = true,
+                "xx" => options.xx = true,
+                "gt" => options.gt = true,
+                "lt" => options.lt = true,
+                "ch" => options.ch = true,
+                "incr" => options.incr = true,
+                _ => break,
+            }
+            args.next();
+        }
+
+        if options.nx && options.xx {
+            return Err(CommandError::InvalidArgument(
+                "XX and NX options at the same time are not compatible".to_string(),
+            ));
+        }
+        if (options.gt && options.lt) || ((options.gt || options.lt) && options.nx) {
+            return Err(CommandError::InvalidArgument(
+                "GT, LT, and/or NX options at the same time are not compatible".to_string(),
+            ));
+        }
+
+        let rest: Vec<String> = args.collect();
+        if rest.is_empty() || !rest.len().is_multiple_of(2) {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        if options.incr && rest.len() != 2 {
+            return Err(CommandError::InvalidArgument(
+                "INCR option supports a single increment-element pair".to_string(),
+            ));
+        }
+
+        let members = rest
+            .chunks(2)
+            .map(|pair| Ok((parse_float(&pair[0])?, pair[1].clone())))
+            .collect::<Result<Vec<_>, CommandError>>()?;
+
+        Ok(ZAdd {
+            key,
+            options,
+            members,
+        })
+    }
+}
+
+impl CommandExecutor for ZScan {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let (cursor, members) = backend.zscan(&self.key, self.cursor, &self.opts);
+        let items = members
+            .into_iter()
+            .flat_map(|(member, score)| {
+                let score = (!self.noscores).then(|| BulkString::new(format_score(score)).into());
+                std::iter::once(BulkString::new(member).into()).chain(score)
+            })
+            .collect();
+        scan_reply(cursor, items)
+    }
+}
+
+impl TryFrom<RespArray> for ZRem {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["zrem"], 2)?;
+
+        let mut args = extract_strings(value, 1)?.into_iter();
+        let key = args.next().unwrap_or_default();
+        Ok(ZRem {
+            key,
+            members: args.collect(),
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZScore {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zscore"], 2)?;
+
+        let mut args = extract_strings(value, 1)?.into_iter();
+        match (args.next(), args.next()) {
+            (Some(key), Some(member)) => Ok(ZScore { key, member }),
+            _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for ZCard {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zcard"], 1)?;
+
+        let mut args = extract_strings(value, 1)?.into_iter();
+        match args.next() {
+            Some(key) => Ok(ZCard { key }),
+            _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for ZRange {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, kind, rev, unified) = match command_name(&value).as_str() {
+            "zrange" => ("zrange", RangeKind::Rank, false, true),
+            "zrevrange" => ("zrevrange", RangeKind::Rank, true, false),
+            "zrangebyscore" => ("zrangebyscore", RangeKind::Score, false, false),
+            "zrevrangebyscore" => ("zrevrangebyscore", RangeKind::Score, true, false),
+            "zrangebylex" => ("zrangebylex", RangeKind::Lex, false, false),
+            "zrevrangebylex" => ("zrevrangebylex", RangeKind::Lex, true, false),
+            v => return Err(CommandError::InvalidCommand(v.to_string())),
+        };
+        validate_command_at_least(&value, &[name], 3)?;
+
+        let mut args = extract_strings(value, 1)?.into_iter();
+        let key = args.next().unwrap_or_default();
+        let (spec, withscores) = parse_range(kind, rev, unified, args)?;
+        Ok(ZRange {
+            key,
+            spec,
+            withscores,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZRangeStore {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["zrangestore"], 4)?;
+
+        let mut args = extract_strings(value, 1)?.into_iter();
+        let dst = args.next().unwrap_or_default();
+        let src = args.next().unwrap_or_default();
+        match parse_range(RangeKind::Rank, false, true, args)? {
+            (spec, false) => Ok(ZRangeStore { dst, src, spec }),
+            (_, true) => Err(CommandError::InvalidArgument("syntax error".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for ZCount {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, kind) = match command_name(&value).as_str() {
+            "zcount" => ("zcount", RangeKind::Score),
+            "zlexcount" => ("zlexcount", RangeKind::Lex),
+            v => return Err(CommandError::InvalidCommand(v.to_string())),
+        };
+        validate_command(&value, &[name], 3)?;
+
+        let args = extract_strings(value, 1)?;
+        Ok(ZCount {
+            key: args[0].clone(),
+            by: parse_range_by(kind, &args[1], &args[2])?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZRemRange {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, kind) = match command_name(&value).as_str() {
+            "zremrangebyrank" => ("zremrangebyrank", RangeKind::Rank),
+            "zremrangebyscore" => ("zremrangebyscore", RangeKind::Score),
+            "zremrangebylex" => ("zremrangebylex", RangeKind::Lex),
+            v => return Err(CommandError::InvalidCommand(v.to_string())),
+        };
+        validate_command(&value, &[name], 3)?;
+
+        let args = extract_strings(value, 1)?;
+        Ok(ZRemRange {
+            key: args[0].clone(),
+            by: parse_range_by(kind, &args[1], &args[2])?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZCombine {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let name = command_name(&value);
+        let (name, op, store) = match name.as_str() {
+            "zunion" => ("zunion", ZSetOp::Union, false),
+            "zinter" => ("zinter", ZSetOp::Inter, false),
+            "zdiff" => ("zdiff", ZSetOp::Diff, false),
+            "zunionstore" => ("zunionstore", ZSetOp::Union, true),
+            "zinterstore" => ("zinterstore", ZSetOp::Inter, true),
+            "zdiffstore" => ("zdiffstore", ZSetOp::Diff, true),
+            v => return Err(CommandError::InvalidCommand(v.to_string())),
+        };
+        validate_command_at_least(&value, &[name], if store { 3 } else { 2 })?;
+
+        let syntax_error = || CommandError::InvalidArgument("syntax error".to_string());
+        let mut args = extract_strings(value, 1)?.into_iter();
+        let dst = if store { args.next() } else { None };
+        let numkeys = parse_int(&args.next().ok_or_else(syntax_error)?)?;
+        if numkeys <= 0 {
+            return Err(CommandError::InvalidArgument(format!(
+                "at least 1 input key is needed for '{}' command",
+                name
+            )));
+        }
+        let keys: Vec<String> = args.by_ref().take(numkeys as usize).collect();
+        if keys.len() != numkeys as usize {
+            return Err(syntax_error());
+        }
+
+        let mut weights = vec![];
+        let mut aggregate = Aggregate::default();
+        let mut withscores = false;
+        while let Some(opt) = args.next() {
+            match opt.to_ascii_lowercase().as_str() {
+                "weights" if op != ZSetOp::Diff => {
+                    weights = args
+                        .by_ref()
+                        .take(keys.len())
+                        .map(|w| {
+                            parse_float(&w).map_err(|_| {
+                                CommandError::InvalidArgument(
+                                    "weight value is not a float".to_string(),
+                                )
+                            })
+                        })
+                        .collect::<Result<_, _>>()?;
+                    if weights.len() != keys.len() {
+                        return Err(syntax_error());
+                    }
+                }
+                "aggregate" if op != ZSetOp::Diff => {
+                    let v = args.next().ok_or_else(syntax_error)?;
+                    aggregate = match v.to_ascii_lowercase().as_str() {
+                        "sum" => Aggregate::Sum,
+                        "min" => Aggregate::Min,
+                        "max" => Aggregate::Max,
+                        _ => return Err(syntax_error()),
+                    };
+                }
+                "withscores" if !store => withscores = true,
+                _ => return Err(syntax_error()),
+            }
+        }
+
+        Ok(ZCombine {
+            op,
+            dst,
+            keys,
+            weights,
+            aggregate,
+            withscores,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZPop {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, min) = match command_name(&value).as_str() {
+            "zpopmin" => ("zpopmin", true),
+            "zpopmax" => ("zpopmax", false),
+            v => return Err(CommandError::InvalidCommand(v.to_string())),
+        };
+        validate_command_at_least(&value, &[name], 1)?;
+        if value.len() > 3 {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+
+        let mut args = extract_strings(value, 1)?.into_iter();
+        let key = args.next().unwrap_or_default();
+        let count = match args.next() {
+            Some(count) => Some(parse_count(&count)?),
+            None => None,
+        };
+        Ok(ZPop { key, min, count })
+    }
+}
+
+impl TryFrom<RespArray> for BZPop {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, min) = match command_name(&value).as_str() {
+            "bzpopmin" => ("bzpopmin", true),
+            "bzpopmax" => ("bzpopmax", false),
+            v => return Err(CommandError::InvalidCommand(v.to_string())),
+        };
+        validate_command_at_least(&value, &[name], 2)?;
+
+        let mut keys = extract_strings(value, 1)?;
+        let timeout = parse_timeout(&keys.pop().unwrap_or_default())?;
+        Ok(BZPop { keys, min, timeout })
+    }
+}
+
+impl TryFrom<RespArray> for ZMPop {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, blocking) = match command_name(&value).as_str() {
+            "zmpop" => ("zmpop", false),
+            "bzmpop" => ("bzmpop", true),
+            v => return Err(CommandError::InvalidCommand(v.to_string())),
+        };
+        validate_command_at_least(&value, &[name], if blocking { 4 } else { 3 })?;
+
+        let syntax_error = || CommandError::InvalidArgument("syntax error".to_string());
+        let mut args = extract_strings(value, 1)?.into_iter();
+        let timeout = match blocking {
+            true => parse_timeout(&args.next().unwrap_or_default())?,
+            false => None,
+        };
+        let numkeys = parse_int(&args.next().ok_or_else(syntax_error)?)?;
+        if numkeys <= 0 {
+            return Err(CommandError::InvalidArgument(
+                "numkeys should be greater than 0".to_string(),
+            ));
+        }
+        let keys: Vec<String> = args.by_ref().take(numkeys as usize).collect();
+        if keys.len() != numkeys as usize {
+            return Err(syntax_error());
+        }
+        let min = match args.next().map(|v| v.to_ascii_lowercase()).as_deref() {
+            Some("min") => true,
+            Some("max") => false,
+            _ => return Err(syntax_error()),
+        };
+        let count = match (args.next(), args.next()) {
+            (Some(opt), Some(count)) if opt.eq_ignore_ascii_case("count") => parse_count(&count)?,
+            (None, None) => 1,
+            _ => return Err(syntax_error()),
+        };
+        if args.next().is_some() {
+            return Err(syntax_error());
+        }
+
+        Ok(ZMPop {
+            keys,
+            min,
+            count,
+            blocking,
+            timeout,
+        })
+    }
+}
+
+fn parse_count(s: &str) -> Result<usize, CommandError> {
+    match parse_int(s)? {
+        count if count > 0 => Ok(count as usize),
+        _ => Err(CommandError::InvalidArgument(
+            "count should be greater than 0".to_string(),
+        )),
+    }
+}
+
+fn range_reply(items: Vec<(String, f64)>, withscores: bool) -> RespFrame {
+    let mut ret = Vec::with_capacity(items.len() * 2);
+    for (member, score) in items {
+        ret.push(BulkString::new(member).into());
+        if withscores {
+            ret.push(BulkString::new(format_score(score)).into());
+        }
+    }
+    RespArray::new(ret).into()
+}
+
+// parse `start stop [BYSCORE|BYLEX] [REV] [LIMIT offset count] [WITHSCORES]`,
+// legacy commands have a fixed kind and direction and only accept LIMIT/WITHSCORES
+fn parse_range(
+    mut kind: RangeKind,
+    mut rev: bool,
+    unified: bool,
+    mut args: impl Iterator<Item = String>,
+) -> Result<(ZRangeSpec, bool), CommandError> {
+    let syntax_error = || CommandError::InvalidArgument("syntax error".to_string());
+    let start = args.next().ok_or_else(syntax_error)?;
+    let stop = args.next().ok_or_else(syntax_error)?;
+
+    let mut limit = None;
+    let mut withscores = false;
+    while let Some(opt) = args.next() {
+        match opt.to_ascii_lowercase().as_str() {
+            "byscore" if unified => kind = RangeKind::Score,
+            "bylex" if unified => kind = RangeKind::Lex,
+            "rev" if unified => rev = true,
+            "withscores" => withscores = true,
+            "limit" if kind != RangeKind::Rank || unified => {
+                let offset = parse_int(&args.next().ok_or_else(syntax_error)?)?;
+                let count = parse_int(&args.next().ok_or_else(syntax_error)?)?;
+                limit = Some((offset, count));
+            }
+            _ => return Err(syntax_error()),
+        }
+    }
+
+    if limit.is_some() && kind == RangeKind::Rank {
+        return Err(CommandError::InvalidArgument(
+            "syntax error, LIMIT is only supported in combination with either BYSCORE or BYLEX"
+                .to_string(),
+        ));
+    }
+    if withscores && kind == RangeKind::Lex {
+        return Err(CommandError::InvalidArgument(
+            "syntax error, WITHSCORES not supported in combination with BYLEX".to_string(),
+        ));
+    }
+
+    // score and lex ranges are given as `max min` when reversed
+    let by = match (kind, rev) {
+        (RangeKind::Rank, _) | (_, false) => parse_range_by(kind, &start, &stop)?,
+        (_, true) => parse_range_by(kind, &stop, &start)?,
+    };
+    Ok((ZRangeSpec { by, rev, limit }, withscores))
+}
+
+fn parse_range_by(kind: RangeKind, min: &str, max: &str) -> Result<ZRangeBy, CommandError> {
+    match kind {
+        RangeKind::Rank => Ok(ZRangeBy::Rank(parse_int(min)?, parse_int(max)?)),
+        RangeKind::Score => Ok(ZRangeBy::Score(
+            parse_score_bound(min)?,
+            parse_score_bound(max)?,
+        )),
+        RangeKind::Lex => Ok(ZRangeBy::Lex(parse_lex_bound(min)?, parse_lex_bound(max)?)),
+    }
+}
+
+fn parse_score_bound(s: &str) -> Result<ScoreBound, CommandError> {
+    let err = |_| CommandError::InvalidArgument("min or max is not a float".to_string());
+    match s.strip_prefix('(') {
+        Some(v) => Ok(ScoreBound::Exclusive(parse_float(v).map_err(err)?)),
+        None => Ok(ScoreBound::Inclusive(parse_float(s).map_err(err)?)),
+    }
+}
+
+fn parse_lex_bound(s: &str) -> Result<LexBound, CommandError> {
+    match s {
+        "-" => Ok(LexBound::Min),
+        "+" => Ok(LexBound::Max),
+        _ => match (s.strip_prefix('['), s.strip_prefix('(')) {
+            (Some(v), _) => Ok(LexBound::Inclusive(v.to_string())),
+            (_, Some(v)) => Ok(LexBound::Exclusive(v.to_string())),
+            _ => Err(CommandError::InvalidArgument(
+                "min or max not valid string range item".to_string(),
+            )),
+        },
+    }
+}
+
+impl TryFrom<RespArray> for ZScan {
+    type Error = CommandError;
+
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command_at_least(&value, &["zscan"], 2)?;
+
+        let mut args = extract_strings(value, 1)?.into_iter();
+        let key = args.next().unwrap_or_default();
+        let mut noscores = false;
+        let (cursor, opts) = parse_scan_args(&mut args, |opt, _| {
+            noscores |= opt == "noscores";
+            Ok(opt == "noscores")
+        })?;
+        Ok(ZScan {
+            key,
+            cursor,
+            opts,
+            noscores,
+        })
+    }
+}
+
+fn format_score(score: f64) -> String {
+    format_double(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cmd::execute_blocking, RespDecode};
+    use bytes::BytesMut;
+    use futures::FutureExt;
+
+    fn decode(cmd: &[u8]) -> anyhow::Result<RespArray> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(cmd);
+        Ok(RespArray::decode(&mut buf)?)
+    }
+
+    #[test]
+    fn test_zrange_from_resp_array() -> anyhow::Result<()> {
+        let frame = decode(b"*9\r\n$6\r\nzrange\r\n$1\r\nz\r\n$2\r\n+1\r\n$2\r\n(0\r\n$7\r\nBYSCORE\r\n$3\r\nREV\r\n$5\r\nLIMIT\r\n$1\r\n0\r\n$1\r\n2\r\n")?;
+        let result: ZRange = frame.try_into()?;
+        assert_eq!(result.key, "z");
+        assert_eq!(
+            result.spec,
+            ZRangeSpec {
+                by: ZRangeBy::Score(ScoreBound::Exclusive(0.0), ScoreBound::Inclusive(1.0)),
+                rev: true,
+                limit: Some((0, 2)),
+            }
+        );
+        assert!(!result.withscores);
+        Ok(())
+    }
+
+    #[test]
+    fn test_zrevrangebylex_from_resp_array() -> anyhow::Result<()> {
+        let frame = decode(b"*4\r\n$14\r\nzrevrangebylex\r\n$1\r\nz\r\n$1\r\n+\r\n$2\r\n[b\r\n")?;
+        let result: ZRange = frame.try_into()?;
+        assert_eq!(
+            result.spec.by,
+            ZRangeBy::Lex(LexBound::Inclusive("b".to_string()), LexBound::Max)
+        );
+        assert!(result.spec.rev);
+        Ok(())
+    }
+
+    #[test]
+    fn test_zrange_invalid_options() -> anyhow::Result<()> {
+        let frame = decode(b"*7\r\n$6\r\nzrange\r\n$1\r\nz\r\n$1\r\n0\r\n$2\r\n-1\r\n$5\r\nLIMIT\r\n$1\r\n0\r\n$1\r\n2\r\n")?;
+        assert!(ZRange::try_from(frame).is_err());
+
+        let frame = decode(b"*4\r\n$13\r\nzrangebyscore\r\n$1\r\nz\r\n$1\r\na\r\n$1\r\n1\r\n")?;
+        assert!(ZRange::try_from(frame).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_zadd_zrange_cmd() {
+        let backend = Backend::new();
+        let cmd = ZAdd {
+            key: "z".to_string(),
+            options: ZAddOptions::default(),
+            members: vec![
+                (1.0, "a".to_string()),
+                (2.5, "b".to_string()),
+                (f64::INFINITY, "c".to_string()),
+            ],
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(3));
+
+        let cmd = ZRange {
+            key: "z".to_string(),
+            spec: ZRangeSpec {
+                by: ZRangeBy::Score(
+                    ScoreBound::Exclusive(1.0),
+                    ScoreBound::Inclusive(f64::INFINITY),
+                ),
+                rev: false,
+                limit: None,
+            },
+            withscores: true,
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([b"b".into(), b"2.5".into(), b"c".into(), b"inf".into()]).into()
+        );
+
+        let cmd = ZRemRange {
+            key: "z".to_string(),
+            by: ZRangeBy::Rank(0, -1),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(3));
+        assert!(backend.zset.get("z").is_none());
+    }
+
+    #[test]
+    fn test_zadd_incr_nan() {
+        let backend = Backend::new();
+        let incr = |score: f64| ZAdd {
+            key: "z".to_string(),
+            options: ZAddOptions {
+                incr: true,
+                ..Default::default()
+            },
+            members: vec![(score, "m".to_string())],
+        };
+        assert_eq!(incr(f64::INFINITY).execute(&backend), b"inf".into());
+        assert_eq!(
+            incr(f64::NEG_INFINITY).execute(&backend),
+            SimpleError::new("ERR resulting score is not a number (NaN)").into()
+        );
+        assert_eq!(backend.zscore("z", "m"), Some(f64::INFINITY));
+    }
+
+    #[test]
+    fn test_zmpop_from_resp_array() -> anyhow::Result<()> {
+        let frame = decode(b"*8\r\n$6\r\nbzmpop\r\n$3\r\n0.5\r\n$1\r\n2\r\n$1\r\na\r\n$1\r\nb\r\n$3\r\nMAX\r\n$5\r\nCOUNT\r\n$1\r\n3\r\n")?;
+        let result: ZMPop = frame.try_into()?;
+        assert_eq!(result.keys, ["a", "b"]);
+        assert!(!result.min);
+        assert_eq!(result.count, 3);
+        assert!(result.blocking);
+        assert_eq!(result.timeout, Some(Duration::from_millis(500)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_bzpopmin_timeout_out_of_range() -> anyhow::Result<()> {
+        for timeout in ["1e300", "inf", "nan"] {
+            let frame = decode(
+                format!(
+                    "*3\r\n$8\r\nbzpopmin\r\n$1\r\nq\r\n${}\r\n{}\r\n",
+                    timeout.len(),
+                    timeout
+                )
+                .as_bytes(),
+            )?;
+            let err = BZPop::try_from(frame).unwrap_err();
+            assert_eq!(err.to_string(), "Invalid argument: timeout is out of range");
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_bzpopmin_wakes_on_zadd() {
+        let backend = Backend::new();
+        let cmd = BZPop {
+            keys: vec!["q".to_string()],
+            min: true,
+            timeout: Some(Duration::from_secs(5)),
+        };
+
+        let waiter = {
+            let backend = backend.clone();
+            tokio::spawn(async move { execute_blocking(&cmd, &backend).await })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        backend
+            .zadd(
+                "q".to_string(),
+                vec![(1.0, "job".to_string())],
+                &ZAddOptions::default(),
+            )
+            .unwrap();
+
+        assert_eq!(
+            waiter.await.unwrap(),
+            Some(RespArray::new([b"q".into(), b"job".into(), b"1".into()]).into())
+        );
+        assert_eq!(backend.zcard("q"), 0);
+    }
+
+    #[test]
+    fn test_blocked_clients_woken_in_order() {
+        let backend = Backend::new();
+        let keys = ["q".to_string()];
+        let first = backend.block_on_keys(&keys);
+        let second = backend.block_on_keys(&keys);
+
+        // a single member is for the longest waiting client only
+        backend
+            .zadd(
+                "q".to_string(),
+                vec![(1.0, "job".to_string())],
+                &ZAddOptions::default(),
+            )
+            .unwrap();
+        assert!(first.notified().now_or_never().is_some());
+        assert!(second.notified().now_or_never().is_none());
+
+        // it goes to the next one if the first leaves without it
+        backend.unblock_keys(&keys, &first);
+        assert!(second.notified().now_or_never().is_some());
+        backend.unblock_keys(&keys, &second);
+    }
+
+    #[tokio::test]
+    async fn test_bzpopmin_timeout() {
+        let backend = Backend::new();
+        let cmd = BZPop {
+            keys: vec!["q".to_string()],
+            min: true,
+            timeout: Some(Duration::from_millis(10)),
+        };
+        assert_eq!(
+            execute_blocking(&cmd, &backend).await,
+            Some(RespArray::null().into())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bzpopmin_released_on_shutdown() {
+        let backend = Backend::new();
+        let cmd = BZPop {
+            keys: vec!["q".to_string()],
+            min: true,
+            timeout: None,
+        };
+        let waiter = {
+            let backend = backend.clone();
+            tokio::spawn(async move { execute_blocking(&cmd, &backend).await })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        backend.shutdown(Default::default());
+        assert_eq!(waiter.await.unwrap(), None);
+    }
+
+    #[test]
+    fn test_zunionstore_from_resp_array() -> anyhow::Result<()> {
+        let frame = decode(b"*10\r\n$11\r\nzunionstore\r\n$3\r\nout\r\n$1\r\n2\r\n$1\r\na\r\n$1\r\nb\r\n$7\r\nWEIGHTS\r\n$1\r\n2\r\n$1\r\n3\r\n$9\r\nAGGREGATE\r\n$3\r\nmax\r\n")?;
+        let result: ZCombine = frame.try_into()?;
+        assert_eq!(result.op, ZSetOp::Union);
+        assert_eq!(result.dst.as_deref(), Some("out"));
+        assert_eq!(result.keys, ["a", "b"]);
+        assert_eq!(result.weights, [2.0, 3.0]);
+        assert_eq!(result.aggregate, Aggregate::Max);
+
+        let frame = decode(
+            b"*6\r\n$5\r\nzdiff\r\n$1\r\n1\r\n$1\r\na\r\n$7\r\nWEIGHTS\r\n$1\r\n2\r\n$1\r\n3\r\n",
+        )?;
+        assert!(ZCombine::try_from(frame).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_zinter_cmd() {
+        let backend = Backend::new();
+        let members = |v: &[(f64, &str)]| v.iter().map(|(s, m)| (*s, m.to_string())).collect();
+        backend
+            .zadd(
+                "a".to_string(),
+                members(&[(1.0, "x"), (2.0, "y")]),
+                &ZAddOptions::default(),
+            )
+            .unwrap();
+        backend
+            .zadd(
+                "b".to_string(),
+                members(&[(3.0, "y"), (4.0, "z")]),
+                &ZAddOptions::default(),
+            )
+            .unwrap();
+
+        let cmd = ZCombine {
+            op: ZSetOp::Inter,
+            dst: None,
+            keys: vec!["a".to_string(), "b".to_string()],
+            weights: vec![],
+            aggregate: Aggregate::Sum,
+            withscores: true,
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([b"y".into(), b"5".into()]).into()
+        );
+    }
+
+    #[test]
+    fn test_zunion_first_key_missing() {
+        let backend = Backend::new();
+        backend
+            .zadd(
+                "q".to_string(),
+                vec![(1.0, "a".to_string())],
+                &ZAddOptions::default(),
+            )
+            .unwrap();
+        let union = |dst: Option<&str>| ZCombine {
+            op: ZSetOp::Union,
+            dst: dst.map(String::from),
+            keys: vec!["missing".to_string(), "q".to_string()],
+            weights: vec![],
+            aggregate: Aggregate::Sum,
+            withscores: true,
+        };
+        assert_eq!(
+            union(None).execute(&backend),
+            RespArray::new([b"a".into(), b"1".into()]).into()
+        );
+        assert_eq!(union(Some("out")).execute(&backend), RespFrame::Integer(1));
+        assert_eq!(backend.zscore("out", "a"), Some(1.0));
+    }
+}
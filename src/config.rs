@@ -0,0 +1,753 @@
+use std::{
+    fs,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+use crate::backend::DEFAULT_DATABASES;
+
+/// Server configuration, read from a redis.conf style file and overridden by
+/// `--<directive> <args>` on the command line:
+///
+/// ```text
+/// simple-redis [/path/to/redis.conf] [--port 7777] [--bind 127.0.0.1 ::1]
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Config {
+    pub bind: Vec<String>,
+    pub port: u16,
+    /// Path of a Unix socket to listen on as well.
+    pub unixsocket: Option<PathBuf>,
+    /// Permissions of the Unix socket, 0 to keep the umask default.
+    pub unixsocketperm: u32,
+    /// Seconds a client may stay idle before it is closed, 0 to never close.
+    pub timeout: u64,
+    /// Interval of the TCP keepalive probes in seconds, 0 to disable them.
+    pub tcp_keepalive: u64,
+    /// Port for TLS connections, 0 to disable TLS.
+    pub tls_port: u16,
+    pub tls_cert_file: Option<PathBuf>,
+    pub tls_key_file: Option<PathBuf>,
+    pub tls_ca_cert_file: Option<PathBuf>,
+    /// `yes`, `no` or `optional`: whether clients must present a certificate
+    /// signed by `tls_ca_cert_file`.
+    pub tls_auth_clients: String,
+    pub databases: usize,
+    /// Connections past this number are refused.
+    pub maxclients: usize,
+    /// Bytes, 0 for no limit. Only reported, keys are never evicted.
+    pub maxmemory: u64,
+    /// Only reported, like `maxmemory`.
+    pub maxmemory_policy: String,
+    pub proto_max_bulk_len: usize,
+    /// Seconds to wait on shutdown for the clients to finish their commands.
+    pub shutdown_timeout: u64,
+    pub loglevel: String,
+    pub hide_user_data_from_log: bool,
+    /// The file the configuration was read from, for CONFIG REWRITE.
+    pub config_file: Option<PathBuf>,
+}
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Bad directive or wrong number of arguments")]
+    BadDirective,
+    #[error("argument couldn't be parsed into an integer")]
+    InvalidInteger,
+    #[error("argument must be a memory value")]
+    InvalidMemory,
+    #[error("argument must be 'yes' or 'no'")]
+    InvalidBool,
+    #[error("argument must be one of {0}")]
+    InvalidEnum(&'static str),
+    #[error("argument must be between {0} and {1} inclusive")]
+    OutOfRange(u64, u64),
+    #[error("Invalid bind address '{0}'")]
+    InvalidBindAddress(String),
+    #[error("unbalanced quotes in configuration line")]
+    UnbalancedQuotes,
+    #[error("can't open config file '{0}': {1}")]
+    Io(PathBuf, std::io::Error),
+    #[error("{path}:{line}: '{text}': {source}")]
+    File {
+        path: PathBuf,
+        line: usize,
+        text: String,
+        source: Box<ConfigError>,
+    },
+    #[error("'{text}': {source}")]
+    Arg {
+        text: String,
+        source: Box<ConfigError>,
+    },
+}
+
+const LOGLEVELS: &str = "debug, verbose, notice, warning, nothing";
+const MAXMEMORY_POLICIES: &str = "volatile-lru, volatile-lfu, volatile-random, volatile-ttl, \
+                                  allkeys-lru, allkeys-lfu, allkeys-random, noeviction";
+
+// marks the directives CONFIG REWRITE appends to the file
+const REWRITE_SIGNATURE: &str = "# Generated by CONFIG REWRITE";
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            bind: vec!["0.0.0.0".to_string()],
+            port: 6378,
+            unixsocket: None,
+            unixsocketperm: 0,
+            timeout: 0,
+            tcp_keepalive: 300,
+            tls_port: 0,
+            tls_cert_file: None,
+            tls_key_file: None,
+            tls_ca_cert_file: None,
+            tls_auth_clients: "yes".to_string(),
+            databases: DEFAULT_DATABASES,
+            maxclients: 10000,
+            maxmemory: 0,
+            maxmemory_policy: "noeviction".to_string(),
+            proto_max_bulk_len: 512 * 1024 * 1024,
+            shutdown_timeout: 10,
+            loglevel: "notice".to_string(),
+            hide_user_data_from_log: false,
+            config_file: None,
+        }
+    }
+}
+
+impl Config {
+    /// Names of the directives, in the order CONFIG GET lists them.
+    pub const DIRECTIVES: &'static [&'static str] = &[
+        "bind",
+        "port",
+        "unixsocket",
+        "unixsocketperm",
+        "timeout",
+        "tcp-keepalive",
+        "tls-port",
+        "tls-cert-file",
+        "tls-key-file",
+        "tls-ca-cert-file",
+        "tls-auth-clients",
+        "databases",
+        "maxclients",
+        "maxmemory",
+        "maxmemory-policy",
+        "proto-max-bulk-len",
+        "shutdown-timeout",
+        "loglevel",
+        "hide-user-data-from-log",
+    ];
+
+    /// Directives only read at startup, CONFIG SET refuses them.
+    pub const IMMUTABLE: &'static [&'static str] = &[
+        "bind",
+        "port",
+        "unixsocket",
+        "unixsocketperm",
+        "tls-port",
+        "databases",
+    ];
+
+    /// Directives of the TLS setup, setting one reloads the certificates.
+    pub const TLS: &'static [&'static str] = &[
+        "tls-cert-file",
+        "tls-key-file",
+        "tls-ca-cert-file",
+        "tls-auth-clients",
+    ];
+
+    /// The configuration given by the command line arguments, without the
+    /// program name: an optional config file then directives.
+    pub fn from_args<I>(args: I) -> Result<Self, ConfigError>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut config = Config::default();
+        let mut args = args.into_iter().peekable();
+        if let Some(path) = args.next_if(|arg| !arg.starts_with("--")) {
+            config.load_file(&path)?;
+            config.config_file = Some(path.into());
+        }
+
+        let mut directive: Option<(String, Vec<String>)> = None;
+        for arg in args.map(Some).chain([None]) {
+            match arg {
+                Some(arg) if !arg.starts_with("--") => match directive.as_mut() {
+                    Some((_, values)) => values.push(arg),
+                    None => {
+                        return Err(ConfigError::Arg {
+                            text: arg,
+                            source: Box::new(ConfigError::BadDirective),
+                        })
+                    }
+                },
+                arg => {
+                    if let Some((name, values)) = directive.take() {
+                        config.set(&name, &values).map_err(|e| ConfigError::Arg {
+                            text: format!("--{} {}", name, values.join(" ")),
+                            source: Box::new(e),
+                        })?;
+                    }
+                    directive = arg.map(|arg| (arg[2..].to_string(), vec![]));
+                }
+            }
+        }
+        Ok(config)
+    }
+
+    pub fn load_file(&mut self, path: impl AsRef<Path>) -> Result<(), ConfigError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|e| ConfigError::Io(path.into(), e))?;
+        self.load_str(&text).map_err(|e| match e {
+            ConfigError::File {
+                line, text, source, ..
+            } => ConfigError::File {
+                path: path.into(),
+                line,
+                text,
+                source,
+            },
+            e => e,
+        })
+    }
+
+    /// Apply the directives of a config file, one per line.
+    pub fn load_str(&mut self, text: &str) -> Result<(), ConfigError> {
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            split_args(line)
+                .and_then(|args| match args.split_first() {
+                    Some((name, values)) => self.set(name, values),
+                    None => Ok(()),
+                })
+                .map_err(|e| ConfigError::File {
+                    path: PathBuf::new(),
+                    line: i + 1,
+                    text: line.to_string(),
+                    source: Box::new(e),
+                })?;
+        }
+        Ok(())
+    }
+
+    /// Set a directive from its arguments, names are case insensitive.
+    pub fn set(&mut self, name: &str, args: &[String]) -> Result<(), ConfigError> {
+        if name.eq_ignore_ascii_case("bind") {
+            let bind = args
+                .iter()
+                .flat_map(|arg| arg.split_whitespace())
+                .map(|addr| parse_bind(addr).map(|_| addr.to_string()))
+                .collect::<Result<Vec<_>, _>>()?;
+            if bind.is_empty() {
+                return Err(ConfigError::BadDirective);
+            }
+            self.bind = bind;
+            return Ok(());
+        }
+
+        let [arg] = args else {
+            return Err(ConfigError::BadDirective);
+        };
+        match name.to_ascii_lowercase().as_str() {
+            "port" => self.port = parse_int(arg, 0, u16::MAX.into())? as u16,
+            "unixsocket" => self.unixsocket = parse_path(arg),
+            "unixsocketperm" => {
+                let perm = u32::from_str_radix(arg, 8).map_err(|_| ConfigError::InvalidInteger)?;
+                self.unixsocketperm = check_range(perm.into(), 0, 0o777)? as u32;
+            }
+            "timeout" => self.timeout = parse_int(arg, 0, i32::MAX as u64)?,
+            "tcp-keepalive" => self.tcp_keepalive = parse_int(arg, 0, i32::MAX as u64)?,
+            "tls-port" => self.tls_port = parse_int(arg, 0, u16::MAX.into())? as u16,
+            "tls-cert-file" => self.tls_cert_file = parse_path(arg),
+            "tls-key-file" => self.tls_key_file = parse_path(arg),
+            "tls-ca-cert-file" => self.tls_ca_cert_file = parse_path(arg),
+            "tls-auth-clients" => self.tls_auth_clients = parse_enum(arg, "yes, no, optional")?,
+            "databases" => self.databases = parse_int(arg, 1, i32::MAX as u64)? as usize,
+            "maxclients" => self.maxclients = parse_int(arg, 1, i32::MAX as u64)? as usize,
+            "maxmemory" => self.maxmemory = parse_memory(arg)?,
+            "proto-max-bulk-len" => {
+                let len = parse_memory(arg)?;
+                self.proto_max_bulk_len = check_range(len, 1024 * 1024, i64::MAX as u64)? as usize
+            }
+            "maxmemory-policy" => self.maxmemory_policy = parse_enum(arg, MAXMEMORY_POLICIES)?,
+            "shutdown-timeout" => self.shutdown_timeout = parse_int(arg, 0, i32::MAX as u64)?,
+            "loglevel" => self.loglevel = parse_enum(arg, LOGLEVELS)?,
+            "hide-user-data-from-log" => self.hide_user_data_from_log = parse_bool(arg)?,
+            _ => return Err(ConfigError::BadDirective),
+        }
+        Ok(())
+    }
+
+    /// The value of a directive as CONFIG GET shows it.
+    pub fn get(&self, name: &str) -> Option<String> {
+        let value = match name.to_ascii_lowercase().as_str() {
+            "bind" => self.bind.join(" "),
+            "port" => self.port.to_string(),
+            "unixsocket" => display_path(&self.unixsocket),
+            "unixsocketperm" => format!("{:o}", self.unixsocketperm),
+            "timeout" => self.timeout.to_string(),
+            "tcp-keepalive" => self.tcp_keepalive.to_string(),
+            "tls-port" => self.tls_port.to_string(),
+            "tls-cert-file" => display_path(&self.tls_cert_file),
+            "tls-key-file" => display_path(&self.tls_key_file),
+            "tls-ca-cert-file" => display_path(&self.tls_ca_cert_file),
+            "tls-auth-clients" => self.tls_auth_clients.clone(),
+            "databases" => self.databases.to_string(),
+            "maxclients" => self.maxclients.to_string(),
+            "maxmemory" => self.maxmemory.to_string(),
+            "maxmemory-policy" => self.maxmemory_policy.clone(),
+            "proto-max-bulk-len" => self.proto_max_bulk_len.to_string(),
+            "shutdown-timeout" => self.shutdown_timeout.to_string(),
+            "loglevel" => self.loglevel.clone(),
+            "hide-user-data-from-log" => yes_no(self.hide_user_data_from_log).to_string(),
+            _ => return None,
+        };
+        Some(value)
+    }
+
+    /// Write the current values back to the config file. The directives
+    /// already in it are updated in place, comments and unknown lines are
+    /// kept, and the values changed from their default are appended.
+    pub fn rewrite(&self) -> std::io::Result<()> {
+        let path = self
+            .config_file
+            .as_ref()
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "no config file"))?;
+        let old = match fs::read_to_string(path) {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            ret => ret?,
+        };
+        // replace the file at once, never leave it half written
+        let mut tmp = path.clone().into_os_string();
+        tmp.push(".tmp");
+        fs::write(&tmp, self.rewrite_str(&old))?;
+        fs::rename(&tmp, path)
+    }
+
+    fn rewrite_str(&self, old: &str) -> String {
+        let mut lines = Vec::new();
+        let mut written = Vec::new();
+        for line in old.lines() {
+            let name = match split_args(line.trim()) {
+                Ok(args) if !line.trim_start().starts_with('#') => args
+                    .into_iter()
+                    .next()
+                    .map(|name| name.to_ascii_lowercase()),
+                _ => None,
+            };
+            match name {
+                Some(name) if Self::DIRECTIVES.contains(&name.as_str()) => {
+                    // repeated directives are merged into the first one
+                    if !written.contains(&name) {
+                        lines.push(self.directive_line(&name));
+                        written.push(name);
+                    }
+                }
+                _ => lines.push(line.to_string()),
+            }
+        }
+
+        let defaults = Config::default();
+        for name in Self::DIRECTIVES {
+            if written.iter().any(|w| w == name) || self.get(name) == defaults.get(name) {
+                continue;
+            }
+            if !lines.iter().any(|line| line == REWRITE_SIGNATURE) {
+                lines.push(REWRITE_SIGNATURE.to_string());
+            }
+            lines.push(self.directive_line(name));
+        }
+        lines.push(String::new());
+        lines.join("\n")
+    }
+
+    fn directive_line(&self, name: &str) -> String {
+        match name {
+            "bind" => format!("bind {}", self.bind.join(" ")),
+            _ => format!("{} {}", name, quote(&self.get(name).unwrap_or_default())),
+        }
+    }
+
+    /// Addresses to listen on for TCP connections, none if port is 0.
+    pub fn listen_addrs(&self) -> Vec<SocketAddr> {
+        self.bind_addrs(self.port)
+    }
+
+    /// Addresses to listen on for TLS connections, none if tls-port is 0.
+    pub fn tls_listen_addrs(&self) -> Vec<SocketAddr> {
+        self.bind_addrs(self.tls_port)
+    }
+
+    fn bind_addrs(&self, port: u16) -> Vec<SocketAddr> {
+        if port == 0 {
+            return vec![];
+        }
+        self.bind
+            .iter()
+            .filter_map(|addr| parse_bind(addr).ok())
+            .map(|ip| SocketAddr::new(ip, port))
+            .collect()
+    }
+
+    /// The tracing filter for `loglevel`, used when RUST_LOG isn't set.
+    pub fn log_filter(&self) -> &'static str {
+        match self.loglevel.as_str() {
+            "debug" => "debug",
+            "verbose" => "info",
+            "warning" => "warn",
+            "nothing" => "off",
+            _ => "info",
+        }
+    }
+}
+
+fn yes_no(v: bool) -> &'static str {
+    if v {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
+// an empty path unsets it
+fn parse_path(arg: &str) -> Option<PathBuf> {
+    (!arg.is_empty()).then(|| arg.into())
+}
+
+fn display_path(path: &Option<PathBuf>) -> String {
+    path.as_ref()
+        .map(|path| path.display().to_string())
+        .unwrap_or_default()
+}
+
+fn parse_bool(arg: &str) -> Result<bool, ConfigError> {
+    match arg.to_ascii_lowercase().as_str() {
+        "yes" => Ok(true),
+        "no" => Ok(false),
+        _ => Err(ConfigError::InvalidBool),
+    }
+}
+
+fn parse_enum(arg: &str, values: &'static str) -> Result<String, ConfigError> {
+    let arg = arg.to_ascii_lowercase();
+    if !values.split(", ").any(|v| v == arg) {
+        return Err(ConfigError::InvalidEnum(values));
+    }
+    Ok(arg)
+}
+
+fn parse_int(arg: &str, min: u64, max: u64) -> Result<u64, ConfigError> {
+    let v: i128 = arg.parse().map_err(|_| ConfigError::InvalidInteger)?;
+    let v = u64::try_from(v).map_err(|_| ConfigError::OutOfRange(min, max))?;
+    check_range(v, min, max)
+}
+
+fn check_range(v: u64, min: u64, max: u64) -> Result<u64, ConfigError> {
+    if v < min || v > max {
+        return Err(ConfigError::OutOfRange(min, max));
+    }
+    Ok(v)
+}
+
+/// A number of bytes with an optional unit: k, m and g are powers of 1000,
+/// kb, mb and gb powers of 1024.
+pub fn parse_memory(arg: &str) -> Result<u64, ConfigError> {
+    let arg = arg.to_ascii_lowercase();
+    let units: [(&str, u64); 6] = [
+        ("kb", 1 << 10),
+        ("mb", 1 << 20),
+        ("gb", 1 << 30),
+        ("k", 1000),
+        ("m", 1000 * 1000),
+        ("g", 1000 * 1000 * 1000),
+    ];
+    let (digits, unit) = units
+        .iter()
+        .find_map(|(suffix, unit)| arg.strip_suffix(suffix).map(|d| (d, *unit)))
+        .unwrap_or((&arg, 1));
+    if digits.is_empty() || !digits.bytes().all(|c| c.is_ascii_digit()) {
+        return Err(ConfigError::InvalidMemory);
+    }
+    digits
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(unit))
+        .ok_or(ConfigError::InvalidMemory)
+}
+
+// `*` and `::*` are the wildcard addresses, a leading `-` marks an optional
+// address in redis and is accepted as is
+fn parse_bind(addr: &str) -> Result<IpAddr, ConfigError> {
+    match addr.strip_prefix('-').unwrap_or(addr) {
+        "*" => Ok(Ipv4Addr::UNSPECIFIED.into()),
+        "::*" => Ok(Ipv6Addr::UNSPECIFIED.into()),
+        ip => ip
+            .parse()
+            .map_err(|_| ConfigError::InvalidBindAddress(addr.to_string())),
+    }
+}
+
+/// Split a config line into arguments like redis does: separated by spaces,
+/// "double quoted" with C escapes or 'single quoted'.
+fn split_args(line: &str) -> Result<Vec<String>, ConfigError> {
+    let mut args = Vec::new();
+    let mut chars = line.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let Some(&first) = chars.peek() else {
+            return Ok(args);
+        };
+
+        let mut arg = String::new();
+        if first == '"' || first == '\'' {
+            chars.next();
+            loop {
+                match chars.next() {
+                    None => return Err(ConfigError::UnbalancedQuotes),
+                    Some(c) if c == first => break,
+                    Some('\\') if first == '"' => arg.push(unescape(&mut chars)?),
+                    Some('\\') if chars.peek() == Some(&'\'') => {
+                        chars.next();
+                        arg.push('\'');
+                    }
+                    Some(c) => arg.push(c),
+                }
+            }
+            // the closing quote must end the argument
+            if chars.peek().is_some_and(|c| !c.is_whitespace()) {
+                return Err(ConfigError::UnbalancedQuotes);
+            }
+        } else {
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                arg.push(c);
+            }
+        }
+        args.push(arg);
+    }
+}
+
+// the reverse of `split_args` for a single argument
+fn quote(arg: &str) -> String {
+    let plain = |c: char| c.is_ascii_graphic() && !matches!(c, '"' | '\'' | '\\');
+    if !arg.is_empty() && arg.chars().all(plain) {
+        return arg.to_string();
+    }
+    let mut quoted = String::from("\"");
+    for c in arg.chars() {
+        match c {
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_ascii_control() => quoted.push_str(&format!("\\x{:02x}", c as u8)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn unescape(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<char, ConfigError> {
+    let c = match chars.next().ok_or(ConfigError::UnbalancedQuotes)? {
+        'n' => '\n',
+        'r' => '\r',
+        't' => '\t',
+        'b' => '\u{8}',
+        'a' => '\u{7}',
+        'x' => {
+            let hex: String = chars.clone().take(2).collect();
+            match u8::from_str_radix(&hex, 16) {
+                Ok(b) if hex.len() == 2 => {
+                    chars.nth(1);
+                    b as char
+                }
+                _ => 'x',
+            }
+        }
+        c => c,
+    };
+    Ok(c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_load_str() -> anyhow::Result<()> {
+        let mut config = Config::default();
+        config.load_str(
+            "# comment\n\
+             \n\
+             bind 127.0.0.1 -::1\n\
+             PORT 7000\n\
+             maxmemory 2mb\n\
+             timeout 60\n\
+             maxclients 128\n\
+             loglevel \"warning\"\n\
+             hide-user-data-from-log yes\n",
+        )?;
+        assert_eq!(config.bind, ["127.0.0.1", "-::1"]);
+        assert_eq!(config.port, 7000);
+        assert_eq!(config.maxmemory, 2 * 1024 * 1024);
+        assert_eq!(config.timeout, 60);
+        assert_eq!(config.maxclients, 128);
+        assert_eq!(config.loglevel, "warning");
+        assert!(config.hide_user_data_from_log);
+        assert_eq!(
+            config.listen_addrs(),
+            [
+                "127.0.0.1:7000".parse::<SocketAddr>()?,
+                "[::1]:7000".parse()?
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_args() -> anyhow::Result<()> {
+        let config = Config::from_args(args(
+            "--port 7001 --bind * ::* --databases 2 --unixsocket /tmp/redis.sock --unixsocketperm 770",
+        ))?;
+        assert_eq!(config.port, 7001);
+        assert_eq!(config.unixsocket, Some("/tmp/redis.sock".into()));
+        assert_eq!(config.unixsocketperm, 0o770);
+        assert_eq!(config.get("unixsocketperm"), Some("770".to_string()));
+        assert_eq!(config.bind, ["*", "::*"]);
+        assert_eq!(config.databases, 2);
+
+        let err = Config::from_args(args("--port 70000")).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "'--port 70000': argument must be between 0 and 65535 inclusive"
+        );
+        assert!(Config::from_args(args("--nope 1")).is_err());
+        assert!(Config::from_args(args("--port")).is_err());
+        assert!(Config::from_args(args("--port 1 2")).is_err());
+        assert!(Config::from_args(args("--unixsocketperm 778")).is_err());
+        assert!(Config::from_args(args("--unixsocketperm 1777")).is_err());
+        assert!(Config::from_args(args("--maxclients 0")).is_err());
+        assert!(Config::from_args(args("/no/such/redis.conf")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_errors() {
+        let mut config = Config::default();
+        let err = config.load_str("port 1\nmaxmemory lots\n").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            ":2: 'maxmemory lots': argument must be a memory value"
+        );
+        assert!(matches!(
+            config.set("bind", &args("localhost")),
+            Err(ConfigError::InvalidBindAddress(_))
+        ));
+        assert!(matches!(
+            config.set("loglevel", &args("loud")),
+            Err(ConfigError::InvalidEnum(_))
+        ));
+        assert!(matches!(
+            config.set("hide-user-data-from-log", &args("1")),
+            Err(ConfigError::InvalidBool)
+        ));
+        assert!(matches!(
+            config.load_str("bind \"127.0.0.1"),
+            Err(ConfigError::File { .. })
+        ));
+        // nothing was applied past the error
+        assert_eq!(config.port, 1);
+        assert_eq!(config.get("bind"), Some("0.0.0.0".to_string()));
+    }
+
+    #[test]
+    fn test_rewrite() -> anyhow::Result<()> {
+        let old = "# my server\n\
+                   port 7000\n\
+                   unknown-directive kept\n\
+                   # maxmemory 1mb\n\
+                   loglevel debug\n\
+                   loglevel warning\n";
+        let mut config = Config::default();
+        config.set("port", &args("7000"))?;
+        config.set("loglevel", &args("verbose"))?;
+        config.set("maxmemory", &args("1mb"))?;
+        assert_eq!(
+            config.rewrite_str(old),
+            "# my server\n\
+             port 7000\n\
+             unknown-directive kept\n\
+             # maxmemory 1mb\n\
+             loglevel verbose\n\
+             # Generated by CONFIG REWRITE\n\
+             maxmemory 1048576\n"
+        );
+
+        let dir = std::env::temp_dir().join(format!("simple-redis-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let path = dir.join("redis.conf");
+        fs::write(&path, "# empty\n")?;
+        config.config_file = Some(path.clone());
+        config.rewrite()?;
+        let mut reloaded = Config::default();
+        reloaded.load_file(&path)?;
+        reloaded.config_file = Some(path);
+        assert_eq!(reloaded, config);
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_quote() {
+        for arg in [
+            "plain",
+            "",
+            "two words",
+            "q\"uo'te",
+            "back\\slash",
+            "nl\n\x01",
+        ] {
+            assert_eq!(split_args(&quote(arg)).ok(), Some(vec![arg.to_string()]));
+        }
+    }
+
+    #[test]
+    fn test_parse_memory() {
+        assert_eq!(parse_memory("100").ok(), Some(100));
+        assert_eq!(parse_memory("1k").ok(), Some(1000));
+        assert_eq!(parse_memory("1KB").ok(), Some(1024));
+        assert_eq!(parse_memory("3g").ok(), Some(3_000_000_000));
+        assert_eq!(parse_memory("1gb").ok(), Some(1 << 30));
+        assert!(parse_memory("-1").is_err());
+        assert!(parse_memory("gb").is_err());
+        assert!(parse_memory("99999999999gb").is_err());
+    }
+
+    #[test]
+    fn test_split_args() {
+        assert_eq!(
+            split_args(r#"  set "a b\x41\n" 'it\'s'  c"#).ok(),
+            Some(vec![
+                "set".to_string(),
+                "a bA\n".to_string(),
+                "it's".to_string(),
+                "c".to_string()
+            ])
+        );
+        assert!(split_args(r#""unclosed"#).is_err());
+        assert!(split_args(r#""a"b"#).is_err());
+    }
+}
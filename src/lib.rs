@@ -1,14 +1,25 @@
 pub mod backend;
 pub mod cmd;
+pub mod config;
 pub mod network;
 pub mod resp;
+pub mod tls;
 pub use resp::array::*;
+pub use resp::attribute::*;
+pub use resp::big_number::*;
+pub use resp::blob_error::*;
 pub use resp::bulk_string::*;
+pub use resp::decoder::*;
 pub use resp::frame::*;
+pub use resp::limits::*;
 pub use resp::map::*;
 pub use resp::null::*;
+pub use resp::push::*;
 pub use resp::set::*;
 pub use resp::simple_error::*;
 pub use resp::simple_string::*;
+pub use resp::verbatim_string::*;
 
 pub use resp::*;
+#[cfg(feature = "serde")]
+pub use resp::{de::from_frame, ser::to_frame};
@@ -1,22 +1,291 @@
-use anyhow::Result;
-use simple_redis::{backend::Backend, network};
-use tokio::net::TcpListener;
+use anyhow::{bail, Result};
+use simple_redis::{
+    backend::{Backend, ClientError, ClientSlot, Shutdown},
+    config::Config,
+    network, tls,
+};
+use std::{
+    fs,
+    future::Future,
+    io,
+    os::unix::fs::PermissionsExt,
+    path::Path,
+    time::{Duration, Instant},
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::{TcpListener, TcpStream, UnixListener},
+    signal::unix::{signal, SignalKind},
+    sync::watch,
+    task::JoinSet,
+};
+use tokio_rustls::TlsAcceptor;
+use tokio_util::task::TaskTracker;
 use tracing::{info, warn};
+use tracing_subscriber::{fmt, prelude::*, reload, EnvFilter};
+
+// what a TLS client may take to complete its handshake
+const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
 #[tokio::main]
 async fn main() -> Result<()> {
-    tracing_subscriber::fmt().init();
-    let addr = "0.0.0.0:6378";
-    info!("Simple-Redis-Server is listening on {}", addr);
-    let listener = TcpListener::bind(addr).await?;
-    let backend = Backend::new();
+    let config = match Config::from_args(std::env::args().skip(1)) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("*** FATAL CONFIG ERROR ***\n{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // RUST_LOG=simple_redis=debug traces every command, it takes precedence
+    // over loglevel
+    let env_filter = EnvFilter::try_from_default_env().ok();
+    let has_env_filter = env_filter.is_some();
+    let (filter, filter_handle) =
+        reload::Layer::new(env_filter.unwrap_or_else(|| EnvFilter::new(config.log_filter())));
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer())
+        .init();
+
+    let backend = Backend::with_config(config.clone());
+    if !has_env_filter {
+        // follow CONFIG SET loglevel
+        let mut config = backend.subscribe_config();
+        tokio::spawn(async move {
+            while config.changed().await.is_ok() {
+                let filter = EnvFilter::new(config.borrow_and_update().log_filter());
+                if let Err(e) = filter_handle.reload(filter) {
+                    warn!("Failed to change the log level: {}", e);
+                }
+            }
+        });
+    }
+    let mut listeners = JoinSet::new();
+    let clients = TaskTracker::new();
+    for addr in config.listen_addrs() {
+        let listener = TcpListener::bind(addr).await?;
+        info!("Simple-Redis-Server is listening on {}", addr);
+        listeners.spawn(serve_tcp(listener, backend.clone(), clients.clone()));
+    }
+    let tls_addrs = config.tls_listen_addrs();
+    if !tls_addrs.is_empty() {
+        let acceptor = watch_tls_acceptor(&backend)?;
+        for addr in tls_addrs {
+            let listener = TcpListener::bind(addr).await?;
+            info!("Simple-Redis-Server is listening for TLS on {}", addr);
+            listeners.spawn(serve_tls(
+                listener,
+                acceptor.clone(),
+                backend.clone(),
+                clients.clone(),
+            ));
+        }
+    }
+    if let Some(path) = &config.unixsocket {
+        let listener = bind_unix(path, config.unixsocketperm)?;
+        info!("Simple-Redis-Server is listening on {}", path.display());
+        listeners.spawn(serve_unix(listener, backend.clone(), clients.clone()));
+    }
+    if listeners.is_empty() {
+        bail!("Configured to not listen anywhere, exiting");
+    }
+    let ret = run(&backend, &mut listeners, &clients).await;
+    if let Some(path) = &config.unixsocket {
+        let _ = fs::remove_file(path);
+    }
+    info!("Simple-Redis-Server is now ready to exit, bye bye...");
+    ret
+}
+
+// serve until SHUTDOWN or SIGINT/SIGTERM, then give the clients
+// shutdown-timeout seconds to finish their commands
+async fn run(
+    backend: &Backend,
+    listeners: &mut JoinSet<Result<()>>,
+    clients: &TaskTracker,
+) -> Result<()> {
+    let mut sigterm = signal(SignalKind::terminate())?;
+    let mut sigint = signal(SignalKind::interrupt())?;
+    let mut shutdown = backend.subscribe_shutdown();
+    loop {
+        tokio::select! {
+            Some(ret) = listeners.join_next() => ret??,
+            _ = sigterm.recv() => request_shutdown(backend, "SIGTERM"),
+            _ = sigint.recv() => request_shutdown(backend, "SIGINT"),
+            ret = shutdown.wait_for(Option::is_some) => {
+                ret?;
+            }
+        }
+        let Some(options) = *shutdown.borrow() else {
+            continue;
+        };
+        let timeout = match options.now {
+            true => Duration::ZERO,
+            false => Duration::from_secs(backend.config().shutdown_timeout),
+        };
+        info!("Shutting down, waiting for {} clients", clients.len());
+        clients.close();
+        tokio::select! {
+            ret = tokio::time::timeout(timeout, clients.wait()) => {
+                if ret.is_err() {
+                    warn!("{} clients still busy, closing them", clients.len());
+                }
+                return Ok(());
+            }
+            _ = sigterm.recv() => {}
+            _ = sigint.recv() => {}
+            ret = shutdown.wait_for(Option::is_none) => {
+                ret?;
+                warn!("Shutdown aborted");
+                clients.reopen();
+                continue;
+            }
+        }
+        warn!("You insist... exiting now.");
+        return Ok(());
+    }
+}
+
+fn request_shutdown(backend: &Backend, signal: &str) {
+    warn!("Received {} scheduling shutdown...", signal);
+    backend.shutdown(Shutdown::default());
+}
+
+// the next connection of a listener, none is accepted while the server shuts
+// down
+async fn accept<T, F>(backend: &Backend, mut accept: impl FnMut() -> F) -> Result<T>
+where
+    F: Future<Output = io::Result<T>>,
+{
+    let mut shutdown = backend.subscribe_shutdown();
+    loop {
+        shutdown.wait_for(Option::is_none).await?;
+        tokio::select! {
+            biased;
+            ret = shutdown.wait_for(Option::is_some) => {
+                ret?;
+            }
+            ret = accept() => return Ok(ret?),
+        }
+    }
+}
+
+async fn serve_tcp(listener: TcpListener, backend: Backend, clients: TaskTracker) -> Result<()> {
     loop {
-        let (socket, raddr) = listener.accept().await?;
+        let (socket, raddr) = accept(&backend, || listener.accept()).await?;
         info!("Accept connection from {}", raddr);
-        let backend_cloned = backend.clone();
-        tokio::spawn(async move {
-            if let Err(e) = network::stream_handler(socket, backend_cloned).await {
-                warn!("Handle error for {}: {:?}", raddr, e);
+        set_keepalive(&socket, &raddr.to_string(), &backend);
+        spawn_client(socket, raddr.to_string(), &backend, &clients);
+    }
+}
+
+async fn serve_tls(
+    listener: TcpListener,
+    acceptor: watch::Receiver<TlsAcceptor>,
+    backend: Backend,
+    clients: TaskTracker,
+) -> Result<()> {
+    loop {
+        let (socket, raddr) = accept(&backend, || listener.accept()).await?;
+        let accepted = Instant::now();
+        set_keepalive(&socket, &raddr.to_string(), &backend);
+        let acceptor = acceptor.borrow().clone();
+        let backend = backend.connect();
+        // handshakes in progress count towards maxclients
+        let slot = backend.register_client();
+        // a slow handshake must not hold back the other clients
+        // the idle timeout runs from the accept, through the handshake
+        let timeout = match backend.config().timeout {
+            0 => TLS_HANDSHAKE_TIMEOUT,
+            idle => TLS_HANDSHAKE_TIMEOUT.min(Duration::from_secs(idle)),
+        };
+        clients.spawn(async move {
+            let handshake = acceptor.accept(socket);
+            match tokio::time::timeout(timeout, handshake).await {
+                Ok(Ok(stream)) => {
+                    info!("Accept TLS connection from {}", raddr);
+                    handle_client(stream, raddr.to_string(), backend, slot, accepted).await;
+                }
+                Ok(Err(e)) => warn!("TLS handshake failed for {}: {}", raddr, e),
+                Err(_) => warn!("TLS handshake timed out for {}", raddr),
             }
         });
     }
 }
+
+// the acceptor for the current TLS configuration, rebuilt on CONFIG SET so
+// certificates are reloaded without a restart
+fn watch_tls_acceptor(backend: &Backend) -> Result<watch::Receiver<TlsAcceptor>> {
+    let mut config = backend.subscribe_config();
+    let acceptor = tls::acceptor(&config.borrow_and_update())?;
+    let (tx, rx) = watch::channel(acceptor);
+    tokio::spawn(async move {
+        while config.changed().await.is_ok() {
+            let acceptor = tls::acceptor(&config.borrow_and_update());
+            match acceptor {
+                Ok(acceptor) => {
+                    tx.send_replace(acceptor);
+                }
+                Err(e) => warn!("Failed to reload the TLS configuration: {}", e),
+            }
+        }
+    });
+    Ok(rx)
+}
+
+async fn serve_unix(listener: UnixListener, backend: Backend, clients: TaskTracker) -> Result<()> {
+    let path = listener.local_addr()?;
+    let path = path
+        .as_pathname()
+        .map(|path| path.display().to_string())
+        .unwrap_or_default();
+    loop {
+        let (socket, _) = accept(&backend, || listener.accept()).await?;
+        info!("Accept connection to {}", path);
+        spawn_client(socket, path.clone(), &backend, &clients);
+    }
+}
+
+// CONFIG SET tcp-keepalive applies to the next connections
+fn set_keepalive(socket: &TcpStream, peer: &str, backend: &Backend) {
+    if let Err(e) = network::set_tcp_keepalive(socket, backend.config().tcp_keepalive) {
+        warn!("Failed to set TCP keepalive for {}: {}", peer, e);
+    }
+}
+
+fn spawn_client<S>(stream: S, peer: String, backend: &Backend, clients: &TaskTracker)
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let backend = backend.connect();
+    let slot = backend.register_client();
+    clients.spawn(handle_client(stream, peer, backend, slot, Instant::now()));
+}
+
+async fn handle_client<S>(
+    stream: S,
+    peer: String,
+    backend: Backend,
+    slot: Result<ClientSlot, ClientError>,
+    accepted: Instant,
+) where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    if let Err(e) = network::client_handler(stream, backend, slot, accepted).await {
+        warn!("Handle error for {}: {:#}", peer, e);
+    }
+}
+
+// a socket file left by a previous run would make bind fail
+fn bind_unix(path: &Path, perm: u32) -> Result<UnixListener> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+        _ => {}
+    }
+    let listener = UnixListener::bind(path)?;
+    if perm != 0 {
+        fs::set_permissions(path, fs::Permissions::from_mode(perm))?;
+    }
+    Ok(listener)
+}
@@ -1,26 +1,38 @@
 use crate::{
-    backend::Backend,
-    cmd::{Command, CommandExecutor},
-    RespDecode, RespEncode, RespError, RespFrame,
+    backend::{Backend, ClientError, ClientSlot, Protocol},
+    cmd::{execute_blocking, Command, CommandExecutor},
+    RespArray, RespEncode, RespError, RespFrame, RespFrameDecoder, RespLimits, SimpleError,
 };
 use anyhow::Result;
-use futures::SinkExt;
-use tokio::net::TcpStream;
+use bytes::BytesMut;
+use futures::{FutureExt, SinkExt};
+use socket2::{SockRef, TcpKeepalive};
+use std::{
+    io,
+    sync::atomic::Ordering,
+    time::{Duration, Instant},
+};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::TcpStream,
+};
 use tokio_stream::StreamExt;
 use tokio_util::codec::{Decoder, Encoder, Framed};
-use tracing::info;
+use tracing::{debug, debug_span, field, trace, Instrument, Span};
 
-#[derive(Debug)]
-struct RespFrameCodec;
+// what a blocked client may send before it is no longer read
+const MAX_READ_WHILE_BLOCKED: usize = 1024 * 1024;
+
+#[derive(Debug, Default)]
+struct RespFrameCodec {
+    decoder: RespFrameDecoder,
+}
 
 impl Encoder<RespFrame> for RespFrameCodec {
     type Error = anyhow::Error;
 
-    fn encode(&mut self, item: RespFrame, dst: &mut bytes::BytesMut) -> Result<()> {
-        info!("Send frame: {:?}", item);
-        let encoded = item.encode();
-        info!("Send frame encoded: {:?}", encoded);
-        dst.extend_from_slice(&encoded);
+    fn encode(&mut self, item: RespFrame, dst: &mut BytesMut) -> Result<()> {
+        item.encode_to(dst);
         Ok(())
     }
 }
@@ -30,29 +42,705 @@ impl Decoder for RespFrameCodec {
 
     type Error = anyhow::Error;
 
-    fn decode(&mut self, src: &mut bytes::BytesMut) -> Result<Option<Self::Item>> {
-        // let frame = RespFrame::decode(src)?;
-        match RespFrame::decode(src) {
-            Ok(frame) => Ok(Some(frame)),
-            Err(RespError::NotComplete) => Ok(None),
-            Err(e) => Err(e.into()),
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>> {
+        // requests not starting as an array are inline commands (telnet, nc...)
+        if self.decoder.is_idle() && src.first().is_some_and(|c| *c != b'*') {
+            let max_len = self.decoder.limits().max_inline_len;
+            return match RespArray::decode_inline_with_limit(src, max_len) {
+                Ok(frame) => Ok(Some(frame.into())),
+                Err(RespError::NotComplete) => Ok(None),
+                Err(e) => Err(e.into()),
+            };
         }
+
+        Ok(self.decoder.decode(src)?)
     }
 }
 
-pub async fn stream_handler(stream: TcpStream, backend: Backend) -> Result<()> {
-    let mut framed = Framed::new(stream, RespFrameCodec);
+/// Serve a client connection, over TCP, a Unix socket or any other stream.
+pub async fn stream_handler<S>(stream: S, backend: Backend) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let slot = backend.register_client();
+    client_handler(stream, backend, slot, Instant::now()).await
+}
+
+/// Serve a client connection counted in the connected clients before it was
+/// set up, as `slot`. A refused client is told why and closed. The idle
+/// timeout runs from `accepted`, so the setup counts as idle time.
+pub async fn client_handler<S>(
+    stream: S,
+    backend: Backend,
+    slot: Result<ClientSlot, ClientError>,
+    accepted: Instant,
+) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut framed = Framed::new(stream, RespFrameCodec::default());
+    let ret = match slot {
+        Ok(_slot) => serve(&mut framed, &backend, accepted).await,
+        Err(e) => {
+            backend
+                .stats()
+                .rejected_connections
+                .fetch_add(1, Ordering::Relaxed);
+            debug!(client = backend.client_id(), "{}", e);
+            framed.feed(SimpleError::new(e.to_string()).into()).await
+        }
+    };
+    // the replies queued before an error are still delivered
+    ret.and(framed.flush().await)?;
+    // a clean close tells TLS clients no reply was cut, the client may be
+    // gone already
+    let _ = framed.get_mut().shutdown().await;
+    Ok(())
+}
+
+async fn serve<S>(
+    framed: &mut Framed<S, RespFrameCodec>,
+    backend: &Backend,
+    accepted: Instant,
+) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut config = backend.subscribe_config();
+    config.mark_changed();
+    let mut shutdown = backend.subscribe_shutdown();
+    let mut idle_timeout = None;
+    // the idle timeout runs from the last request, whatever changes meanwhile
+    let mut last_activity = accepted;
     loop {
-        match framed.next().await {
-            Some(Ok(frame)) => {
-                info!("Received frame: {:?}", frame);
-                let cmd: Command = Command::try_from(frame)?;
-                info!("Executing cmd : {:?}", cmd);
-                let frame = cmd.execute(&backend);
-                framed.send(frame).await?;
+        // CONFIG SET applies to the connections already open
+        if config.has_changed()? {
+            let config = config.borrow_and_update();
+            let limits = RespLimits {
+                max_bulk_len: config.proto_max_bulk_len,
+                ..RespLimits::DEFAULT
+            };
+            framed.codec_mut().decoder.set_limits(limits);
+            idle_timeout = (config.timeout > 0).then(|| Duration::from_secs(config.timeout));
+        }
+        let deadline = idle_timeout.and_then(|t| last_activity.checked_add(t));
+        // an idle client is closed as soon as the server shuts down, the requests
+        // it already sent are dropped
+        let frame = tokio::select! {
+            biased;
+            _ = shutdown.wait_for(Option::is_some) => None,
+            // a new timeout applies to the clients already idle too
+            ret = config.changed() => {
+                ret?;
+                config.mark_changed();
+                continue;
             }
-            Some(Err(e)) => return Err(e),
-            None => return Ok(()),
+            frame = next_request(framed, deadline) => frame,
+        };
+        let Some(frame) = frame else {
+            break;
+        };
+        let frame = protocol_checked(frame, framed).await?;
+        request_handler(frame, framed, backend).await?;
+        // answer every request a pipelining client has already sent, then
+        // write all the replies at once
+        while !backend.is_shutting_down() {
+            let Some(Some(frame)) = framed.next().now_or_never() else {
+                break;
+            };
+            let frame = protocol_checked(frame, framed).await?;
+            request_handler(frame, framed, backend).await?;
         }
+        framed.flush().await?;
+        last_activity = Instant::now();
+    }
+    Ok(())
+}
+
+// a request that can't be decoded is answered with a protocol error, after the
+// replies queued before it, then the error closes the connection as the stream
+// can't be resynchronized
+async fn protocol_checked<S>(
+    frame: Result<RespFrame>,
+    framed: &mut Framed<S, RespFrameCodec>,
+) -> Result<RespFrame>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let e = match frame {
+        Ok(frame) => return Ok(frame),
+        Err(e) => e,
+    };
+    if let Some(reason) = e.downcast_ref::<RespError>() {
+        let msg = format!("ERR Protocol error: {}", reason);
+        framed.send(SimpleError::new(msg).into()).await?;
+    }
+    Err(e)
+}
+
+// the next request of the client, `None` once it is gone or still idle at
+// `deadline`
+async fn next_request<S>(
+    framed: &mut Framed<S, RespFrameCodec>,
+    deadline: Option<Instant>,
+) -> Option<Result<RespFrame>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    match deadline {
+        Some(deadline) => match tokio::time::timeout_at(deadline.into(), framed.next()).await {
+            Ok(frame) => frame,
+            Err(_) => {
+                debug!("Closing idle client");
+                None
+            }
+        },
+        None => framed.next().await,
+    }
+}
+
+// execute a request and queue its reply, the caller flushes
+async fn request_handler<S>(
+    frame: RespFrame,
+    framed: &mut Framed<S, RespFrameCodec>,
+    backend: &Backend,
+) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let span = debug_span!(
+        "command",
+        client = backend.client_id(),
+        name = %command_name(&frame),
+        keys = field::Empty,
+    );
+    execute(frame, framed, backend).instrument(span).await
+}
+
+async fn execute<S>(
+    frame: RespFrame,
+    framed: &mut Framed<S, RespFrameCodec>,
+    backend: &Backend,
+) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let start = Instant::now();
+    // arguments and replies are user data, they are only ever traced
+    let hide = backend.hide_user_data_from_log();
+    if !hide {
+        trace!(?frame, "request");
+    }
+    let cmd = match Command::try_from(frame) {
+        Ok(cmd) => cmd,
+        Err(e) => {
+            // the message may quote the arguments
+            if hide {
+                debug!("invalid request");
+            } else {
+                debug!("invalid request: {}", e);
+            }
+            return framed.feed(e.into()).await;
+        }
+    };
+    Span::current().record("keys", cmd.key_count());
+    let backend = backend.current();
+    let is_shutdown = cmd.is_shutdown();
+    let frame = match cmd.as_blocking() {
+        Some(cmd) => {
+            // don't hold back the replies of earlier requests while waiting
+            framed.flush().await?;
+            // a client gone while blocked must not take the data it waited for
+            let frame = tokio::select! {
+                biased;
+                ret = client_closed(framed) => {
+                    ret?;
+                    debug!("client closed while blocked");
+                    return Ok(());
+                }
+                frame = execute_blocking(cmd, &backend) => frame,
+            };
+            match frame {
+                Some(frame) => frame,
+                // the server shuts down, the client gets no reply
+                None => return Ok(()),
+            }
+        }
+        None => cmd.execute(&backend),
+    };
+    // the protocol may just have been switched by HELLO
+    let frame = match backend.protocol() {
+        Protocol::Resp2 => frame.into_resp2(),
+        Protocol::Resp3 => frame,
+    };
+    if !hide {
+        trace!(?frame, "reply");
+    }
+    backend
+        .stats()
+        .total_commands_processed
+        .fetch_add(1, Ordering::Relaxed);
+    debug!(latency_us = start.elapsed().as_micros() as u64, "executed");
+    // like redis, a successful SHUTDOWN closes the connection without a reply
+    if is_shutdown && !matches!(frame, RespFrame::Error(_)) {
+        return Ok(());
+    }
+    framed.feed(frame).await
+}
+
+// wait for a blocked client to close the connection, the requests it sends
+// meanwhile are kept for after the blocking command, up to a limit
+async fn client_closed<S>(framed: &mut Framed<S, RespFrameCodec>) -> io::Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut buf = BytesMut::with_capacity(4096);
+    while framed.read_buffer().len() < MAX_READ_WHILE_BLOCKED {
+        if framed.get_mut().read_buf(&mut buf).await? == 0 {
+            return Ok(());
+        }
+        // the frame being served was just decoded from this buffer, so the
+        // codec looks into it before reading the stream again
+        framed.read_buffer_mut().extend_from_slice(&buf);
+        buf.clear();
+    }
+    std::future::pending().await
+}
+
+/// Probe an idle TCP peer after `interval` seconds, then every third of it
+/// like redis, so dead peers are detected. 0 disables the probes.
+pub fn set_tcp_keepalive(stream: &TcpStream, interval: u64) -> io::Result<()> {
+    let socket = SockRef::from(stream);
+    if interval == 0 {
+        return socket.set_keepalive(false);
+    }
+    let keepalive = TcpKeepalive::new()
+        .with_time(Duration::from_secs(interval))
+        .with_interval(Duration::from_secs((interval / 3).max(1)))
+        .with_retries(3);
+    socket.set_tcp_keepalive(&keepalive)
+}
+
+// lowercase name of a request, for the logs
+fn command_name(frame: &RespFrame) -> String {
+    match frame {
+        RespFrame::Array(RespArray(Some(args))) => match args.first() {
+            Some(RespFrame::BulkString(name)) => {
+                String::from_utf8_lossy(&name.to_ascii_lowercase()).into_owned()
+            }
+            _ => String::new(),
+        },
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{backend::ZAddOptions, config::Config, BulkString};
+    use tokio::net::{TcpListener, UnixStream};
+
+    #[test]
+    fn test_codec_byte_at_a_time() -> Result<()> {
+        let data = b"*2\r\n$4\r\necho\r\n$7\r\nhel\r\nlo\r\nPING\r\n*3\r\n$3\r\nset\r\n$1\r\nk\r\n$0\r\n\r\n";
+        let mut codec = RespFrameCodec::default();
+        let mut buf = BytesMut::new();
+        let mut frames = vec![];
+        for c in data {
+            buf.extend_from_slice(&[*c]);
+            if let Some(frame) = codec.decode(&mut buf)? {
+                frames.push(frame);
+            }
+        }
+
+        let command = |args: &[&str]| -> RespFrame {
+            RespArray::new(
+                args.iter()
+                    .map(|arg| BulkString::new(*arg).into())
+                    .collect::<Vec<_>>(),
+            )
+            .into()
+        };
+        assert_eq!(
+            frames,
+            [
+                command(&["echo", "hel\r\nlo"]),
+                command(&["PING"]),
+                command(&["set", "k", ""]),
+            ]
+        );
+        assert!(buf.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_codec_error() {
+        let mut codec = RespFrameCodec::default();
+        let mut buf = BytesMut::from(&b"*1\r\n$x\r\n"[..]);
+        assert!(codec.decode(&mut buf).is_err());
+    }
+
+    #[test]
+    fn test_codec_hostile_blobs() {
+        for data in [
+            &b"*1\r\n$18446744073709551615\r\n"[..],
+            b"*2\r\n$1\r\na\r\n=18446744073709551615\r\n",
+            b"*1\r\n!18446744073709551615\r\n",
+        ] {
+            let mut codec = RespFrameCodec::default();
+            let mut buf = BytesMut::from(data);
+            let err = codec.decode(&mut buf).unwrap_err();
+            assert_eq!(
+                err.downcast::<RespError>().ok(),
+                Some(RespError::InvalidFrameLength(
+                    "invalid bulk length".to_string()
+                )),
+                "{}",
+                data.escape_ascii()
+            );
+        }
+
+        // the data of a blob must be followed by CRLF
+        for data in [&b"*1\r\n$3\r\nabcXY"[..], b"*1\r\n=5\r\ntxt:aXY"] {
+            let mut codec = RespFrameCodec::default();
+            let mut buf = BytesMut::from(data);
+            let err = codec.decode(&mut buf).unwrap_err();
+            assert!(
+                matches!(err.downcast::<RespError>(), Ok(RespError::InvalidFrame(_))),
+                "{}",
+                data.escape_ascii()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pipelined_replies() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let server = tokio::spawn(async move {
+            let (socket, _) = listener.accept().await?;
+            stream_handler(socket, Backend::new().connect()).await
+        });
+
+        let mut client = TcpStream::connect(addr).await?;
+        let mut requests = Vec::new();
+        for i in 0..16 {
+            requests.extend_from_slice(format!("SET k{i} {i}\r\n").as_bytes());
+        }
+        requests.extend_from_slice(b"*2\r\n$3\r\nGET\r\n$3\r\nk15\r\n");
+        client.write_all(&requests).await?;
+        client.shutdown().await?;
+
+        let mut replies = Vec::new();
+        client.read_to_end(&mut replies).await?;
+        assert_eq!(
+            replies,
+            [b"+OK\r\n".repeat(16), b"$2\r\n15\r\n".to_vec()].concat()
+        );
+        server.await??;
+        Ok(())
+    }
+
+    #[test]
+    fn test_command_name_and_keys() -> Result<()> {
+        let frame: RespFrame = RespArray::new([
+            b"ZUNIONSTORE".into(),
+            b"dst".into(),
+            b"2".into(),
+            b"a".into(),
+            b"b".into(),
+        ])
+        .into();
+        assert_eq!(command_name(&frame), "zunionstore");
+        assert_eq!(Command::try_from(frame)?.key_count(), 3);
+        assert_eq!(command_name(&RespFrame::Integer(1)), "");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unix_stream() -> Result<()> {
+        let (server, mut client) = UnixStream::pair()?;
+        let server = tokio::spawn(stream_handler(server, Backend::new().connect()));
+        client.write_all(b"PING\r\nECHO hi\r\n").await?;
+        client.shutdown().await?;
+
+        let mut replies = Vec::new();
+        client.read_to_end(&mut replies).await?;
+        assert_eq!(replies, b"+PONG\r\n$2\r\nhi\r\n");
+        server.await??;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_protocol_error_reply() -> Result<()> {
+        let (server, mut client) = UnixStream::pair()?;
+        let server = tokio::spawn(stream_handler(server, Backend::new().connect()));
+        // the request before the bad one is answered, the ones after are not
+        client
+            .write_all(b"PING\r\n*1\r\n$3\r\nabcXY\r\nPING\r\n")
+            .await?;
+
+        let mut replies = Vec::new();
+        client.read_to_end(&mut replies).await?;
+        assert_eq!(
+            replies,
+            b"+PONG\r\n-ERR Protocol error: Invalid frame: blob not terminated by CRLF\r\n"
+        );
+        assert!(server.await?.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_closes_clients() -> Result<()> {
+        let backend = Backend::new();
+        let (idle, mut idle_client) = UnixStream::pair()?;
+        let idle = tokio::spawn(stream_handler(idle, backend.connect()));
+        let (server, mut client) = UnixStream::pair()?;
+        let server = tokio::spawn(stream_handler(server, backend.connect()));
+
+        // the requests after SHUTDOWN are not served
+        client
+            .write_all(b"SHUTDOWN SAVE\r\nPING\r\nSHUTDOWN NOSAVE\r\nPING\r\n")
+            .await?;
+        let mut replies = Vec::new();
+        client.read_to_end(&mut replies).await?;
+        assert_eq!(
+            replies,
+            b"-ERR SAVE is not supported, the server has no persistence\r\n+PONG\r\n"
+        );
+        server.await??;
+
+        let mut replies = Vec::new();
+        idle_client.read_to_end(&mut replies).await?;
+        assert!(replies.is_empty());
+        idle.await??;
+        assert!(backend.is_shutting_down());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_invalid_request_replies() -> Result<()> {
+        let (server, mut client) = UnixStream::pair()?;
+        let server = tokio::spawn(stream_handler(server, Backend::new().connect()));
+        client.write_all(b"ZADD z NX XX 1 a\r\nPING\r\n").await?;
+        client.shutdown().await?;
+
+        let mut replies = Vec::new();
+        client.read_to_end(&mut replies).await?;
+        assert_eq!(
+            replies,
+            b"-ERR XX and NX options at the same time are not compatible\r\n+PONG\r\n"
+        );
+        server.await??;
+        Ok(())
+    }
+
+    #[derive(Clone, Default)]
+    struct LogBuffer(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl io::Write for LogBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hide_user_data_from_log() -> Result<()> {
+        let logs = LogBuffer::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::TRACE)
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let backend = Backend::with_config(Config {
+            hide_user_data_from_log: true,
+            ..Config::default()
+        });
+        let (server, mut client) = UnixStream::pair()?;
+        let server = tokio::spawn(stream_handler(server, backend.connect()));
+        client
+            .write_all(b"SET k hunter2\r\nHELLO 3 hunter2\r\n*1\r\n@hunter2-secret\r\n")
+            .await?;
+        let mut replies = Vec::new();
+        client.read_to_end(&mut replies).await?;
+        // the client is answered with its own data
+        let replies = String::from_utf8_lossy(&replies);
+        assert!(replies.contains("'hunter2'"));
+        assert!(replies.ends_with(
+            "-ERR Protocol error: Invalid frame type: decode unknown frame type: '@'\r\n"
+        ));
+
+        // main logs the error that closed the connection
+        let err = format!("{:#}", server.await?.unwrap_err());
+        assert_eq!(err, "Invalid frame type: decode unknown frame type: '@'");
+        let logs = String::from_utf8(logs.0.lock().unwrap().clone())?;
+        assert!(logs.contains("invalid request"));
+        assert!(!logs.contains("hunter2"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_blocked_client_disconnects() -> Result<()> {
+        let backend = Backend::new();
+        let (server, mut client) = UnixStream::pair()?;
+        let server = tokio::spawn(stream_handler(server, backend.connect()));
+        client.write_all(b"BZPOPMIN jobs 0\r\n").await?;
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        drop(client);
+        server.await??;
+
+        // nobody is waiting anymore, the element stays
+        backend
+            .zadd(
+                "jobs".to_string(),
+                vec![(1.0, "job1".to_string())],
+                &ZAddOptions::default(),
+            )
+            .unwrap();
+        assert_eq!(backend.zcard("jobs"), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_requests_while_blocked() -> Result<()> {
+        let backend = Backend::new();
+        let (server, mut client) = UnixStream::pair()?;
+        let server = tokio::spawn(stream_handler(server, backend.connect()));
+        client.write_all(b"BZPOPMIN jobs 0\r\nPING\r\n").await?;
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        client.write_all(b"ECHO hi\r\n").await?;
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        backend
+            .zadd(
+                "jobs".to_string(),
+                vec![(1.0, "job1".to_string())],
+                &ZAddOptions::default(),
+            )
+            .unwrap();
+        client.shutdown().await?;
+
+        let mut replies = Vec::new();
+        client.read_to_end(&mut replies).await?;
+        assert_eq!(
+            replies,
+            b"*3\r\n$4\r\njobs\r\n$4\r\njob1\r\n$1\r\n1\r\n+PONG\r\n$2\r\nhi\r\n"
+        );
+        server.await??;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_maxclients() -> Result<()> {
+        let backend = Backend::with_config(Config {
+            maxclients: 1,
+            ..Config::default()
+        });
+        let (first, mut first_client) = UnixStream::pair()?;
+        let first = tokio::spawn(stream_handler(first, backend.connect()));
+        first_client.write_all(b"PING\r\n").await?;
+        let mut reply = [0; 7];
+        first_client.read_exact(&mut reply).await?;
+        assert_eq!(backend.connected_clients(), 1);
+
+        let (second, mut second_client) = UnixStream::pair()?;
+        stream_handler(second, backend.connect()).await?;
+        let mut replies = Vec::new();
+        second_client.read_to_end(&mut replies).await?;
+        assert_eq!(replies, b"-ERR max number of clients reached\r\n");
+        let rejected = &backend.stats().rejected_connections;
+        assert_eq!(rejected.load(Ordering::Relaxed), 1);
+
+        first_client.shutdown().await?;
+        first.await??;
+        assert_eq!(backend.connected_clients(), 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_idle_timeout() -> Result<()> {
+        let backend = Backend::new();
+        let (server, mut client) = UnixStream::pair()?;
+        let server = tokio::spawn(stream_handler(server, backend.connect()));
+        client.write_all(b"PING\r\n").await?;
+        let mut reply = [0; 7];
+        client.read_exact(&mut reply).await?;
+
+        // applies to a client already idle
+        backend.config_set(&[("timeout".to_string(), "1".to_string())])?;
+        let start = Instant::now();
+        let mut replies = Vec::new();
+        client.read_to_end(&mut replies).await?;
+        assert!(replies.is_empty());
+        assert!(start.elapsed() >= Duration::from_millis(900));
+        server.await??;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_idle_timeout_from_accept() -> Result<()> {
+        let backend = Backend::new();
+        backend.config_set(&[("timeout".to_string(), "1".to_string())])?;
+        // the time spent setting the connection up counts as idle
+        let accepted = Instant::now() - Duration::from_millis(800);
+        let (server, mut client) = UnixStream::pair()?;
+        let backend = backend.connect();
+        let slot = backend.register_client();
+        let server = tokio::spawn(client_handler(server, backend, slot, accepted));
+        let start = Instant::now();
+        let mut replies = Vec::new();
+        client.read_to_end(&mut replies).await?;
+        assert!(replies.is_empty());
+        assert!(start.elapsed() < Duration::from_millis(600));
+        server.await??;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_idle_timeout_config_change() -> Result<()> {
+        let backend = Backend::new();
+        backend.config_set(&[("timeout".to_string(), "1".to_string())])?;
+        let (server, mut client) = UnixStream::pair()?;
+        let server = tokio::spawn(stream_handler(server, backend.connect()));
+        client.write_all(b"PING\r\n").await?;
+        let mut reply = [0; 7];
+        client.read_exact(&mut reply).await?;
+        let start = Instant::now();
+
+        // changing the config doesn't restart the idle timer
+        tokio::time::sleep(Duration::from_millis(600)).await;
+        backend.config_set(&[("maxclients".to_string(), "100".to_string())])?;
+        let mut replies = Vec::new();
+        client.read_to_end(&mut replies).await?;
+        assert!(replies.is_empty());
+        assert!(start.elapsed() < Duration::from_millis(1500));
+        server.await??;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_tcp_keepalive() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let client = TcpStream::connect(listener.local_addr()?).await?;
+        let (socket, _) = listener.accept().await?;
+
+        set_tcp_keepalive(&socket, 300)?;
+        let sock = SockRef::from(&socket);
+        assert!(sock.keepalive()?);
+        assert_eq!(sock.tcp_keepalive_time()?, Duration::from_secs(300));
+        assert_eq!(sock.tcp_keepalive_interval()?, Duration::from_secs(100));
+        assert_eq!(sock.tcp_keepalive_retries()?, 3);
+
+        set_tcp_keepalive(&socket, 0)?;
+        assert!(!sock.keepalive()?);
+        drop(client);
+        Ok(())
     }
 }
@@ -1,8 +1,8 @@
 use super::{
-    calc_total_length, parse_length, RespDecode, RespEncode, RespError, RespFrame, BUF_CAP,
+    calc_total_length, parse_length, put_header, RespDecode, RespEncode, RespError, RespFrame,
     CRLF_LEN,
 };
-use bytes::{Buf, BytesMut};
+use bytes::{Buf, BufMut, BytesMut};
 use std::ops::Deref;
 
 #[derive(Debug, Hash, Clone, PartialEq, PartialOrd)]
@@ -19,17 +19,15 @@ impl RespArray {
 }
 
 impl RespEncode for RespArray {
-    fn encode(self) -> Vec<u8> {
+    fn encode_to<B: BufMut>(self, buf: &mut B) {
         match self.0 {
             Some(arr) => {
-                let mut buf = Vec::with_capacity(BUF_CAP);
-                buf.extend_from_slice(&format!("*{}\r\n", arr.len()).into_bytes());
+                put_header(buf, b'*', arr.len());
                 for frame in arr {
-                    buf.extend_from_slice(&frame.encode());
+                    frame.encode_to(buf);
                 }
-                buf
             }
-            None => b"*-1\r\n".to_vec(),
+            None => buf.put_slice(b"*-1\r\n"),
         }
     }
 }
@@ -38,6 +36,7 @@ impl RespDecode for RespArray {
     const PREFIX: &'static str = "*";
     fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
         if buf.starts_with(b"*-1\r\n") {
+            buf.advance(5);
             return Ok(RespArray::null());
         }
 
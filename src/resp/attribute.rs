@@ -0,0 +1,94 @@
+use super::{
+    calc_total_length, parse_length, put_header, RespDecode, RespEncode, RespError, RespFrame,
+    RespMap, CRLF_LEN,
+};
+use bytes::{Buf, BufMut, BytesMut};
+
+/// Auxiliary data attached to a reply. On the wire the attribute map comes
+/// right before the reply it describes, here it wraps that reply.
+#[derive(Debug, Hash, Clone, PartialEq, PartialOrd)]
+pub struct RespAttribute {
+    pub(crate) attrs: RespMap,
+    pub(crate) frame: Box<RespFrame>,
+}
+
+impl RespAttribute {
+    pub fn new(attrs: RespMap, frame: impl Into<RespFrame>) -> Self {
+        RespAttribute {
+            attrs,
+            frame: Box::new(frame.into()),
+        }
+    }
+
+    pub fn attrs(&self) -> &RespMap {
+        &self.attrs
+    }
+
+    pub fn frame(&self) -> &RespFrame {
+        &self.frame
+    }
+
+    pub fn into_frame(self) -> RespFrame {
+        *self.frame
+    }
+}
+
+impl RespEncode for RespAttribute {
+    fn encode_to<B: BufMut>(self, buf: &mut B) {
+        put_header(buf, b'|', self.attrs.len());
+        self.attrs.encode_entries(buf);
+        self.frame.encode_to(buf);
+    }
+}
+
+impl RespDecode for RespAttribute {
+    const PREFIX: &'static str = "|";
+    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
+        let (end, len) = parse_length(buf, Self::PREFIX)?;
+        let total_len = calc_total_length(buf, end, len, Self::PREFIX)?;
+        if buf.len() < total_len {
+            return Err(RespError::NotComplete);
+        }
+        buf.advance(end + CRLF_LEN);
+
+        let attrs = RespMap::decode_entries(buf, len)?;
+        let frame = RespFrame::decode(buf)?;
+        Ok(RespAttribute::new(attrs, frame))
+    }
+
+    fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
+        let (end, len) = parse_length(buf, Self::PREFIX)?;
+        calc_total_length(buf, end, len, Self::PREFIX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RespArray;
+
+    fn attribute() -> RespAttribute {
+        let mut attrs = RespMap::new();
+        attrs.insert("ttl", 3600);
+        RespAttribute::new(attrs, RespArray::new([1.into(), 2.into()]))
+    }
+
+    #[test]
+    fn test_attribute_encode() {
+        let frame: RespFrame = attribute().into();
+        assert_eq!(frame.encode(), b"|1\r\n+ttl\r\n:3600\r\n*2\r\n:1\r\n:2\r\n");
+    }
+
+    #[test]
+    fn test_attribute_decode() -> anyhow::Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"|1\r\n+ttl\r\n:3600\r\n*2\r\n:1\r\n");
+        assert_eq!(RespAttribute::decode(&mut buf), Err(RespError::NotComplete));
+
+        buf.extend_from_slice(b":2\r\n");
+        let frame = RespAttribute::decode(&mut buf)?;
+        assert_eq!(frame, attribute());
+        assert!(buf.is_empty());
+        Ok(())
+    }
+}
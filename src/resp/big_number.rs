@@ -0,0 +1,90 @@
+use super::{extract_simple_frame_data, put_line, RespDecode, RespEncode, RespError, CRLF_LEN};
+use bytes::{Buf, BufMut, BytesMut};
+use std::ops::Deref;
+
+/// Integer outside of the i64 range, kept as its decimal representation.
+#[derive(Debug, Hash, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct BigNumber(pub(crate) String);
+
+impl BigNumber {
+    pub fn new(s: impl Into<String>) -> Self {
+        BigNumber(s.into())
+    }
+}
+
+fn is_valid(s: &str) -> bool {
+    let digits = s.strip_prefix(['-', '+']).unwrap_or(s);
+    !digits.is_empty() && digits.bytes().all(|c| c.is_ascii_digit())
+}
+
+impl RespEncode for BigNumber {
+    fn encode_to<B: BufMut>(self, buf: &mut B) {
+        put_line(buf, b'(', self.0.as_bytes());
+    }
+}
+
+impl RespDecode for BigNumber {
+    const PREFIX: &'static str = "(";
+    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
+        match extract_simple_frame_data(buf, Self::PREFIX)? {
+            Some(end) => {
+                let s = String::from_utf8_lossy(&buf[Self::PREFIX.len()..end]).to_string();
+                if !is_valid(&s) {
+                    return Err(RespError::InvalidFrame("invalid big number".to_string()));
+                }
+                buf.advance(end + CRLF_LEN);
+                Ok(BigNumber::new(s))
+            }
+            None => Err(RespError::NotComplete),
+        }
+    }
+
+    fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
+        let end = extract_simple_frame_data(buf, Self::PREFIX)?;
+        match end {
+            Some(end) => Ok(end + CRLF_LEN),
+            None => Err(RespError::NotComplete),
+        }
+    }
+}
+
+impl Deref for BigNumber {
+    type Target = String;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RespFrame;
+
+    #[test]
+    fn test_big_number_encode() {
+        let frame: RespFrame = BigNumber::new("3492890328409238509324850943850943825024385").into();
+        assert_eq!(
+            frame.encode(),
+            b"(3492890328409238509324850943850943825024385\r\n"
+        );
+    }
+
+    #[test]
+    fn test_big_number_decode() -> anyhow::Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"(-3492890328409238509324850943850943825024385\r\n");
+        let frame = BigNumber::decode(&mut buf)?;
+        assert_eq!(
+            frame,
+            BigNumber::new("-3492890328409238509324850943850943825024385")
+        );
+
+        buf.extend_from_slice(b"(12a\r\n");
+        assert!(matches!(
+            BigNumber::decode(&mut buf),
+            Err(RespError::InvalidFrame(_))
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,85 @@
+use super::{
+    expect_blob_len, parse_blob, put_header, RespDecode, RespEncode, RespError, RespLimits, CRLF,
+    CRLF_LEN,
+};
+use bytes::{Buf, BufMut, BytesMut};
+use std::ops::Deref;
+
+/// Error carrying a binary safe message, which may contain CRLF.
+#[derive(Debug, Hash, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct BlobError(pub(crate) Vec<u8>);
+
+impl BlobError {
+    pub fn new(e: impl Into<Vec<u8>>) -> Self {
+        BlobError(e.into())
+    }
+
+    // `decode` with the length bounded by `limits` instead of the default ones
+    pub(super) fn decode_with_limits(
+        buf: &mut BytesMut,
+        limits: &RespLimits,
+    ) -> Result<Self, RespError> {
+        let (start, len) = parse_blob(buf, Self::PREFIX, limits)?;
+        buf.advance(start);
+        let data = buf.split_to(len + CRLF_LEN);
+        Ok(BlobError::new(data[..len].to_vec()))
+    }
+}
+
+impl RespEncode for BlobError {
+    fn encode_to<B: BufMut>(self, buf: &mut B) {
+        put_header(buf, b'!', self.len());
+        buf.put_slice(&self.0);
+        buf.put_slice(CRLF);
+    }
+}
+
+impl RespDecode for BlobError {
+    const PREFIX: &'static str = "!";
+
+    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
+        Self::decode_with_limits(buf, &RespLimits::DEFAULT)
+    }
+
+    fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
+        expect_blob_len(buf, Self::PREFIX)
+    }
+}
+
+impl From<&str> for BlobError {
+    fn from(value: &str) -> Self {
+        BlobError(value.as_bytes().to_vec())
+    }
+}
+
+impl Deref for BlobError {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RespFrame;
+
+    #[test]
+    fn test_blob_error_encode() {
+        let frame: RespFrame = BlobError::new("SYNTAX invalid\r\nsyntax").into();
+        assert_eq!(frame.encode(), b"!22\r\nSYNTAX invalid\r\nsyntax\r\n");
+    }
+
+    #[test]
+    fn test_blob_error_decode() -> anyhow::Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"!21\r\nSYNTAX invalid syntax");
+        assert_eq!(BlobError::decode(&mut buf), Err(RespError::NotComplete));
+
+        buf.extend_from_slice(b"\r\n");
+        let frame = BlobError::decode(&mut buf)?;
+        assert_eq!(frame, BlobError::new("SYNTAX invalid syntax"));
+        Ok(())
+    }
+}
@@ -1,9 +1,9 @@
 use super::{extract_fixed_data, RespDecode, RespEncode, RespError};
-use bytes::BytesMut;
+use bytes::{BufMut, BytesMut};
 
 impl RespEncode for bool {
-    fn encode(self) -> Vec<u8> {
-        format!("#{}\r\n", if self { "t" } else { "f" }).into_bytes()
+    fn encode_to<B: BufMut>(self, buf: &mut B) {
+        buf.put_slice(if self { b"#t\r\n" } else { b"#f\r\n" });
     }
 }
 
@@ -1,31 +1,51 @@
-use super::{parse_length, RespDecode, RespEncode, RespError, CRLF_LEN};
-use bytes::{Buf, BytesMut};
+use super::{
+    expect_blob_len, parse_blob, put_header, RespDecode, RespEncode, RespError, RespLimits, CRLF,
+    CRLF_LEN,
+};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
 use std::ops::Deref;
 
+/// The payload is a `Bytes`, so decoded bulk strings share the read buffer
+/// instead of being copied out of it.
 #[derive(Debug, Hash, Clone, PartialEq, Eq, PartialOrd, Ord)]
-pub struct BulkString(pub(crate) Option<Vec<u8>>);
+pub struct BulkString(pub(crate) Option<Bytes>);
 
 impl BulkString {
     pub fn new(b: impl Into<Vec<u8>>) -> Self {
-        BulkString(Some(b.into()))
+        BulkString(Some(Bytes::from(b.into())))
     }
 
     pub fn null() -> Self {
         BulkString(None)
     }
+
+    // `decode` with the length bounded by `limits` instead of the default ones
+    pub(super) fn decode_with_limits(
+        buf: &mut BytesMut,
+        limits: &RespLimits,
+    ) -> Result<Self, RespError> {
+        if buf.starts_with(b"$-1\r\n") {
+            buf.advance(5);
+            return Ok(BulkString::null());
+        }
+
+        let (start, len) = parse_blob(buf, Self::PREFIX, limits)?;
+        buf.advance(start);
+        let data = buf.split_to(len).freeze();
+        buf.advance(CRLF_LEN);
+        Ok(BulkString(Some(data)))
+    }
 }
 
 impl RespEncode for BulkString {
-    fn encode(self) -> Vec<u8> {
+    fn encode_to<B: BufMut>(self, buf: &mut B) {
         match self.0 {
             Some(data) => {
-                let mut buf = Vec::with_capacity(data.len() + 16);
-                buf.extend_from_slice(&format!("${}\r\n", data.len()).into_bytes());
-                buf.extend_from_slice(&data);
-                buf.extend_from_slice(b"\r\n");
-                buf
+                put_header(buf, b'$', data.len());
+                buf.put_slice(&data);
+                buf.put_slice(CRLF);
             }
-            None => b"$-1\r\n".to_vec(),
+            None => buf.put_slice(b"$-1\r\n"),
         }
     }
 }
@@ -34,47 +54,38 @@ impl RespDecode for BulkString {
     const PREFIX: &'static str = "$";
 
     fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
-        if buf.starts_with(b"$-1\r\n") {
-            return Ok(BulkString::null());
-        }
-
-        let (end, len) = parse_length(buf, Self::PREFIX)?;
-        let remained = &buf[end + CRLF_LEN..];
-        if remained.len() < len + CRLF_LEN {
-            return Err(RespError::NotComplete);
-        }
-
-        buf.advance(end + CRLF_LEN);
-
-        let data = buf.split_to(len + CRLF_LEN);
-        Ok(BulkString::new(data[..len].to_vec()))
+        Self::decode_with_limits(buf, &RespLimits::DEFAULT)
     }
 
     fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
         if buf.starts_with(b"$-1\r\n") {
             return Ok(5);
         }
+        expect_blob_len(buf, Self::PREFIX)
+    }
+}
 
-        let (end, len) = parse_length(buf, Self::PREFIX)?;
-        Ok(end + CRLF_LEN + len + CRLF_LEN)
+impl From<Bytes> for BulkString {
+    fn from(value: Bytes) -> Self {
+        BulkString(Some(value))
     }
 }
 
 impl From<&str> for BulkString {
     fn from(value: &str) -> Self {
-        BulkString(Some(value.as_bytes().to_vec()))
+        BulkString::new(value)
     }
 }
 
 impl From<&[u8]> for BulkString {
     fn from(value: &[u8]) -> Self {
-        BulkString(Some(value.to_vec()))
+        BulkString::new(value)
     }
 }
 
 impl<const N: usize> From<&[u8; N]> for BulkString {
     fn from(value: &[u8; N]) -> Self {
-        BulkString(Some(value.to_vec()))
+        BulkString::new(value)
     }
 }
 
@@ -92,7 +103,7 @@ impl Deref for BulkString {
 
     fn deref(&self) -> &Self::Target {
         match &self.0 {
-            Some(data) => data.as_slice(),
+            Some(data) => data,
             None => &[],
         }
     }
@@ -141,6 +152,7 @@ mod tests {
 
         let frame = BulkString::decode(&mut buf)?;
         assert_eq!(frame, BulkString::null());
+        assert!(buf.is_empty());
 
         Ok(())
     }
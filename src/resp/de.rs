@@ -0,0 +1,506 @@
+use super::{
+    BulkString, RespArray, RespFrame, RespMap, SerdeError, SimpleError, SimpleString,
+    VerbatimString,
+};
+use serde::{
+    de::{self, DeserializeOwned, IntoDeserializer, Visitor},
+    forward_to_deserialize_any,
+};
+use std::vec;
+
+/// Deserialize a value from a frame, the reverse of `to_frame`.
+///
+/// Replies are accepted in the shapes redis sends them: numbers can come as
+/// strings, maps as flat key/value arrays like in RESP2, and attributes are
+/// skipped. Error replies fail with `SerdeError::ErrorReply`.
+pub fn from_frame<T: DeserializeOwned>(frame: RespFrame) -> Result<T, SerdeError> {
+    T::deserialize(Deserializer::new(frame))
+}
+
+#[derive(Debug)]
+pub struct Deserializer {
+    frame: RespFrame,
+}
+
+struct SeqDeserializer {
+    iter: vec::IntoIter<RespFrame>,
+}
+
+struct MapDeserializer {
+    iter: vec::IntoIter<(RespFrame, RespFrame)>,
+    value: Option<RespFrame>,
+}
+
+struct EnumDeserializer {
+    variant: RespFrame,
+    value: Option<RespFrame>,
+}
+
+// the data of an enum variant, none for a unit variant sent as a string
+struct VariantDeserializer {
+    value: Option<RespFrame>,
+}
+
+impl Deserializer {
+    pub fn new(frame: RespFrame) -> Self {
+        // attributes only describe the reply they wrap
+        let mut frame = frame;
+        while let RespFrame::Attribute(attr) = frame {
+            frame = attr.into_frame();
+        }
+        Deserializer { frame }
+    }
+
+    // the text of a string reply, if it is one
+    fn text(&self) -> Option<String> {
+        match &self.frame {
+            RespFrame::SimpleString(SimpleString(s)) => Some(s.clone()),
+            RespFrame::BulkString(BulkString(Some(b))) => String::from_utf8(b.to_vec()).ok(),
+            RespFrame::VerbatimString(s) => String::from_utf8(s.data.clone()).ok(),
+            _ => None,
+        }
+    }
+
+    fn is_null(&self) -> bool {
+        matches!(
+            self.frame,
+            RespFrame::Null(_)
+                | RespFrame::BulkString(BulkString(None))
+                | RespFrame::Array(RespArray(None))
+        )
+    }
+
+    fn parse<T: std::str::FromStr>(&self) -> Option<T> {
+        self.text().and_then(|s| s.parse().ok())
+    }
+}
+
+fn visit_seq<'de, V: Visitor<'de>>(
+    frames: Vec<RespFrame>,
+    visitor: V,
+) -> Result<V::Value, SerdeError> {
+    let len = frames.len();
+    let mut seq = SeqDeserializer {
+        iter: frames.into_iter(),
+    };
+    let value = visitor.visit_seq(&mut seq)?;
+    match seq.iter.len() {
+        0 => Ok(value),
+        left => Err(de::Error::invalid_length(
+            len,
+            &format!("{} elements", len - left).as_str(),
+        )),
+    }
+}
+
+fn visit_map<'de, V: Visitor<'de>>(
+    entries: Vec<(RespFrame, RespFrame)>,
+    visitor: V,
+) -> Result<V::Value, SerdeError> {
+    visitor.visit_map(MapDeserializer {
+        iter: entries.into_iter(),
+        value: None,
+    })
+}
+
+// a RESP2 map reply: keys and values one after the other
+fn flat_entries(frames: Vec<RespFrame>) -> Option<Vec<(RespFrame, RespFrame)>> {
+    if !frames.len().is_multiple_of(2) {
+        return None;
+    }
+    let mut iter = frames.into_iter();
+    let mut entries = Vec::with_capacity(iter.len() / 2);
+    while let (Some(k), Some(v)) = (iter.next(), iter.next()) {
+        entries.push((k, v));
+    }
+    Some(entries)
+}
+
+macro_rules! deserialize_number {
+    ($($method:ident => $visit:ident($ty:ty),)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+                match self.parse::<$ty>() {
+                    Some(v) => visitor.$visit(v),
+                    None => self.deserialize_any(visitor),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer {
+    type Error = SerdeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        match self.frame {
+            RespFrame::SimpleString(SimpleString(s)) => visitor.visit_string(s),
+            RespFrame::Error(SimpleError(e)) => Err(SerdeError::ErrorReply(e)),
+            RespFrame::Integer(i) => visitor.visit_i64(i),
+            RespFrame::BulkString(BulkString(Some(b))) => match String::from_utf8(b.into()) {
+                Ok(s) => visitor.visit_string(s),
+                Err(e) => visitor.visit_byte_buf(e.into_bytes()),
+            },
+            RespFrame::BulkString(BulkString(None))
+            | RespFrame::Array(RespArray(None))
+            | RespFrame::Null(_) => visitor.visit_unit(),
+            RespFrame::Array(RespArray(Some(frames))) => visit_seq(frames, visitor),
+            RespFrame::Boolean(b) => visitor.visit_bool(b),
+            RespFrame::Double(f) => visitor.visit_f64(f),
+            RespFrame::Map(RespMap(entries)) => visit_map(entries, visitor),
+            RespFrame::Set(set) => visit_seq(set.0, visitor),
+            RespFrame::BigNumber(n) => match n.0.parse::<i128>() {
+                Ok(v) => visitor.visit_i128(v),
+                Err(_) => visitor.visit_string(n.0),
+            },
+            RespFrame::VerbatimString(VerbatimString { data, .. }) => {
+                match String::from_utf8(data) {
+                    Ok(s) => visitor.visit_string(s),
+                    Err(e) => visitor.visit_byte_buf(e.into_bytes()),
+                }
+            }
+            RespFrame::BlobError(e) => Err(SerdeError::ErrorReply(
+                String::from_utf8_lossy(&e).to_string(),
+            )),
+            RespFrame::Push(push) => visit_seq(push.0, visitor),
+            RespFrame::Attribute(attr) => {
+                Deserializer::new(attr.into_frame()).deserialize_any(visitor)
+            }
+        }
+    }
+
+    deserialize_number! {
+        deserialize_i8 => visit_i64(i64),
+        deserialize_i16 => visit_i64(i64),
+        deserialize_i32 => visit_i64(i64),
+        deserialize_i64 => visit_i64(i64),
+        deserialize_i128 => visit_i128(i128),
+        deserialize_u8 => visit_u64(u64),
+        deserialize_u16 => visit_u64(u64),
+        deserialize_u32 => visit_u64(u64),
+        deserialize_u64 => visit_u64(u64),
+        deserialize_u128 => visit_u128(u128),
+        deserialize_f32 => visit_f64(f64),
+        deserialize_f64 => visit_f64(f64),
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        // RESP2 has no booleans, they are sent as 0 or 1
+        match self.frame {
+            RespFrame::Integer(i @ (0 | 1)) => visitor.visit_bool(i == 1),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        match self.frame {
+            RespFrame::Integer(i) => visitor.visit_string(i.to_string()),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        match self.frame {
+            RespFrame::BulkString(BulkString(Some(b))) => visitor.visit_byte_buf(b.into()),
+            RespFrame::SimpleString(SimpleString(s)) => visitor.visit_byte_buf(s.into_bytes()),
+            RespFrame::VerbatimString(s) => visitor.visit_byte_buf(s.data),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        self.deserialize_byte_buf(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        if self.is_null() {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        if self.is_null() {
+            visitor.visit_unit()
+        } else {
+            self.deserialize_any(visitor)
+        }
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        match self.frame {
+            RespFrame::Array(RespArray(Some(frames))) => match flat_entries(frames) {
+                Some(entries) => visit_map(entries, visitor),
+                None => Err(de::Error::invalid_length(1, &"key/value pairs")),
+            },
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        match self.frame {
+            RespFrame::Map(RespMap(mut entries)) if entries.len() == 1 => {
+                let (variant, value) = entries.remove(0);
+                visitor.visit_enum(EnumDeserializer {
+                    variant,
+                    value: Some(value),
+                })
+            }
+            frame @ (RespFrame::SimpleString(_) | RespFrame::BulkString(_)) => {
+                visitor.visit_enum(EnumDeserializer {
+                    variant: frame,
+                    value: None,
+                })
+            }
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        char seq tuple tuple_struct
+    }
+}
+
+impl<'de> de::SeqAccess<'de> for SeqDeserializer {
+    type Error = SerdeError;
+
+    fn next_element_seed<T: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, SerdeError> {
+        self.iter
+            .next()
+            .map(|frame| seed.deserialize(Deserializer::new(frame)))
+            .transpose()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+impl<'de> de::MapAccess<'de> for MapDeserializer {
+    type Error = SerdeError;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, SerdeError> {
+        let Some((key, value)) = self.iter.next() else {
+            return Ok(None);
+        };
+        self.value = Some(value);
+        seed.deserialize(Deserializer::new(key)).map(Some)
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, SerdeError> {
+        let value = self
+            .value
+            .take()
+            .ok_or_else(|| SerdeError::Message("map value without a key".to_string()))?;
+        seed.deserialize(Deserializer::new(value))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+impl<'de> de::EnumAccess<'de> for EnumDeserializer {
+    type Error = SerdeError;
+    type Variant = VariantDeserializer;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self::Variant), SerdeError> {
+        let variant = seed.deserialize(Deserializer::new(self.variant))?;
+        Ok((variant, VariantDeserializer { value: self.value }))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for VariantDeserializer {
+    type Error = SerdeError;
+
+    fn unit_variant(self) -> Result<(), SerdeError> {
+        match self.value {
+            Some(frame) => de::Deserialize::deserialize(Deserializer::new(frame)),
+            None => Ok(()),
+        }
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, SerdeError> {
+        match self.value {
+            Some(frame) => seed.deserialize(Deserializer::new(frame)),
+            None => Err(de::Error::invalid_type(
+                de::Unexpected::UnitVariant,
+                &"newtype variant",
+            )),
+        }
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        match self.value {
+            Some(frame) => de::Deserializer::deserialize_seq(Deserializer::new(frame), visitor),
+            None => Err(de::Error::invalid_type(
+                de::Unexpected::UnitVariant,
+                &"tuple variant",
+            )),
+        }
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        match self.value {
+            Some(frame) => de::Deserializer::deserialize_map(Deserializer::new(frame), visitor),
+            None => Err(de::Error::invalid_type(
+                de::Unexpected::UnitVariant,
+                &"struct variant",
+            )),
+        }
+    }
+}
+
+impl IntoDeserializer<'_, SerdeError> for RespFrame {
+    type Deserializer = Deserializer;
+
+    fn into_deserializer(self) -> Deserializer {
+        Deserializer::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{to_frame, RespDecode, RespNull};
+    use bytes::BytesMut;
+    use serde::{Deserialize, Serialize};
+    use std::collections::HashMap;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct User {
+        name: String,
+        age: u8,
+        email: Option<String>,
+        tags: Vec<String>,
+        role: Role,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Role {
+        Admin,
+        Guest(u32),
+        Member { since: i64 },
+    }
+
+    #[test]
+    fn test_round_trip() -> anyhow::Result<()> {
+        for role in [Role::Admin, Role::Guest(7), Role::Member { since: -1 }] {
+            let user = User {
+                name: "alice".to_string(),
+                age: 30,
+                email: Some("a@b.c".to_string()),
+                tags: vec!["x".to_string()],
+                role,
+            };
+            let frame = to_frame(&user)?;
+            assert_eq!(from_frame::<User>(frame)?, user);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_resp2_reply() -> anyhow::Result<()> {
+        // HGETALL under RESP2: a flat array with numbers as strings
+        let mut buf = BytesMut::from(
+            &b"*8\r\n$4\r\nname\r\n$3\r\nbob\r\n$3\r\nage\r\n$2\r\n42\r\n\
+               $4\r\ntags\r\n*0\r\n$4\r\nrole\r\n$5\r\nAdmin\r\n"[..],
+        );
+        let user: User = from_frame(RespFrame::decode(&mut buf)?)?;
+        assert_eq!(
+            user,
+            User {
+                name: "bob".to_string(),
+                age: 42,
+                email: None,
+                tags: vec![],
+                role: Role::Admin,
+            }
+        );
+
+        let map: HashMap<String, f64> = from_frame(
+            RespArray::new([b"a".into(), b"1.5".into(), b"b".into(), b"inf".into()]).into(),
+        )?;
+        assert_eq!(map["a"], 1.5);
+        assert_eq!(map["b"], f64::INFINITY);
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_frame_errors() {
+        assert_eq!(
+            from_frame::<String>(SimpleError::new("ERR nope").into()),
+            Err(SerdeError::ErrorReply("ERR nope".to_string()))
+        );
+        assert!(from_frame::<u8>(RespFrame::Integer(300)).is_err());
+        assert!(from_frame::<(i64, i64)>(RespArray::new([1.into()]).into()).is_err());
+        assert!(from_frame::<(i64,)>(RespArray::new([1.into(), 2.into()]).into()).is_err());
+        assert_eq!(from_frame::<Option<i64>>(RespNull.into()), Ok(None));
+    }
+}
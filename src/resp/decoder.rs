@@ -0,0 +1,391 @@
+use super::{
+    aggregate_frames, line_end, parse_length, unterminated_line_len, BlobError, BulkString,
+    RespArray, RespAttribute, RespDecode, RespError, RespFrame, RespLimits, RespMap, RespNull,
+    RespPush, RespSet, VerbatimString, CRLF_LEN,
+};
+use bytes::{Buf, BytesMut};
+
+// upper bound for preallocating the elements of an aggregate, its length
+// comes from the peer
+const PREALLOC_MAX: usize = 1024;
+
+/// A resumable decoder for a stream of frames.
+///
+/// `RespFrame::decode` checks that a whole aggregate is buffered before
+/// parsing it, so a large array is scanned again on every read until it is
+/// complete, then parsed once more. This decoder instead consumes elements
+/// as soon as they are complete and keeps the aggregates being built between
+/// calls, so every byte is parsed once. Bulk strings are split off the read
+/// buffer without copying.
+///
+/// Lengths and nesting are checked against its `RespLimits` as soon as the
+/// headers are read, and a line is rejected once it is longer than allowed
+/// even before its CRLF arrives.
+#[derive(Debug, Default)]
+pub struct RespFrameDecoder {
+    limits: RespLimits,
+    stack: Vec<Partial>,
+    // bytes of the line starting the buffer already searched for its CRLF
+    scanned: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Aggregate {
+    Array,
+    Set,
+    Push,
+    Map,
+    Attribute,
+}
+
+// an aggregate waiting for more elements
+#[derive(Debug)]
+struct Partial {
+    kind: Aggregate,
+    expected: usize,
+    frames: Vec<RespFrame>,
+}
+
+impl Aggregate {
+    fn from_prefix(prefix: u8) -> Option<Self> {
+        match prefix {
+            b'*' => Some(Aggregate::Array),
+            b'~' => Some(Aggregate::Set),
+            b'>' => Some(Aggregate::Push),
+            b'%' => Some(Aggregate::Map),
+            b'|' => Some(Aggregate::Attribute),
+            _ => None,
+        }
+    }
+
+    fn prefix(&self) -> &'static str {
+        match self {
+            Aggregate::Array => RespArray::PREFIX,
+            Aggregate::Set => RespSet::PREFIX,
+            Aggregate::Push => RespPush::PREFIX,
+            Aggregate::Map => RespMap::PREFIX,
+            Aggregate::Attribute => RespAttribute::PREFIX,
+        }
+    }
+}
+
+impl Partial {
+    fn new(kind: Aggregate, len: usize) -> Result<Self, RespError> {
+        let expected = aggregate_frames(kind.prefix(), len)?;
+        Ok(Partial {
+            kind,
+            expected,
+            frames: Vec::with_capacity(expected.min(PREALLOC_MAX)),
+        })
+    }
+
+    fn is_complete(&self) -> bool {
+        self.frames.len() == self.expected
+    }
+
+    fn into_frame(self) -> RespFrame {
+        let mut frames = self.frames;
+        match self.kind {
+            Aggregate::Array => RespArray::new(frames).into(),
+            Aggregate::Set => RespSet::new(frames).into(),
+            Aggregate::Push => RespPush::new(frames).into(),
+            Aggregate::Map => RespMap(pairs(frames)).into(),
+            Aggregate::Attribute => {
+                let frame = frames.pop().unwrap_or_else(|| RespNull.into());
+                RespAttribute::new(RespMap(pairs(frames)), frame).into()
+            }
+        }
+    }
+}
+
+fn pairs(frames: Vec<RespFrame>) -> Vec<(RespFrame, RespFrame)> {
+    let mut iter = frames.into_iter();
+    let mut entries = Vec::with_capacity(iter.len() / 2);
+    while let (Some(k), Some(v)) = (iter.next(), iter.next()) {
+        entries.push((k, v));
+    }
+    entries
+}
+
+impl RespFrameDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_limits(limits: RespLimits) -> Self {
+        RespFrameDecoder {
+            limits,
+            stack: Vec::new(),
+            scanned: 0,
+        }
+    }
+
+    pub fn limits(&self) -> &RespLimits {
+        &self.limits
+    }
+
+    /// Change the limits, they apply from the next header read.
+    pub fn set_limits(&mut self, limits: RespLimits) {
+        self.limits = limits;
+    }
+
+    /// Whether no frame is partially decoded.
+    pub fn is_idle(&self) -> bool {
+        self.stack.is_empty()
+    }
+
+    /// Decode the next frame from `buf`, consuming what has been parsed.
+    /// Returns `Ok(None)` when more data is needed, the decoding resumes
+    /// from where it stopped on the next call.
+    pub fn decode(&mut self, buf: &mut BytesMut) -> Result<Option<RespFrame>, RespError> {
+        let ret = self.decode_frame(buf);
+        if ret.is_err() {
+            self.stack.clear();
+            self.scanned = 0;
+        }
+        ret
+    }
+
+    fn decode_frame(&mut self, buf: &mut BytesMut) -> Result<Option<RespFrame>, RespError> {
+        loop {
+            let Some(&prefix) = buf.first() else {
+                return Ok(None);
+            };
+            if !self.line_complete(buf)? {
+                return Ok(None);
+            }
+
+            let frame = match Aggregate::from_prefix(prefix) {
+                Some(Aggregate::Array) if buf.starts_with(b"*-1\r\n") => {
+                    buf.advance(5);
+                    RespArray::null().into()
+                }
+                Some(kind) => {
+                    let (end, len) = match parse_length(buf, kind.prefix()) {
+                        Err(RespError::NotComplete) => return Ok(None),
+                        ret => ret?,
+                    };
+                    self.limits.check_multibulk_len(len)?;
+                    self.limits.check_depth(self.stack.len() + 1)?;
+                    buf.advance(end + CRLF_LEN);
+                    let partial = Partial::new(kind, len)?;
+                    if !partial.is_complete() {
+                        self.stack.push(partial);
+                        continue;
+                    }
+                    partial.into_frame()
+                }
+                None => match self.decode_scalar(buf) {
+                    Err(RespError::NotComplete) => return Ok(None),
+                    ret => ret?,
+                },
+            };
+
+            if let Some(frame) = self.complete(frame) {
+                return Ok(Some(frame));
+            }
+        }
+    }
+
+    // Whether the line starting `buf`, a header or a whole simple frame, is
+    // buffered. The search for its CRLF resumes where the previous call
+    // stopped, so a line received in many reads is scanned once.
+    fn line_complete(&mut self, buf: &[u8]) -> Result<bool, RespError> {
+        match line_end(buf, self.scanned) {
+            Some(end) => {
+                self.scanned = 0;
+                self.limits.check_line_len(end - 1)?;
+                Ok(true)
+            }
+            None => {
+                self.limits.check_line_len(unterminated_line_len(buf))?;
+                // the last byte may be the '\r' of the CRLF
+                self.scanned = buf.len().saturating_sub(1);
+                Ok(false)
+            }
+        }
+    }
+
+    // a frame that isn't an aggregate, blobs longer than allowed are rejected
+    // from their header, before buffering them
+    fn decode_scalar(&self, buf: &mut BytesMut) -> Result<RespFrame, RespError> {
+        Ok(match buf.first() {
+            Some(b'$') => BulkString::decode_with_limits(buf, &self.limits)?.into(),
+            Some(b'=') => VerbatimString::decode_with_limits(buf, &self.limits)?.into(),
+            Some(b'!') => BlobError::decode_with_limits(buf, &self.limits)?.into(),
+            _ => RespFrame::decode(buf)?,
+        })
+    }
+
+    // add a decoded frame to the aggregate being built, returns the top
+    // level frame once there is nothing left to fill
+    fn complete(&mut self, mut frame: RespFrame) -> Option<RespFrame> {
+        loop {
+            let Some(partial) = self.stack.last_mut() else {
+                return Some(frame);
+            };
+            partial.frames.push(frame);
+            if !partial.is_complete() {
+                return None;
+            }
+            frame = self.stack.pop()?.into_frame();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+
+    #[test]
+    fn test_decoder_resumes() -> anyhow::Result<()> {
+        let data = b"*3\r\n$3\r\nset\r\n*2\r\n:1\r\n%1\r\n$1\r\nk\r\n~0\r\n$5\r\nhello\r\n";
+        let expected: RespFrame = RespArray::new([
+            b"set".into(),
+            RespArray::new([
+                1.into(),
+                [(BulkString::new("k"), RespSet::new([]))]
+                    .into_iter()
+                    .collect::<RespMap>()
+                    .into(),
+            ])
+            .into(),
+            b"hello".into(),
+        ])
+        .into();
+
+        // feed one byte at a time
+        let mut decoder = RespFrameDecoder::new();
+        let mut buf = BytesMut::new();
+        for (i, c) in data.iter().enumerate() {
+            buf.extend_from_slice(&[*c]);
+            let ret = decoder.decode(&mut buf)?;
+            if i + 1 < data.len() {
+                assert_eq!(ret, None);
+            } else {
+                assert_eq!(ret, Some(expected.clone()));
+            }
+        }
+        assert!(decoder.is_idle());
+        assert!(buf.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_decoder_pipeline() -> anyhow::Result<()> {
+        let mut decoder = RespFrameDecoder::new();
+        let mut buf = BytesMut::from(&b"*0\r\n*-1\r\n|1\r\n+ttl\r\n:1\r\n_\r\n>1\r\n:2"[..]);
+        assert_eq!(decoder.decode(&mut buf)?, Some(RespArray::new([]).into()));
+        assert_eq!(decoder.decode(&mut buf)?, Some(RespArray::null().into()));
+
+        let mut attrs = RespMap::new();
+        attrs.insert("ttl", 1);
+        assert_eq!(
+            decoder.decode(&mut buf)?,
+            Some(RespAttribute::new(attrs, RespNull).into())
+        );
+
+        assert_eq!(decoder.decode(&mut buf)?, None);
+        assert!(!decoder.is_idle());
+        buf.extend_from_slice(b"\r\n");
+        assert_eq!(
+            decoder.decode(&mut buf)?,
+            Some(RespPush::new([2.into()]).into())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_decoder_shares_buffer() -> anyhow::Result<()> {
+        let mut buf = BytesMut::from(&b"*1\r\n$5\r\nhello\r\n"[..]);
+        let start = buf.as_ptr() as usize;
+        let frame = RespFrameDecoder::new().decode(&mut buf)?;
+        let Some(RespFrame::Array(RespArray(Some(frames)))) = frame else {
+            panic!("expect an array, got {:?}", frame);
+        };
+        let RespFrame::BulkString(BulkString(Some(data))) = &frames[0] else {
+            panic!("expect a bulk string, got {:?}", frames[0]);
+        };
+        assert_eq!(data.as_ptr() as usize, start + 8);
+        Ok(())
+    }
+
+    #[test]
+    fn test_decoder_limits() {
+        let limits = RespLimits {
+            max_bulk_len: 4,
+            max_multibulk_len: 2,
+            max_depth: 2,
+            ..RespLimits::DEFAULT
+        };
+        let decode = |data: &[u8]| {
+            let mut buf = BytesMut::from(data);
+            RespFrameDecoder::with_limits(limits).decode(&mut buf)
+        };
+
+        assert!(decode(b"*2\r\n$4\r\nabcd\r\n$-1\r\n").is_ok());
+        assert!(decode(b"*1\r\n*1\r\n:1\r\n").is_ok());
+        // rejected from the header, without waiting for the data
+        assert_eq!(
+            decode(b"$5\r\n"),
+            Err(RespError::InvalidFrameLength(
+                "invalid bulk length".to_string()
+            ))
+        );
+        assert_eq!(
+            decode(b"*999999999\r\n"),
+            Err(RespError::InvalidFrameLength(
+                "invalid multibulk length".to_string()
+            ))
+        );
+        assert!(matches!(
+            decode(b"*1\r\n*1\r\n*1\r\n"),
+            Err(RespError::InvalidFrame(_))
+        ));
+    }
+
+    #[test]
+    fn test_decoder_line_limit() -> anyhow::Result<()> {
+        let limits = RespLimits {
+            max_line_len: 8,
+            ..RespLimits::DEFAULT
+        };
+        let mut decoder = RespFrameDecoder::with_limits(limits);
+        let mut buf = BytesMut::new();
+        // a header arriving a byte at a time, up to the limit
+        for c in b"*1\r\n+12345678\r" {
+            buf.extend_from_slice(&[*c]);
+            assert_eq!(decoder.decode(&mut buf)?, None);
+        }
+        buf.extend_from_slice(b"\n");
+        assert_eq!(
+            decoder.decode(&mut buf)?,
+            Some(RespArray::new([RespFrame::SimpleString("12345678".into())]).into())
+        );
+
+        // rejected without waiting for the CRLF, whether nested or not
+        for data in [&b"*123456789"[..], b"*1\r\n:123456789"] {
+            let mut buf = BytesMut::from(data);
+            assert_eq!(
+                decoder.decode(&mut buf),
+                Err(RespError::InvalidFrameLength("line too long".to_string()))
+            );
+            assert!(decoder.is_idle());
+        }
+        let mut buf = BytesMut::from(&b"+123456789\r\n"[..]);
+        assert!(decoder.decode(&mut buf).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_decoder_error() {
+        let mut decoder = RespFrameDecoder::new();
+        let mut buf = BytesMut::from(&b"*2\r\n:1\r\n@oops\r\n"[..]);
+        assert!(matches!(
+            decoder.decode(&mut buf),
+            Err(RespError::InvalidFrameType(_))
+        ));
+        assert!(decoder.is_idle());
+    }
+}
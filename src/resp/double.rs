@@ -1,17 +1,54 @@
-use super::{extract_simple_frame_data, RespDecode, RespEncode, RespError, CRLF_LEN};
-use bytes::BytesMut;
+use super::{extract_simple_frame_data, put_line, RespDecode, RespEncode, RespError, CRLF_LEN};
+use bytes::{BufMut, BytesMut};
+
+/// The text of a double as in RESP3: `inf`, `-inf`, `nan`, or the shortest
+/// decimal that parses back to the same value. An exponent is only used for
+/// magnitudes where `%g` would use one.
+pub fn format_double(f: f64) -> String {
+    match f {
+        f if f.is_nan() => "nan".to_string(),
+        f if f.is_infinite() && f > 0.0 => "inf".to_string(),
+        f if f.is_infinite() => "-inf".to_string(),
+        f if f != 0.0 && (f.abs() >= 1e17 || f.abs() < 1e-4) => format!("{:e}", f),
+        f => f.to_string(),
+    }
+}
+
+// `[+|-]<integral>[.<fractional>][<e|E>[+|-]<exponent>]`, or a special value
+fn parse_double(s: &str) -> Result<f64, RespError> {
+    match s {
+        "inf" | "+inf" => return Ok(f64::INFINITY),
+        "-inf" => return Ok(f64::NEG_INFINITY),
+        "nan" => return Ok(f64::NAN),
+        _ => {}
+    }
+
+    fn digits(s: &str) -> bool {
+        !s.is_empty() && s.bytes().all(|c| c.is_ascii_digit())
+    }
+    fn unsigned(s: &str) -> &str {
+        s.strip_prefix(['+', '-']).unwrap_or(s)
+    }
+    let (mantissa, exponent) = match unsigned(s).split_once(['e', 'E']) {
+        Some((m, e)) => (m, Some(e)),
+        None => (unsigned(s), None),
+    };
+    let (integral, fractional) = match mantissa.split_once('.') {
+        Some((i, f)) => (i, Some(f)),
+        None => (mantissa, None),
+    };
+    if !digits(integral)
+        || !fractional.is_none_or(digits)
+        || !exponent.is_none_or(|e| digits(unsigned(e)))
+    {
+        return Err(RespError::InvalidFrame("invalid double".to_string()));
+    }
+    Ok(s.parse()?)
+}
 
 impl RespEncode for f64 {
-    fn encode(self) -> Vec<u8> {
-        let mut buf = Vec::with_capacity(32);
-        let ret = if self.abs() > 1e+8 || self.abs() < 1e-8 {
-            format!(",{:+e}\r\n", self)
-        } else {
-            let sign = if self < 0.0 { "" } else { "+" };
-            format!(",{}{}\r\n", sign, self)
-        };
-        buf.extend_from_slice(&ret.into_bytes());
-        buf
+    fn encode_to<B: BufMut>(self, buf: &mut B) {
+        put_line(buf, b',', format_double(self).as_bytes());
     }
 }
 
@@ -22,7 +59,7 @@ impl RespDecode for f64 {
             Some(end) => {
                 let data = buf.split_to(end + 2);
                 let s = String::from_utf8_lossy(&data[Self::PREFIX.len()..end]);
-                Ok(s.parse()?)
+                parse_double(&s)
             }
             None => Err(RespError::NotComplete),
         }
@@ -45,13 +82,63 @@ mod tests {
     #[test]
     fn test_double_encode() {
         let frame: RespFrame = 123.456.into();
-        assert_eq!(frame.encode(), b",+123.456\r\n");
+        assert_eq!(frame.encode(), b",123.456\r\n");
         let frame: RespFrame = (-123.456).into();
         assert_eq!(frame.encode(), b",-123.456\r\n");
         let frame: RespFrame = 1.23456e+8.into();
-        assert_eq!(frame.encode(), b",+1.23456e8\r\n");
+        assert_eq!(frame.encode(), b",123456000\r\n");
         let frame: RespFrame = (-1.23456e-9).into();
         assert_eq!(frame.encode(), b",-1.23456e-9\r\n");
+        let frame: RespFrame = 1e300.into();
+        assert_eq!(frame.encode(), b",1e300\r\n");
+    }
+
+    #[test]
+    fn test_double_special_values() {
+        assert_eq!(0.0.encode(), b",0\r\n");
+        assert_eq!((-0.0).encode(), b",-0\r\n");
+        assert_eq!(f64::INFINITY.encode(), b",inf\r\n");
+        assert_eq!(f64::NEG_INFINITY.encode(), b",-inf\r\n");
+        assert_eq!(f64::NAN.encode(), b",nan\r\n");
+
+        let mut buf = BytesMut::from(&b",inf\r\n,-inf\r\n,nan\r\n"[..]);
+        assert_eq!(f64::decode(&mut buf), Ok(f64::INFINITY));
+        assert_eq!(f64::decode(&mut buf), Ok(f64::NEG_INFINITY));
+        assert!(f64::decode(&mut buf).is_ok_and(|f| f.is_nan()));
+    }
+
+    #[test]
+    fn test_double_round_trip() {
+        for f in [
+            0.1,
+            1.0 / 3.0,
+            f64::MAX,
+            f64::MIN_POSITIVE,
+            5e-324,
+            -2.5e-5,
+            1e17,
+        ] {
+            let mut buf = BytesMut::from(&f.encode()[..]);
+            assert_eq!(f64::decode(&mut buf), Ok(f));
+        }
+    }
+
+    #[test]
+    fn test_double_decode_invalid() {
+        for s in [
+            ",1.\r\n",
+            ",.5\r\n",
+            ",1e\r\n",
+            ",infinity\r\n",
+            ",0x10\r\n",
+        ] {
+            let mut buf = BytesMut::from(s.as_bytes());
+            assert!(
+                matches!(f64::decode(&mut buf), Err(RespError::InvalidFrame(_))),
+                "{}",
+                s
+            );
+        }
     }
 
     #[test]
@@ -69,10 +156,7 @@ mod tests {
         let ret = f64::decode(&mut buf);
         assert_eq!(
             ret,
-            Err(RespError::InvalidFrame(format!(
-                "expect: SimpleString(+), got: {:?}",
-                buf.to_vec()
-            )))
+            Err(RespError::InvalidFrame("expect: ',', got: '1'".to_string()))
         );
         Ok(())
     }
@@ -1,6 +1,7 @@
+use super::double::format_double;
 use super::{
-    BulkString, RespArray, RespDecode, RespError, RespMap, RespNull, RespSet, SimpleError,
-    SimpleString,
+    type_byte, BigNumber, BlobError, BulkString, RespArray, RespAttribute, RespDecode, RespError,
+    RespMap, RespNull, RespPush, RespSet, SimpleError, SimpleString, VerbatimString,
 };
 use bytes::BytesMut;
 use enum_dispatch::enum_dispatch;
@@ -19,6 +20,11 @@ pub enum RespFrame {
     Double(f64),
     Map(RespMap),
     Set(RespSet),
+    BigNumber(BigNumber),
+    VerbatimString(VerbatimString),
+    BlobError(BlobError),
+    Push(RespPush),
+    Attribute(RespAttribute),
 }
 
 impl Eq for RespFrame {}
@@ -36,6 +42,63 @@ impl Hash for RespFrame {
             RespFrame::Double(f) => f.to_bits().hash(state),
             RespFrame::Map(m) => m.hash(state),
             RespFrame::Set(s) => s.hash(state),
+            RespFrame::BigNumber(n) => n.hash(state),
+            RespFrame::VerbatimString(s) => s.hash(state),
+            RespFrame::BlobError(e) => e.hash(state),
+            RespFrame::Push(p) => p.hash(state),
+            RespFrame::Attribute(a) => a.hash(state),
+        }
+    }
+}
+
+impl RespFrame {
+    /// Downgrade the RESP3-only types to their RESP2 equivalents: maps, sets
+    /// and pushes become flat arrays, booleans integers, doubles, big numbers
+    /// and verbatim strings bulk strings, blob errors simple errors and nulls
+    /// null bulk strings. Attributes are dropped.
+    pub fn into_resp2(self) -> RespFrame {
+        match self {
+            RespFrame::Array(RespArray(Some(frames))) => RespArray::new(
+                frames
+                    .into_iter()
+                    .map(RespFrame::into_resp2)
+                    .collect::<Vec<_>>(),
+            )
+            .into(),
+            RespFrame::Null(_) => BulkString::null().into(),
+            RespFrame::Boolean(b) => RespFrame::Integer(b as i64),
+            RespFrame::Double(f) => BulkString::new(format_double(f)).into(),
+            RespFrame::Map(map) => {
+                let frames = map
+                    .0
+                    .into_iter()
+                    .flat_map(|(k, v)| [k.into_resp2(), v.into_resp2()])
+                    .collect::<Vec<_>>();
+                RespArray::new(frames).into()
+            }
+            RespFrame::Set(set) => RespArray::new(
+                set.0
+                    .into_iter()
+                    .map(RespFrame::into_resp2)
+                    .collect::<Vec<_>>(),
+            )
+            .into(),
+            RespFrame::BigNumber(n) => BulkString::new(n.0).into(),
+            RespFrame::VerbatimString(s) => BulkString::new(s.data).into(),
+            RespFrame::BlobError(e) => {
+                // simple errors can't hold line breaks
+                let e = String::from_utf8_lossy(&e).replace(['\r', '\n'], " ");
+                SimpleError::new(e).into()
+            }
+            RespFrame::Push(push) => RespArray::new(
+                push.0
+                    .into_iter()
+                    .map(RespFrame::into_resp2)
+                    .collect::<Vec<_>>(),
+            )
+            .into(),
+            RespFrame::Attribute(attr) => attr.into_frame().into_resp2(),
+            frame => frame,
         }
     }
 }
@@ -85,10 +148,30 @@ impl RespDecode for RespFrame {
                 let frame = RespSet::decode(buf)?;
                 Ok(frame.into())
             }
+            Some(b'(') => {
+                let frame = BigNumber::decode(buf)?;
+                Ok(frame.into())
+            }
+            Some(b'=') => {
+                let frame = VerbatimString::decode(buf)?;
+                Ok(frame.into())
+            }
+            Some(b'!') => {
+                let frame = BlobError::decode(buf)?;
+                Ok(frame.into())
+            }
+            Some(b'>') => {
+                let frame = RespPush::decode(buf)?;
+                Ok(frame.into())
+            }
+            Some(b'|') => {
+                let frame = RespAttribute::decode(buf)?;
+                Ok(frame.into())
+            }
             None => Err(RespError::NotComplete),
             _ => Err(RespError::InvalidFrameType(format!(
-                "decode unknown frame type: {:?}",
-                buf
+                "decode unknown frame type: '{}'",
+                type_byte(buf)
             ))),
         }
     }
@@ -106,6 +189,11 @@ impl RespDecode for RespFrame {
             Some(b'#') => bool::expect_length(buf),
             Some(b',') => f64::expect_length(buf),
             Some(b'_') => RespNull::expect_length(buf),
+            Some(b'(') => BigNumber::expect_length(buf),
+            Some(b'=') => VerbatimString::expect_length(buf),
+            Some(b'!') => BlobError::expect_length(buf),
+            Some(b'>') => RespPush::expect_length(buf),
+            Some(b'|') => RespAttribute::expect_length(buf),
             _ => Err(RespError::NotComplete),
         }
     }
@@ -119,12 +207,167 @@ impl From<&str> for RespFrame {
 
 impl From<&[u8]> for RespFrame {
     fn from(value: &[u8]) -> Self {
-        BulkString(Some(value.to_vec())).into()
+        BulkString::new(value).into()
     }
 }
 
 impl<const N: usize> From<&[u8; N]> for RespFrame {
     fn from(value: &[u8; N]) -> Self {
-        BulkString(Some(value.to_vec())).into()
+        BulkString::new(value).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{RespEncode, RespFrameDecoder};
+    use proptest::{collection::vec, prelude::*};
+
+    // frames whose encoding decodes back to an equal frame
+    fn arb_frame() -> impl Strategy<Value = RespFrame> {
+        let line = "[^\r\n]{0,16}";
+        let bytes = || vec(any::<u8>(), 0..32);
+        let leaf = prop_oneof![
+            line.prop_map(|s| SimpleString::new(s).into()),
+            line.prop_map(|s| SimpleError::new(s).into()),
+            any::<i64>().prop_map(RespFrame::Integer),
+            bytes().prop_map(|b| BulkString::new(b).into()),
+            Just(BulkString::null().into()),
+            Just(RespArray::null().into()),
+            Just(RespNull.into()),
+            any::<bool>().prop_map(RespFrame::Boolean),
+            any::<f64>()
+                .prop_filter("nan is never equal", |f| !f.is_nan())
+                .prop_map(RespFrame::Double),
+            "-?[0-9]{1,40}".prop_map(|s| BigNumber::new(s).into()),
+            ("[a-z]{3}", bytes()).prop_map(|(format, data)| {
+                let format = [
+                    format.as_bytes()[0],
+                    format.as_bytes()[1],
+                    format.as_bytes()[2],
+                ];
+                VerbatimString::new(format, data).into()
+            }),
+            bytes().prop_map(|e| BlobError::new(e).into()),
+        ];
+        leaf.prop_recursive(3, 32, 4, |inner| {
+            let entries = || vec((inner.clone(), inner.clone()), 0..4);
+            prop_oneof![
+                vec(inner.clone(), 0..4).prop_map(|v| RespArray::new(v).into()),
+                vec(inner.clone(), 0..4).prop_map(|v| RespSet::new(v).into()),
+                vec(inner.clone(), 0..4).prop_map(|v| RespPush::new(v).into()),
+                entries().prop_map(|v| RespMap(v).into()),
+                (entries(), inner.clone()).prop_map(|(attrs, frame)| RespAttribute::new(
+                    RespMap(attrs),
+                    frame
+                )
+                .into()),
+            ]
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn prop_encode_decode(frame in arb_frame()) {
+            let mut buf = BytesMut::from(&frame.clone().encode()[..]);
+            prop_assert_eq!(RespFrame::decode(&mut buf), Ok(frame));
+            prop_assert!(buf.is_empty());
+        }
+
+        #[test]
+        fn prop_partial_frame(frame in arb_frame()) {
+            let data = frame.encode();
+            for i in 0..data.len() {
+                let mut buf = BytesMut::from(&data[..i]);
+                prop_assert_eq!(RespFrame::decode(&mut buf), Err(RespError::NotComplete));
+                prop_assert_eq!(&buf[..], &data[..i]);
+            }
+        }
+
+        #[test]
+        fn prop_decode_garbage(data in vec(prop::sample::select(b"*~>%|$=!+-:_#,(\r\n-0123x".to_vec()), 0..64)) {
+            // the fuzz target's property: errors, not panics
+            let mut buf = BytesMut::from(&data[..]);
+            while RespFrame::decode(&mut buf).is_ok() {}
+            let mut decoder = RespFrameDecoder::new();
+            let mut buf = BytesMut::from(&data[..]);
+            while let Ok(Some(_)) = decoder.decode(&mut buf) {}
+        }
+
+        #[test]
+        fn prop_decoder_chunks(frames in vec(arb_frame(), 1..4), chunk in 1usize..16) {
+            let data = frames.iter().cloned().flat_map(RespFrame::encode).collect::<Vec<_>>();
+            let mut decoder = RespFrameDecoder::new();
+            let mut buf = BytesMut::new();
+            let mut decoded = vec![];
+            for part in data.chunks(chunk) {
+                buf.extend_from_slice(part);
+                while let Some(frame) = decoder.decode(&mut buf).unwrap() {
+                    decoded.push(frame);
+                }
+            }
+            prop_assert_eq!(decoded, frames);
+            prop_assert!(decoder.is_idle());
+        }
+    }
+
+    #[test]
+    fn test_encode_to_appends() {
+        let mut buf = BytesMut::from(&b"+OK\r\n"[..]);
+        let frame: RespFrame = RespArray::new([
+            BulkString::new("a").into(),
+            RespArray::new([RespFrame::Integer(-12), RespNull.into()]).into(),
+        ])
+        .into();
+        frame.clone().encode_to(&mut buf);
+        assert_eq!(&buf[..], b"+OK\r\n*2\r\n$1\r\na\r\n*2\r\n:-12\r\n_\r\n");
+        assert_eq!(&buf[5..], frame.encode());
+    }
+
+    #[test]
+    fn test_into_resp2() {
+        let mut map = RespMap::new();
+        map.insert(BulkString::new("ok"), true);
+        map.insert(BulkString::new("score"), f64::INFINITY);
+        map.insert(RespNull, 1.5);
+        let frame: RespFrame = RespArray::new([
+            map.into(),
+            RespNull.into(),
+            RespSet::new([RespFrame::Double(1.5)]).into(),
+        ])
+        .into();
+
+        assert_eq!(
+            frame.into_resp2(),
+            RespArray::new([
+                RespArray::new([
+                    b"ok".into(),
+                    RespFrame::Integer(1),
+                    b"score".into(),
+                    b"inf".into(),
+                    BulkString::null().into(),
+                    b"1.5".into(),
+                ])
+                .into(),
+                BulkString::null().into(),
+                RespArray::new([b"1.5".into()]).into(),
+            ])
+            .into()
+        );
+    }
+
+    #[test]
+    fn test_resp3_types_into_resp2() {
+        let frame: RespFrame = BlobError::new("ERR bad\r\nthing").into();
+        assert_eq!(
+            frame.into_resp2(),
+            SimpleError::new("ERR bad  thing").into()
+        );
+
+        let frame: RespFrame = RespAttribute::new(RespMap::new(), BigNumber::new("12")).into();
+        assert_eq!(frame.into_resp2(), b"12".into());
+
+        let frame: RespFrame = RespPush::new([VerbatimString::text("hi").into()]).into();
+        assert_eq!(frame.into_resp2(), RespArray::new([b"hi".into()]).into());
     }
 }
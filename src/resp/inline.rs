@@ -0,0 +1,176 @@
+use super::{BulkString, RespArray, RespError, RespFrame, RespLimits};
+use bytes::BytesMut;
+
+impl RespArray {
+    /// Decode a command sent in the inline format, like `SET key "a value"`:
+    /// space separated arguments on a single line, with the quoting rules of
+    /// redis-cli. Blank lines are skipped.
+    pub fn decode_inline(buf: &mut BytesMut) -> Result<Self, RespError> {
+        Self::decode_inline_with_limit(buf, RespLimits::DEFAULT.max_inline_len)
+    }
+
+    /// Same as `decode_inline`, failing once `max_len` bytes are buffered
+    /// without a line feed.
+    pub fn decode_inline_with_limit(buf: &mut BytesMut, max_len: usize) -> Result<Self, RespError> {
+        loop {
+            let Some(end) = buf.iter().position(|c| *c == b'\n') else {
+                if buf.len() > max_len {
+                    return Err(RespError::InvalidFrameLength(
+                        "too big inline request".to_string(),
+                    ));
+                }
+                return Err(RespError::NotComplete);
+            };
+            let line = buf.split_to(end + 1);
+            let line = &line[..end];
+            let args = split_args(line.strip_suffix(b"\r").unwrap_or(line))?;
+            if !args.is_empty() {
+                let args = args
+                    .into_iter()
+                    .map(|v| BulkString::new(v).into())
+                    .collect::<Vec<RespFrame>>();
+                return Ok(RespArray::new(args));
+            }
+        }
+    }
+}
+
+fn unbalanced_quotes() -> RespError {
+    RespError::InvalidFrame("unbalanced quotes in request".to_string())
+}
+
+fn hex_value(c: u8) -> Option<u8> {
+    (c as char).to_digit(16).map(|v| v as u8)
+}
+
+// split a line into arguments like sdssplitargs: double quotes support the
+// usual escapes and `\xHH`, single quotes only `\'`, and a closing quote must
+// be followed by a space or the end of the line
+fn split_args(line: &[u8]) -> Result<Vec<Vec<u8>>, RespError> {
+    let mut args = vec![];
+    let mut i = 0;
+    loop {
+        while i < line.len() && line[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        if i == line.len() {
+            return Ok(args);
+        }
+
+        let mut arg = vec![];
+        let mut quote = None;
+        while i < line.len() {
+            let c = line[i];
+            match quote {
+                Some(b'"') if c == b'\\' && i + 3 < line.len() && line[i + 1] == b'x' => {
+                    match (hex_value(line[i + 2]), hex_value(line[i + 3])) {
+                        (Some(hi), Some(lo)) => {
+                            arg.push(hi * 16 + lo);
+                            i += 3;
+                        }
+                        _ => {
+                            arg.push(b'x');
+                            i += 1;
+                        }
+                    }
+                }
+                Some(b'"') if c == b'\\' && i + 1 < line.len() => {
+                    i += 1;
+                    arg.push(match line[i] {
+                        b'n' => b'\n',
+                        b'r' => b'\r',
+                        b't' => b'\t',
+                        b'b' => 0x08,
+                        b'a' => 0x07,
+                        c => c,
+                    });
+                }
+                Some(b'\'') if c == b'\\' && line.get(i + 1) == Some(&b'\'') => {
+                    i += 1;
+                    arg.push(b'\'');
+                }
+                Some(q) if c == q => {
+                    if line.get(i + 1).is_some_and(|c| !c.is_ascii_whitespace()) {
+                        return Err(unbalanced_quotes());
+                    }
+                    quote = None;
+                    i += 1;
+                    break;
+                }
+                Some(_) => arg.push(c),
+                None if c.is_ascii_whitespace() => break,
+                None if c == b'"' || c == b'\'' => quote = Some(c),
+                None => arg.push(c),
+            }
+            i += 1;
+        }
+        if quote.is_some() {
+            return Err(unbalanced_quotes());
+        }
+        args.push(arg);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(s: &[u8]) -> Result<RespArray, RespError> {
+        let mut buf = BytesMut::from(s);
+        RespArray::decode_inline(&mut buf)
+    }
+
+    #[test]
+    fn test_inline_decode() -> anyhow::Result<()> {
+        assert_eq!(decode(b"PING\r\n")?, RespArray::new([b"PING".into()]));
+        assert_eq!(
+            decode(b"  set  key   value\n")?,
+            RespArray::new([b"set".into(), b"key".into(), b"value".into()])
+        );
+        assert_eq!(decode(b"PING"), Err(RespError::NotComplete));
+        assert_eq!(decode(b"\r\n\r\n"), Err(RespError::NotComplete));
+
+        let mut buf = BytesMut::from(&b"\r\necho a\r\necho b\r\n"[..]);
+        assert_eq!(
+            RespArray::decode_inline(&mut buf)?,
+            RespArray::new([b"echo".into(), b"a".into()])
+        );
+        assert_eq!(buf.as_ref(), b"echo b\r\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_inline_quotes() -> anyhow::Result<()> {
+        assert_eq!(
+            decode(b"set \"a key\" 'it\\'s' \"\\x41\\tb\" \"\"\r\n")?,
+            RespArray::new([
+                b"set".into(),
+                b"a key".into(),
+                b"it's".into(),
+                b"A\tb".into(),
+                b"".into(),
+            ])
+        );
+        assert!(matches!(
+            decode(b"set \"key\r\n"),
+            Err(RespError::InvalidFrame(_))
+        ));
+        assert!(matches!(
+            decode(b"set \"key\"value\r\n"),
+            Err(RespError::InvalidFrame(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_inline_too_big() {
+        let buf = vec![b'a'; RespLimits::DEFAULT.max_inline_len + 1];
+        assert!(matches!(
+            decode(&buf),
+            Err(RespError::InvalidFrameLength(_))
+        ));
+
+        let mut buf = BytesMut::from(&b"ping"[..]);
+        assert!(RespArray::decode_inline_with_limit(&mut buf, 3).is_err());
+    }
+}
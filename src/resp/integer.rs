@@ -1,5 +1,7 @@
-use super::{extract_simple_frame_data, RespDecode, RespEncode, RespError, CRLF_LEN};
-use bytes::BytesMut;
+use super::{
+    extract_simple_frame_data, put_decimal, RespDecode, RespEncode, RespError, CRLF, CRLF_LEN,
+};
+use bytes::{BufMut, BytesMut};
 
 impl RespDecode for i64 {
     const PREFIX: &'static str = ":";
@@ -24,8 +26,13 @@ impl RespDecode for i64 {
 }
 
 impl RespEncode for i64 {
-    fn encode(self) -> Vec<u8> {
-        format!(":{}\r\n", self).into_bytes()
+    fn encode_to<B: BufMut>(self, buf: &mut B) {
+        buf.put_u8(b':');
+        if self < 0 {
+            buf.put_u8(b'-');
+        }
+        put_decimal(buf, self.unsigned_abs());
+        buf.put_slice(CRLF);
     }
 }
 
@@ -40,6 +47,8 @@ mod tests {
         assert_eq!(frame.encode(), b":123\r\n");
         let frame: RespFrame = (-123).into();
         assert_eq!(frame.encode(), b":-123\r\n");
+        assert_eq!(0.encode(), b":0\r\n");
+        assert_eq!(i64::MIN.encode(), b":-9223372036854775808\r\n");
     }
 
     #[test]
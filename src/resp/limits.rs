@@ -0,0 +1,70 @@
+use super::RespError;
+
+/// Bounds on what a peer can make the decoder buffer or build. Lengths read
+/// from the wire are checked against them before waiting for the data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RespLimits {
+    /// Longest bulk string, verbatim string or blob error, like
+    /// `proto-max-bulk-len`.
+    pub max_bulk_len: usize,
+    /// Most elements in an aggregate.
+    pub max_multibulk_len: usize,
+    /// Most aggregates nested in each other.
+    pub max_depth: usize,
+    /// Longest inline request.
+    pub max_inline_len: usize,
+    /// Longest line: the header of an aggregate or blob, or a simple frame
+    /// like a simple string or an integer.
+    pub max_line_len: usize,
+}
+
+impl RespLimits {
+    pub const DEFAULT: RespLimits = RespLimits {
+        max_bulk_len: 512 * 1024 * 1024,
+        max_multibulk_len: i32::MAX as usize,
+        max_depth: 128,
+        max_inline_len: 64 * 1024,
+        max_line_len: 64 * 1024,
+    };
+
+    pub(super) fn check_bulk_len(&self, len: usize) -> Result<(), RespError> {
+        if len > self.max_bulk_len {
+            return Err(RespError::InvalidFrameLength(
+                "invalid bulk length".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    pub(super) fn check_multibulk_len(&self, len: usize) -> Result<(), RespError> {
+        if len > self.max_multibulk_len {
+            return Err(RespError::InvalidFrameLength(
+                "invalid multibulk length".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    pub(super) fn check_line_len(&self, len: usize) -> Result<(), RespError> {
+        if len > self.max_line_len {
+            return Err(RespError::InvalidFrameLength("line too long".to_string()));
+        }
+        Ok(())
+    }
+
+    pub(super) fn check_depth(&self, depth: usize) -> Result<(), RespError> {
+        if depth > self.max_depth {
+            return Err(RespError::InvalidFrame(format!(
+                "nesting deeper than {} aggregates",
+                self.max_depth
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl Default for RespLimits {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
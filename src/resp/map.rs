@@ -1,42 +1,79 @@
 use super::{
-    calc_total_length, parse_length, RespDecode, RespEncode, RespError, RespFrame, SimpleString,
-    BUF_CAP, CRLF_LEN,
-};
-use bytes::{Buf, BytesMut};
-use std::{
-    collections::BTreeMap,
-    hash::Hash,
-    ops::{Deref, DerefMut},
+    calc_total_length, parse_length, put_header, RespDecode, RespEncode, RespError, RespFrame,
+    CRLF_LEN,
 };
+use bytes::{Buf, BufMut, BytesMut};
+use std::ops::Deref;
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
-pub struct RespMap(pub(crate) BTreeMap<String, RespFrame>);
+/// A RESP3 map. Keys can be any frame and entries keep their insertion
+/// order, which is also the order they are encoded in.
+#[derive(Debug, Hash, Clone, PartialEq, PartialOrd)]
+pub struct RespMap(pub(crate) Vec<(RespFrame, RespFrame)>);
 
 impl RespMap {
     pub fn new() -> Self {
-        RespMap(BTreeMap::new())
+        RespMap(Vec::new())
     }
-}
 
-impl Eq for RespMap {}
-impl Hash for RespMap {
-    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        for (k, v) in &self.0 {
-            k.hash(state);
-            v.hash(state);
+    /// Insert a key/value pair. An existing key keeps its position and gets
+    /// the new value, the old one is returned.
+    pub fn insert(
+        &mut self,
+        key: impl Into<RespFrame>,
+        value: impl Into<RespFrame>,
+    ) -> Option<RespFrame> {
+        let key = key.into();
+        let value = value.into();
+        match self.0.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, v)) => Some(std::mem::replace(v, value)),
+            None => {
+                self.0.push((key, value));
+                None
+            }
         }
     }
-}
 
-impl RespEncode for RespMap {
-    fn encode(self) -> Vec<u8> {
-        let mut buf = Vec::with_capacity(BUF_CAP);
-        buf.extend_from_slice(&format!("%{}\r\n", self.len()).into_bytes());
+    pub fn get(&self, key: &RespFrame) -> Option<&RespFrame> {
+        self.0.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    pub fn remove(&mut self, key: &RespFrame) -> Option<RespFrame> {
+        let pos = self.0.iter().position(|(k, _)| k == key)?;
+        Some(self.0.remove(pos).1)
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &RespFrame> {
+        self.0.iter().map(|(k, _)| k)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &RespFrame> {
+        self.0.iter().map(|(_, v)| v)
+    }
+
+    // key/value pairs of a map or an attribute, after the length header
+    pub(super) fn encode_entries<B: BufMut>(self, buf: &mut B) {
         for (key, value) in self.0 {
-            buf.extend_from_slice(&SimpleString(key).encode());
-            buf.extend_from_slice(&value.encode());
+            key.encode_to(buf);
+            value.encode_to(buf);
+        }
+    }
+
+    // entries are kept as sent, without checking for duplicated keys
+    pub(super) fn decode_entries(buf: &mut BytesMut, len: usize) -> Result<Self, RespError> {
+        let mut entries = Vec::with_capacity(len);
+        for _ in 0..len {
+            let key = RespFrame::decode(buf)?;
+            let value = RespFrame::decode(buf)?;
+            entries.push((key, value));
         }
-        buf
+        Ok(RespMap(entries))
+    }
+}
+
+impl RespEncode for RespMap {
+    fn encode_to<B: BufMut>(self, buf: &mut B) {
+        put_header(buf, b'%', self.len());
+        self.encode_entries(buf);
     }
 }
 
@@ -50,13 +87,7 @@ impl RespDecode for RespMap {
         }
         buf.advance(end + CRLF_LEN);
 
-        let mut frames = RespMap::new();
-        for _ in 0..len {
-            let key = SimpleString::decode(buf)?;
-            let value = RespFrame::decode(buf)?;
-            frames.insert(key.0, value);
-        }
-        Ok(frames)
+        RespMap::decode_entries(buf, len)
     }
 
     fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
@@ -72,33 +103,41 @@ impl Default for RespMap {
 }
 
 impl Deref for RespMap {
-    type Target = BTreeMap<String, RespFrame>;
+    type Target = Vec<(RespFrame, RespFrame)>;
 
     fn deref(&self) -> &Self::Target {
         &self.0
     }
 }
 
-impl DerefMut for RespMap {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+impl<K, V> FromIterator<(K, V)> for RespMap
+where
+    K: Into<RespFrame>,
+    V: Into<RespFrame>,
+{
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        let mut map = RespMap::new();
+        for (k, v) in iter {
+            map.insert(k, v);
+        }
+        map
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{BulkString, RespDecode, RespEncode, RespFrame, RespMap};
+    use crate::{BulkString, RespArray, RespDecode, RespEncode, RespFrame, RespMap};
     use bytes::BytesMut;
 
     #[test]
     fn test_resp_map_encode() {
         let mut map = RespMap::new();
-        map.insert("hello".to_string(), BulkString::new("world").into());
-        map.insert("foo".to_string(), (-123456.789).into());
+        map.insert("hello", BulkString::new("world"));
+        map.insert("foo", -123456.789);
         let frame: RespFrame = map.into();
         assert_eq!(
             frame.encode(),
-            b"%2\r\n+foo\r\n,-123456.789\r\n+hello\r\n$5\r\nworld\r\n"
+            b"%2\r\n+hello\r\n$5\r\nworld\r\n+foo\r\n,-123456.789\r\n"
         );
     }
 
@@ -108,12 +147,41 @@ mod tests {
         buf.extend_from_slice(b"%2\r\n+hello\r\n$5\r\nworld\r\n+foo\r\n$3\r\nbar\r\n");
         let frame = RespMap::decode(&mut buf)?;
         let mut map = RespMap::new();
-        map.insert(
-            "hello".to_string(),
-            BulkString::new(b"world".to_vec()).into(),
-        );
-        map.insert("foo".to_string(), b"bar".into());
+        map.insert("hello", BulkString::new(b"world".to_vec()));
+        map.insert("foo", b"bar");
         assert_eq!(frame, map);
         Ok(())
     }
+
+    #[test]
+    fn test_map_decode_frame_keys() -> anyhow::Result<()> {
+        // HGETALL under RESP3 replies with bulk string keys
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"%3\r\n$2\r\nzz\r\n$1\r\n1\r\n$2\r\naa\r\n$1\r\n2\r\n:7\r\n");
+        assert_eq!(
+            RespMap::decode(&mut buf),
+            Err(crate::RespError::NotComplete)
+        );
+
+        buf.extend_from_slice(b"*1\r\n:8\r\n");
+        let map = RespMap::decode(&mut buf)?;
+        assert!(buf.is_empty());
+        let keys = map.keys().cloned().collect::<Vec<_>>();
+        assert_eq!(keys, [b"zz".into(), b"aa".into(), RespFrame::Integer(7)]);
+        assert_eq!(map.get(&b"aa".into()), Some(&b"2".into()));
+        assert_eq!(map.get(&7.into()), Some(&RespArray::new([8.into()]).into()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_map_insert_keeps_order() {
+        let mut map: RespMap = [("b", 1i64), ("a", 2)].into_iter().collect();
+        assert_eq!(map.insert("b", 3), Some(1.into()));
+        assert_eq!(
+            map.values().cloned().collect::<Vec<_>>(),
+            [3.into(), 2.into()]
+        );
+        assert_eq!(map.remove(&"b".into()), Some(3.into()));
+        assert_eq!(map.len(), 1);
+    }
 }
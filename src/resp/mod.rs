@@ -1,31 +1,60 @@
 pub mod array;
+pub mod attribute;
+pub mod big_number;
+pub mod blob_error;
 pub mod bool;
 pub mod bulk_string;
+#[cfg(feature = "serde")]
+pub mod de;
+pub mod decoder;
 pub mod double;
 pub mod frame;
+mod inline;
 pub mod integer;
+pub mod limits;
 pub mod map;
 pub mod null;
+pub mod push;
+#[cfg(feature = "serde")]
+pub mod ser;
 pub mod set;
 pub mod simple_error;
 pub mod simple_string;
+pub mod verbatim_string;
 
 use array::*;
+use attribute::*;
+use big_number::*;
+use blob_error::*;
 use bulk_string::*;
 use frame::*;
+use limits::*;
 use map::*;
 use null::*;
+use push::*;
 use set::*;
 use simple_error::*;
 use simple_string::*;
+use verbatim_string::*;
 
-use bytes::{Buf, BytesMut};
+use bytes::{Buf, BufMut, BytesMut};
 use enum_dispatch::enum_dispatch;
 use thiserror::Error;
 
 #[enum_dispatch]
 pub trait RespEncode {
-    fn encode(self) -> Vec<u8>;
+    /// Append the encoded frame to `buf`, nested frames are written in
+    /// place without intermediate buffers.
+    fn encode_to<B: BufMut>(self, buf: &mut B);
+
+    fn encode(self) -> Vec<u8>
+    where
+        Self: Sized,
+    {
+        let mut buf = Vec::new();
+        self.encode_to(&mut buf);
+        buf
+    }
 }
 
 pub trait RespDecode: Sized {
@@ -53,22 +82,77 @@ pub enum RespError {
     ParseFloatError(#[from] std::num::ParseFloatError),
 }
 
-const BUF_CAP: usize = 4096;
+/// Errors of `to_frame` and `from_frame`.
+#[cfg(feature = "serde")]
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum SerdeError {
+    #[error("{0}")]
+    Message(String),
+    #[error("error reply: {0}")]
+    ErrorReply(String),
+}
+
+#[cfg(feature = "serde")]
+impl serde::ser::Error for SerdeError {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        SerdeError::Message(msg.to_string())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::de::Error for SerdeError {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        SerdeError::Message(msg.to_string())
+    }
+}
+
 const CRLF: &[u8] = b"\r\n";
 const CRLF_LEN: usize = CRLF.len();
 
-// find nth CRLF in the buffer
-fn find_crlf(buf: &[u8], nth: usize) -> Option<usize> {
-    let mut count = 0;
-    for i in 1..buf.len() - 1 {
-        if buf[i] == b'\r' && buf[i + 1] == b'\n' {
-            count += 1;
-            if count == nth {
-                return Some(i);
-            }
+// write an unsigned number in decimal, without going through a String
+fn put_decimal<B: BufMut>(buf: &mut B, mut n: u64) {
+    let mut digits = [0u8; 20];
+    let mut i = digits.len();
+    loop {
+        i -= 1;
+        digits[i] = b'0' + (n % 10) as u8;
+        n /= 10;
+        if n == 0 {
+            break;
         }
     }
-    None
+    buf.put_slice(&digits[i..]);
+}
+
+// `<prefix><len>\r\n`, the header of aggregates and blob types
+fn put_header<B: BufMut>(buf: &mut B, prefix: u8, len: usize) {
+    buf.put_u8(prefix);
+    put_decimal(buf, len as u64);
+    buf.put_slice(CRLF);
+}
+
+// `<prefix><data>\r\n`, for the types encoded on a single line
+fn put_line<B: BufMut>(buf: &mut B, prefix: u8, data: &[u8]) {
+    buf.put_u8(prefix);
+    buf.put_slice(data);
+    buf.put_slice(CRLF);
+}
+
+// the CRLF ending the line that starts `buf`, searched from `from` as the
+// bytes before it are known not to hold it
+fn line_end(buf: &[u8], from: usize) -> Option<usize> {
+    let from = from.max(1);
+    buf.get(from..)?
+        .windows(CRLF_LEN)
+        .position(|v| v == CRLF)
+        .map(|i| from + i)
+}
+
+// the length of a line still waiting for its CRLF, without the prefix and a
+// trailing '\r' which may be the start of it
+fn unterminated_line_len(buf: &[u8]) -> usize {
+    let len = buf.strip_suffix(b"\r").unwrap_or(buf).len();
+    len.saturating_sub(1)
 }
 
 fn parse_length(buf: &[u8], prefix: &str) -> Result<(usize, usize), RespError> {
@@ -81,32 +165,112 @@ fn parse_length(buf: &[u8], prefix: &str) -> Result<(usize, usize), RespError> {
     }
 }
 
+// the whole length of a blob (`$`, `=` or `!`) from its header
+fn blob_frame_len(end: usize, len: usize) -> Result<usize, RespError> {
+    (end + CRLF_LEN)
+        .checked_add(len)
+        .and_then(|n| n.checked_add(CRLF_LEN))
+        .ok_or_else(|| RespError::InvalidFrameLength("invalid bulk length".to_string()))
+}
+
+// The start and the length of the data of a blob, once buffered with the CRLF
+// that must follow it. The length is checked against `limits` from the
+// header, before waiting for the data.
+fn parse_blob(buf: &[u8], prefix: &str, limits: &RespLimits) -> Result<(usize, usize), RespError> {
+    let (end, len) = parse_length(buf, prefix)?;
+    limits.check_bulk_len(len)?;
+    let total = blob_frame_len(end, len)?;
+    if buf.len() < total {
+        return Err(RespError::NotComplete);
+    }
+    if &buf[total - CRLF_LEN..total] != CRLF {
+        return Err(RespError::InvalidFrame(
+            "blob not terminated by CRLF".to_string(),
+        ));
+    }
+    Ok((end + CRLF_LEN, len))
+}
+
+// the whole length of a blob once buffered, for `expect_length`
+fn expect_blob_len(buf: &[u8], prefix: &str) -> Result<usize, RespError> {
+    let (end, len) = parse_length(buf, prefix)?;
+    RespLimits::DEFAULT.check_bulk_len(len)?;
+    blob_frame_len(end, len)
+}
+
+// number of frames after the header of an aggregate: maps hold keys and
+// values, attributes are followed by the reply they describe
+fn aggregate_frames(prefix: &str, len: usize) -> Result<usize, RespError> {
+    match prefix {
+        "%" => len.checked_mul(2),
+        "|" => len.checked_mul(2).and_then(|n| n.checked_add(1)),
+        _ => Some(len),
+    }
+    .ok_or_else(|| RespError::InvalidFrameLength("invalid multibulk length".to_string()))
+}
+
+fn aggregate_prefix(c: u8) -> Option<&'static str> {
+    match c {
+        b'*' => Some(RespArray::PREFIX),
+        b'~' => Some(RespSet::PREFIX),
+        b'>' => Some(RespPush::PREFIX),
+        b'%' => Some(RespMap::PREFIX),
+        b'|' => Some(RespAttribute::PREFIX),
+        _ => None,
+    }
+}
+
+// the whole length of an aggregate once buffered. Nested aggregates are
+// walked with an explicit stack instead of recursing, so deep nesting can't
+// overflow the call stack
 fn calc_total_length(buf: &[u8], end: usize, len: usize, prefix: &str) -> Result<usize, RespError> {
+    let limits = RespLimits::DEFAULT;
+    if prefix.bytes().next().and_then(aggregate_prefix).is_none() {
+        return Ok(len + CRLF_LEN);
+    }
+    limits.check_multibulk_len(len)?;
+
     let mut total = end + CRLF_LEN;
-    let mut data = &buf[total..];
-    match prefix {
-        "*" | "~" => {
-            for _ in 0..len {
-                let elem_len = RespFrame::expect_length(data)?;
-                data = &data[elem_len..];
-                total += elem_len;
-            }
-            Ok(total)
+    // frames left to measure at each nesting level
+    let mut pending = vec![aggregate_frames(prefix, len)?];
+    while let Some(left) = pending.last_mut() {
+        if *left == 0 {
+            pending.pop();
+            continue;
         }
-        "%" => {
-            for _ in 0..len {
-                let key_len = SimpleString::expect_length(data)?;
-                data = &data[key_len..];
-                total += key_len;
-
-                let value_len = RespFrame::expect_length(data)?;
-                data = &data[value_len..];
-                total += value_len;
+        *left -= 1;
+
+        let data = buf.get(total..).ok_or(RespError::NotComplete)?;
+        if data.starts_with(b"*-1\r\n") {
+            total += 5;
+            continue;
+        }
+        match data.first().copied().and_then(aggregate_prefix) {
+            Some(prefix) => {
+                let (end, len) = parse_length(data, prefix)?;
+                limits.check_multibulk_len(len)?;
+                limits.check_depth(pending.len() + 1)?;
+                total += end + CRLF_LEN;
+                pending.push(aggregate_frames(prefix, len)?);
+            }
+            None => {
+                total = total
+                    .checked_add(RespFrame::expect_length(data)?)
+                    .ok_or_else(|| {
+                        RespError::InvalidFrameLength("invalid bulk length".to_string())
+                    })?
             }
-            Ok(total)
         }
-        _ => Ok(len + CRLF_LEN),
     }
+    Ok(total)
+}
+
+// the type of a frame for the error messages, the rest of it is user data
+// that must not end up in the logs
+fn type_byte(buf: &[u8]) -> String {
+    buf.first()
+        .map(|c| c.escape_ascii().to_string())
+        .unwrap_or_default()
 }
 
 fn extract_simple_frame_data(buf: &[u8], prefix: &str) -> Result<Option<usize>, RespError> {
@@ -116,14 +280,19 @@ fn extract_simple_frame_data(buf: &[u8], prefix: &str) -> Result<Option<usize>,
 
     if !buf.starts_with(prefix.as_bytes()) {
         return Err(RespError::InvalidFrame(format!(
-            "expect: SimpleString(+), got: {:?}",
-            buf
+            "expect: '{}', got: '{}'",
+            prefix,
+            type_byte(buf)
         )));
     }
 
-    let end = find_crlf(buf, 1).ok_or(RespError::NotComplete)?;
-
-    Ok(Some(end))
+    match line_end(buf, 1) {
+        Some(end) => Ok(Some(end)),
+        None => {
+            RespLimits::DEFAULT.check_line_len(unterminated_line_len(buf))?;
+            Err(RespError::NotComplete)
+        }
+    }
 }
 
 fn extract_fixed_data(
@@ -137,8 +306,9 @@ fn extract_fixed_data(
 
     if !buf.starts_with(expect.as_bytes()) {
         return Err(RespError::InvalidFrameType(format!(
-            "expect: {}, got: {:?}",
-            expect_type, buf
+            "expect: {}, got: '{}'",
+            expect_type,
+            type_byte(buf)
         )));
     }
 
@@ -150,9 +320,85 @@ fn extract_fixed_data(
 #[cfg(test)]
 mod tests {
     use crate::{
-        resp::{calc_total_length, parse_length},
-        RespError,
+        resp::{calc_total_length, line_end, parse_length},
+        RespDecode, RespError, RespFrame,
     };
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_line_end() {
+        assert_eq!(line_end(b"", 0), None);
+        assert_eq!(line_end(b"+", 0), None);
+        assert_eq!(line_end(b"+a\r\n:1\r\n", 0), Some(2));
+        assert_eq!(line_end(b"+a\r\n:1\r\n", 3), Some(6));
+    }
+
+    #[test]
+    fn test_line_too_long() {
+        let mut data = b"+".to_vec();
+        data.extend(vec![b'a'; 64 * 1024 + 1]);
+        let mut buf = BytesMut::from(&data[..]);
+        assert_eq!(
+            RespFrame::decode(&mut buf),
+            Err(RespError::InvalidFrameLength("line too long".to_string()))
+        );
+
+        // a complete line is accepted, it is already buffered
+        data.extend_from_slice(b"\r\n");
+        let mut buf = BytesMut::from(&data[..]);
+        assert!(RespFrame::decode(&mut buf).is_ok());
+    }
+
+    #[test]
+    fn test_deep_nesting() {
+        let mut data = b"*1\r\n".repeat(100_000);
+        data.extend_from_slice(b":1\r\n");
+        let mut buf = BytesMut::from(&data[..]);
+        assert!(matches!(
+            RespFrame::decode(&mut buf),
+            Err(RespError::InvalidFrame(_))
+        ));
+
+        let mut buf = BytesMut::from(&b"*1\r\n%1\r\n:1\r\n~1\r\n*-1\r\n"[..]);
+        assert!(RespFrame::decode(&mut buf).is_ok());
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn test_blob_hostile_headers() {
+        for data in [
+            &b"$18446744073709551615\r\n"[..],
+            b"=18446744073709551615\r\n",
+            b"!18446744073709551615\r\n",
+            b"*1\r\n$18446744073709551615\r\n",
+            b"$536870913\r\n",
+        ] {
+            let mut buf = BytesMut::from(data);
+            assert_eq!(
+                RespFrame::decode(&mut buf),
+                Err(RespError::InvalidFrameLength(
+                    "invalid bulk length".to_string()
+                )),
+                "{}",
+                data.escape_ascii()
+            );
+        }
+
+        // the data must be followed by CRLF
+        for data in [
+            &b"$3\r\nabcXY"[..],
+            b"=5\r\ntxt:aXY",
+            b"!1\r\naXY",
+            b"*1\r\n$3\r\nabcXY",
+        ] {
+            let mut buf = BytesMut::from(data);
+            assert!(
+                matches!(RespFrame::decode(&mut buf), Err(RespError::InvalidFrame(_))),
+                "{}",
+                data.escape_ascii()
+            );
+        }
+    }
 
     #[test]
     fn test_calc_array_length() -> anyhow::Result<()> {
@@ -165,6 +411,12 @@ mod tests {
         let (end, len) = parse_length(buf, "*")?;
         let ret = calc_total_length(buf, end, len, "*");
         assert_eq!(ret.unwrap_err(), RespError::NotComplete);
+
+        // a bulk string cut in the middle of an array
+        let buf = b"*2\r\n$3\r\nse";
+        let (end, len) = parse_length(buf, "*")?;
+        let ret = calc_total_length(buf, end, len, "*");
+        assert_eq!(ret.unwrap_err(), RespError::NotComplete);
         Ok(())
     }
 }
@@ -1,12 +1,12 @@
 use super::{extract_fixed_data, RespDecode, RespEncode, RespError};
-use bytes::BytesMut;
+use bytes::{BufMut, BytesMut};
 
 #[derive(Debug, Hash, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct RespNull;
 
 impl RespEncode for RespNull {
-    fn encode(self) -> Vec<u8> {
-        b"_\r\n".to_vec()
+    fn encode_to<B: BufMut>(self, buf: &mut B) {
+        buf.put_slice(b"_\r\n");
     }
 }
 
@@ -0,0 +1,90 @@
+use super::{
+    calc_total_length, parse_length, put_header, RespDecode, RespEncode, RespError, RespFrame,
+    CRLF_LEN,
+};
+use bytes::{Buf, BufMut, BytesMut};
+use std::ops::Deref;
+
+/// Out of band data sent by the server, like pub/sub messages or client
+/// tracking invalidations.
+#[derive(Debug, Hash, Clone, PartialEq, PartialOrd)]
+pub struct RespPush(pub(crate) Vec<RespFrame>);
+
+impl RespPush {
+    pub fn new(s: impl Into<Vec<RespFrame>>) -> Self {
+        RespPush(s.into())
+    }
+}
+
+impl RespEncode for RespPush {
+    fn encode_to<B: BufMut>(self, buf: &mut B) {
+        put_header(buf, b'>', self.len());
+        for frame in self.0 {
+            frame.encode_to(buf);
+        }
+    }
+}
+
+impl RespDecode for RespPush {
+    const PREFIX: &'static str = ">";
+    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
+        let (end, len) = parse_length(buf, Self::PREFIX)?;
+        let total_len = calc_total_length(buf, end, len, Self::PREFIX)?;
+        if buf.len() < total_len {
+            return Err(RespError::NotComplete);
+        }
+        buf.advance(end + CRLF_LEN);
+
+        let mut frames = Vec::with_capacity(len);
+        for _ in 0..len {
+            frames.push(RespFrame::decode(buf)?);
+        }
+        Ok(RespPush::new(frames))
+    }
+
+    fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
+        let (end, len) = parse_length(buf, Self::PREFIX)?;
+        calc_total_length(buf, end, len, Self::PREFIX)
+    }
+}
+
+impl Deref for RespPush {
+    type Target = Vec<RespFrame>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_encode() {
+        let frame: RespFrame =
+            RespPush::new([b"message".into(), b"news".into(), b"hello".into()]).into();
+        assert_eq!(
+            frame.encode(),
+            b">3\r\n$7\r\nmessage\r\n$4\r\nnews\r\n$5\r\nhello\r\n"
+        );
+    }
+
+    #[test]
+    fn test_push_decode() -> anyhow::Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b">2\r\n$10\r\ninvalidate\r\n*1\r\n");
+        assert_eq!(RespPush::decode(&mut buf), Err(RespError::NotComplete));
+
+        buf.extend_from_slice(b"$3\r\nkey\r\n");
+        let frame = RespPush::decode(&mut buf)?;
+        assert_eq!(
+            frame,
+            RespPush::new([
+                b"invalidate".into(),
+                crate::RespArray::new([b"key".into()]).into()
+            ])
+        );
+        Ok(())
+    }
+}
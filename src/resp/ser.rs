@@ -0,0 +1,396 @@
+use super::{BigNumber, BulkString, RespArray, RespFrame, RespMap, RespNull, SerdeError};
+use serde::{ser, Serialize};
+
+/// Serialize a value into a frame: structs and maps become `RespMap`,
+/// sequences and tuples `RespArray`, strings and bytes `BulkString`, `None`
+/// and `()` `RespNull`. Integers out of the i64 range are `BigNumber`s and
+/// enum variants with data a single entry map from the variant name to it.
+pub fn to_frame<T: Serialize + ?Sized>(value: &T) -> Result<RespFrame, SerdeError> {
+    value.serialize(Serializer)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Serializer;
+
+pub struct SerializeVec {
+    frames: Vec<RespFrame>,
+}
+
+pub struct SerializeTupleVariant {
+    variant: &'static str,
+    frames: Vec<RespFrame>,
+}
+
+pub struct SerializeMap {
+    map: RespMap,
+    key: Option<RespFrame>,
+}
+
+pub struct SerializeStructVariant {
+    variant: &'static str,
+    map: RespMap,
+}
+
+// `{variant: value}`, like serde_json's externally tagged enums
+fn variant_map(variant: &'static str, value: impl Into<RespFrame>) -> RespFrame {
+    let mut map = RespMap::new();
+    map.insert(BulkString::new(variant), value);
+    map.into()
+}
+
+fn integer<T>(v: T) -> RespFrame
+where
+    T: TryInto<i64> + ToString + Copy,
+{
+    match v.try_into() {
+        Ok(v) => RespFrame::Integer(v),
+        Err(_) => BigNumber::new(v.to_string()).into(),
+    }
+}
+
+impl ser::Serializer for Serializer {
+    type Ok = RespFrame;
+    type Error = SerdeError;
+
+    type SerializeSeq = SerializeVec;
+    type SerializeTuple = SerializeVec;
+    type SerializeTupleStruct = SerializeVec;
+    type SerializeTupleVariant = SerializeTupleVariant;
+    type SerializeMap = SerializeMap;
+    type SerializeStruct = SerializeMap;
+    type SerializeStructVariant = SerializeStructVariant;
+
+    fn serialize_bool(self, v: bool) -> Result<RespFrame, SerdeError> {
+        Ok(RespFrame::Boolean(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<RespFrame, SerdeError> {
+        Ok(RespFrame::Integer(v.into()))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<RespFrame, SerdeError> {
+        Ok(RespFrame::Integer(v.into()))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<RespFrame, SerdeError> {
+        Ok(RespFrame::Integer(v.into()))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<RespFrame, SerdeError> {
+        Ok(RespFrame::Integer(v))
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<RespFrame, SerdeError> {
+        Ok(integer(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<RespFrame, SerdeError> {
+        Ok(RespFrame::Integer(v.into()))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<RespFrame, SerdeError> {
+        Ok(RespFrame::Integer(v.into()))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<RespFrame, SerdeError> {
+        Ok(RespFrame::Integer(v.into()))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<RespFrame, SerdeError> {
+        Ok(integer(v))
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<RespFrame, SerdeError> {
+        Ok(integer(v))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<RespFrame, SerdeError> {
+        Ok(RespFrame::Double(v.into()))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<RespFrame, SerdeError> {
+        Ok(RespFrame::Double(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<RespFrame, SerdeError> {
+        Ok(BulkString::new(v.to_string()).into())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<RespFrame, SerdeError> {
+        Ok(BulkString::new(v).into())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<RespFrame, SerdeError> {
+        Ok(BulkString::new(v).into())
+    }
+
+    fn serialize_none(self) -> Result<RespFrame, SerdeError> {
+        Ok(RespNull.into())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<RespFrame, SerdeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<RespFrame, SerdeError> {
+        Ok(RespNull.into())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<RespFrame, SerdeError> {
+        Ok(RespNull.into())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<RespFrame, SerdeError> {
+        Ok(BulkString::new(variant).into())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<RespFrame, SerdeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<RespFrame, SerdeError> {
+        Ok(variant_map(variant, value.serialize(self)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeVec, SerdeError> {
+        Ok(SerializeVec {
+            frames: Vec::with_capacity(len.unwrap_or_default()),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeVec, SerdeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SerializeVec, SerdeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeTupleVariant, SerdeError> {
+        Ok(SerializeTupleVariant {
+            variant,
+            frames: Vec::with_capacity(len),
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<SerializeMap, SerdeError> {
+        Ok(SerializeMap {
+            map: RespMap::new(),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<SerializeMap, SerdeError> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<SerializeStructVariant, SerdeError> {
+        Ok(SerializeStructVariant {
+            variant,
+            map: RespMap::new(),
+        })
+    }
+}
+
+impl ser::SerializeSeq for SerializeVec {
+    type Ok = RespFrame;
+    type Error = SerdeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        self.frames.push(to_frame(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<RespFrame, SerdeError> {
+        Ok(RespArray::new(self.frames).into())
+    }
+}
+
+impl ser::SerializeTuple for SerializeVec {
+    type Ok = RespFrame;
+    type Error = SerdeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<RespFrame, SerdeError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeVec {
+    type Ok = RespFrame;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<RespFrame, SerdeError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleVariant for SerializeTupleVariant {
+    type Ok = RespFrame;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        self.frames.push(to_frame(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<RespFrame, SerdeError> {
+        Ok(variant_map(self.variant, RespArray::new(self.frames)))
+    }
+}
+
+impl ser::SerializeMap for SerializeMap {
+    type Ok = RespFrame;
+    type Error = SerdeError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), SerdeError> {
+        self.key = Some(to_frame(key)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| SerdeError::Message("map value without a key".to_string()))?;
+        self.map.insert(key, to_frame(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<RespFrame, SerdeError> {
+        Ok(self.map.into())
+    }
+}
+
+impl ser::SerializeStruct for SerializeMap {
+    type Ok = RespFrame;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerdeError> {
+        self.map.insert(BulkString::new(key), to_frame(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<RespFrame, SerdeError> {
+        Ok(self.map.into())
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeStructVariant {
+    type Ok = RespFrame;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerdeError> {
+        self.map.insert(BulkString::new(key), to_frame(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<RespFrame, SerdeError> {
+        Ok(variant_map(self.variant, self.map))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RespEncode;
+    use serde::Serialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize)]
+    struct User {
+        name: String,
+        age: u8,
+        email: Option<String>,
+        tags: Vec<&'static str>,
+        role: Role,
+    }
+
+    #[derive(Serialize)]
+    enum Role {
+        Admin,
+        Guest(u32),
+    }
+
+    #[test]
+    fn test_struct_to_frame() -> anyhow::Result<()> {
+        let user = User {
+            name: "alice".to_string(),
+            age: 30,
+            email: None,
+            tags: vec!["a", "b"],
+            role: Role::Admin,
+        };
+        assert_eq!(
+            to_frame(&user)?.encode(),
+            b"%5\r\n$4\r\nname\r\n$5\r\nalice\r\n$3\r\nage\r\n:30\r\n$5\r\nemail\r\n_\r\n\
+              $4\r\ntags\r\n*2\r\n$1\r\na\r\n$1\r\nb\r\n$4\r\nrole\r\n$5\r\nAdmin\r\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_values_to_frame() -> anyhow::Result<()> {
+        assert_eq!(to_frame(&Role::Guest(7))?, variant_map("Guest", 7));
+        assert_eq!(
+            to_frame(&u64::MAX)?,
+            BigNumber::new(u64::MAX.to_string()).into()
+        );
+        assert_eq!(
+            to_frame(&(1.5, true))?,
+            RespArray::new([1.5.into(), true.into()]).into()
+        );
+
+        let map = BTreeMap::from([(1, "one")]);
+        let mut expected = RespMap::new();
+        expected.insert(1, BulkString::new("one"));
+        assert_eq!(to_frame(&map)?, expected.into());
+        Ok(())
+    }
+}
@@ -1,8 +1,8 @@
 use super::{
-    calc_total_length, parse_length, RespDecode, RespEncode, RespError, RespFrame, BUF_CAP,
+    calc_total_length, parse_length, put_header, RespDecode, RespEncode, RespError, RespFrame,
     CRLF_LEN,
 };
-use bytes::{Buf, BytesMut};
+use bytes::{Buf, BufMut, BytesMut};
 use std::ops::Deref;
 
 #[derive(Debug, Hash, Clone, PartialEq, PartialOrd)]
@@ -16,13 +16,11 @@ impl RespSet {
 }
 
 impl RespEncode for RespSet {
-    fn encode(self) -> Vec<u8> {
-        let mut buf = Vec::with_capacity(BUF_CAP);
-        buf.extend_from_slice(&format!("~{}\r\n", self.len()).into_bytes());
+    fn encode_to<B: BufMut>(self, buf: &mut B) {
+        put_header(buf, b'~', self.len());
         for frame in self.0 {
-            buf.extend_from_slice(&frame.encode());
+            frame.encode_to(buf);
         }
-        buf
     }
 }
 
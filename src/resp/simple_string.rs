@@ -1,5 +1,5 @@
-use super::{extract_simple_frame_data, RespDecode, RespEncode, RespError, CRLF_LEN};
-use bytes::BytesMut;
+use super::{extract_simple_frame_data, put_line, RespDecode, RespEncode, RespError, CRLF_LEN};
+use bytes::{BufMut, BytesMut};
 use std::ops::Deref;
 
 #[derive(Debug, Hash, Clone, PartialEq, Eq, PartialOrd, Ord)]
@@ -12,8 +12,8 @@ impl SimpleString {
 }
 
 impl RespEncode for SimpleString {
-    fn encode(self) -> Vec<u8> {
-        format!("+{}\r\n", self.0).into_bytes()
+    fn encode_to<B: BufMut>(self, buf: &mut B) {
+        put_line(buf, b'+', self.0.as_bytes());
     }
 }
 
@@ -0,0 +1,106 @@
+use super::{
+    expect_blob_len, parse_blob, put_header, RespDecode, RespEncode, RespError, RespLimits, CRLF,
+    CRLF_LEN,
+};
+use bytes::{Buf, BufMut, BytesMut};
+use std::ops::Deref;
+
+/// String with a three bytes format hint, `txt` or `mkd`.
+#[derive(Debug, Hash, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct VerbatimString {
+    pub(crate) format: [u8; 3],
+    pub(crate) data: Vec<u8>,
+}
+
+impl VerbatimString {
+    pub fn new(format: [u8; 3], data: impl Into<Vec<u8>>) -> Self {
+        VerbatimString {
+            format,
+            data: data.into(),
+        }
+    }
+
+    pub fn text(data: impl Into<Vec<u8>>) -> Self {
+        Self::new(*b"txt", data)
+    }
+
+    pub fn format(&self) -> &[u8] {
+        &self.format
+    }
+
+    // `decode` with the length bounded by `limits` instead of the default ones
+    pub(super) fn decode_with_limits(
+        buf: &mut BytesMut,
+        limits: &RespLimits,
+    ) -> Result<Self, RespError> {
+        let (start, len) = parse_blob(buf, Self::PREFIX, limits)?;
+        if len < 4 || buf[start + 3] != b':' {
+            return Err(RespError::InvalidFrame(
+                "verbatim string without format".to_string(),
+            ));
+        }
+
+        buf.advance(start);
+        let data = buf.split_to(len + CRLF_LEN);
+        let format = [data[0], data[1], data[2]];
+        Ok(VerbatimString::new(format, data[4..len].to_vec()))
+    }
+}
+
+impl RespEncode for VerbatimString {
+    fn encode_to<B: BufMut>(self, buf: &mut B) {
+        put_header(buf, b'=', self.data.len() + 4);
+        buf.put_slice(&self.format);
+        buf.put_u8(b':');
+        buf.put_slice(&self.data);
+        buf.put_slice(CRLF);
+    }
+}
+
+impl RespDecode for VerbatimString {
+    const PREFIX: &'static str = "=";
+
+    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
+        Self::decode_with_limits(buf, &RespLimits::DEFAULT)
+    }
+
+    fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
+        expect_blob_len(buf, Self::PREFIX)
+    }
+}
+
+impl Deref for VerbatimString {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        &self.data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RespFrame;
+
+    #[test]
+    fn test_verbatim_string_encode() {
+        let frame: RespFrame = VerbatimString::text("Some string").into();
+        assert_eq!(frame.encode(), b"=15\r\ntxt:Some string\r\n");
+    }
+
+    #[test]
+    fn test_verbatim_string_decode() -> anyhow::Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"=15\r\nmkd:Some string\r\n");
+        let frame = VerbatimString::decode(&mut buf)?;
+        assert_eq!(frame.format(), b"mkd");
+        assert_eq!(&*frame, b"Some string");
+
+        buf.extend_from_slice(b"=3\r\ntxt\r\n");
+        assert!(matches!(
+            VerbatimString::decode(&mut buf),
+            Err(RespError::InvalidFrame(_))
+        ));
+        Ok(())
+    }
+}
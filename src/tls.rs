@@ -0,0 +1,209 @@
+use std::{path::Path, sync::Arc};
+use thiserror::Error;
+use tokio_rustls::{
+    rustls::{
+        pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer},
+        server::{VerifierBuilderError, WebPkiClientVerifier},
+        RootCertStore, ServerConfig,
+    },
+    TlsAcceptor,
+};
+
+use crate::config::Config;
+
+#[derive(Debug, Error)]
+pub enum TlsError {
+    #[error("tls-cert-file and tls-key-file must be set")]
+    NoCertificate,
+    #[error("tls-ca-cert-file must be set to authenticate clients")]
+    NoCaCertificate,
+    #[error("Failed to load {0}: {1}")]
+    Pem(String, tokio_rustls::rustls::pki_types::pem::Error),
+    #[error("Invalid CA certificate: {0}")]
+    CaCertificate(#[from] VerifierBuilderError),
+    #[error("Invalid certificate or key: {0}")]
+    Rustls(#[from] tokio_rustls::rustls::Error),
+}
+
+/// The acceptor for TLS connections with the certificates and client
+/// authentication of `config`, the files are read on every call.
+pub fn acceptor(config: &Config) -> Result<TlsAcceptor, TlsError> {
+    let (Some(cert_file), Some(key_file)) = (&config.tls_cert_file, &config.tls_key_file) else {
+        return Err(TlsError::NoCertificate);
+    };
+    let certs = load_certs(cert_file)?;
+    let key = PrivateKeyDer::from_pem_file(key_file)
+        .map_err(|e| TlsError::Pem(key_file.display().to_string(), e))?;
+
+    let builder = ServerConfig::builder();
+    let builder = match config.tls_auth_clients.as_str() {
+        "no" => builder.with_no_client_auth(),
+        auth => {
+            let ca_file = config
+                .tls_ca_cert_file
+                .as_ref()
+                .ok_or(TlsError::NoCaCertificate)?;
+            let mut roots = RootCertStore::empty();
+            for cert in load_certs(ca_file)? {
+                roots.add(cert)?;
+            }
+            let verifier = WebPkiClientVerifier::builder(Arc::new(roots));
+            let verifier = match auth {
+                "optional" => verifier.allow_unauthenticated().build()?,
+                _ => verifier.build()?,
+            };
+            builder.with_client_cert_verifier(verifier)
+        }
+    };
+    let server_config = builder.with_single_cert(certs, key)?;
+    Ok(TlsAcceptor::from(Arc::new(server_config)))
+}
+
+fn load_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>, TlsError> {
+    let name = || path.display().to_string();
+    let certs = CertificateDer::pem_file_iter(path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| TlsError::Pem(name(), e))?;
+    if certs.is_empty() {
+        return Err(TlsError::Pem(
+            name(),
+            tokio_rustls::rustls::pki_types::pem::Error::NoItemsFound,
+        ));
+    }
+    Ok(certs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{backend::Backend, network::stream_handler};
+    use rcgen::{CertificateParams, CertifiedIssuer, KeyPair};
+    use std::{fs, path::PathBuf};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_rustls::{
+        rustls::{pki_types::ServerName, ClientConfig},
+        TlsConnector,
+    };
+
+    struct Pki {
+        dir: PathBuf,
+        ca: CertifiedIssuer<'static, KeyPair>,
+    }
+
+    impl Pki {
+        fn new(name: &str) -> anyhow::Result<Self> {
+            let dir =
+                std::env::temp_dir().join(format!("simple-redis-{}-{}", name, std::process::id()));
+            fs::create_dir_all(&dir)?;
+            let mut params = CertificateParams::new(vec![])?;
+            params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Unconstrained);
+            let ca = CertifiedIssuer::self_signed(params, KeyPair::generate()?)?;
+            fs::write(dir.join("ca.crt"), ca.pem())?;
+            Ok(Pki { dir, ca })
+        }
+
+        // a certificate and its key signed by the CA, returns their paths
+        fn issue(&self, name: &str) -> anyhow::Result<(PathBuf, PathBuf)> {
+            let key = KeyPair::generate()?;
+            let cert =
+                CertificateParams::new(vec!["localhost".to_string()])?.signed_by(&key, &self.ca)?;
+            let (cert_file, key_file) = (
+                self.dir.join(format!("{name}.crt")),
+                self.dir.join(format!("{name}.key")),
+            );
+            fs::write(&cert_file, cert.pem())?;
+            fs::write(&key_file, key.serialize_pem())?;
+            Ok((cert_file, key_file))
+        }
+
+        fn config(&self, auth: &str) -> anyhow::Result<Config> {
+            let (cert, key) = self.issue("server")?;
+            Ok(Config {
+                tls_cert_file: Some(cert),
+                tls_key_file: Some(key),
+                tls_ca_cert_file: Some(self.dir.join("ca.crt")),
+                tls_auth_clients: auth.to_string(),
+                ..Config::default()
+            })
+        }
+
+        fn connector(&self, client_cert: bool) -> anyhow::Result<TlsConnector> {
+            let mut roots = RootCertStore::empty();
+            roots.add(self.ca.der().clone())?;
+            let builder = ClientConfig::builder().with_root_certificates(roots);
+            let config = if client_cert {
+                let (cert, key) = self.issue("client")?;
+                builder
+                    .with_client_auth_cert(load_certs(&cert)?, PrivateKeyDer::from_pem_file(key)?)?
+            } else {
+                builder.with_no_client_auth()
+            };
+            Ok(TlsConnector::from(Arc::new(config)))
+        }
+    }
+
+    impl Drop for Pki {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    // PING through a TLS session, an error if the handshake fails
+    async fn ping(acceptor: TlsAcceptor, connector: TlsConnector) -> anyhow::Result<Vec<u8>> {
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let server = tokio::spawn(async move {
+            let stream = acceptor.accept(server).await?;
+            stream_handler(stream, Backend::new().connect()).await
+        });
+        let mut stream = connector
+            .connect(ServerName::try_from("localhost")?, client)
+            .await?;
+        stream.write_all(b"PING\r\n").await?;
+        stream.shutdown().await?;
+        let mut reply = Vec::new();
+        stream.read_to_end(&mut reply).await?;
+        server.await??;
+        Ok(reply)
+    }
+
+    #[tokio::test]
+    async fn test_tls_ping() -> anyhow::Result<()> {
+        let pki = Pki::new("tls-ping")?;
+        let acceptor = acceptor(&pki.config("no")?)?;
+        assert_eq!(ping(acceptor, pki.connector(false)?).await?, b"+PONG\r\n");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_tls_client_auth() -> anyhow::Result<()> {
+        let pki = Pki::new("tls-auth")?;
+        let required = acceptor(&pki.config("yes")?)?;
+        assert_eq!(
+            ping(required.clone(), pki.connector(true)?).await?,
+            b"+PONG\r\n"
+        );
+        assert!(ping(required, pki.connector(false)?).await.is_err());
+
+        let optional = acceptor(&pki.config("optional")?)?;
+        assert_eq!(ping(optional, pki.connector(false)?).await?, b"+PONG\r\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_acceptor_errors() -> anyhow::Result<()> {
+        let pki = Pki::new("tls-errors")?;
+        assert!(matches!(
+            acceptor(&Config::default()),
+            Err(TlsError::NoCertificate)
+        ));
+
+        let mut config = pki.config("yes")?;
+        config.tls_ca_cert_file = None;
+        assert!(matches!(acceptor(&config), Err(TlsError::NoCaCertificate)));
+
+        let mut config = pki.config("no")?;
+        config.tls_key_file = config.tls_cert_file.clone();
+        assert!(matches!(acceptor(&config), Err(TlsError::Pem(..))));
+        Ok(())
+    }
+}